@@ -63,7 +63,8 @@ fn init(
             );
         }
 
-        let mut env = Env::new(&config, None).expect("Failed to initialize environment.");
+        let mut env =
+            Env::new(&config, None, Vec::new()).expect("Failed to initialize environment.");
 
         if !env.genesis(&config) {
             info!("State already exists. Not loading genesis.");
@@ -3,6 +3,9 @@
 
 use crate::genesis::Genesis;
 
+/// The number of past blocks whose state is kept for historical queries by default.
+pub const DEFAULT_HISTORY_RETENTION: u64 = 128;
+
 #[derive(Serialize, Deserialize, Default)]
 pub enum Mode {
     #[default]
@@ -19,6 +22,13 @@ pub struct Config {
     pub storage: StorageConfig,
     pub db_path: Option<ResolvedPathBuf>,
     pub db_options: Option<ResolvedPathBuf>,
+    /// The number of past blocks whose state is kept in memory for historical queries. Setting
+    /// this to zero disables historical queries.
+    ///
+    /// The history is not persisted, after a restart only the blocks executed since then can be
+    /// queried.
+    #[serde(default = "default_history_retention")]
+    pub history_retention: u64,
 }
 
 impl Config {
@@ -30,6 +40,7 @@ pub fn test() -> Self {
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         }
     }
 }
@@ -47,10 +58,15 @@ fn default() -> Self {
                     .expect("Failed to resolve path"),
             ),
             db_options: None,
+            history_retention: default_history_retention(),
         }
     }
 }
 
+fn default_history_retention() -> u64 {
+    DEFAULT_HISTORY_RETENTION
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum StorageConfig {
     InMemory,
@@ -2,11 +2,17 @@
 use std::time::{Duration, SystemTime};
 
 use affair::AsyncWorker as WorkerTrait;
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use async_trait::async_trait;
 use atomo::{Atomo, AtomoBuilder, DefaultSerdeBackend, QueryPerm, UpdatePerm};
-use atomo_rocks::{Cache as RocksCache, Env as RocksEnv, Options};
-use fleek_crypto::{ClientPublicKey, ConsensusPublicKey, EthAddress, NodePublicKey};
+use atomo_rocks::{deserialize_delta, Cache as RocksCache, Env as RocksEnv, Options};
+use fleek_crypto::{
+    ClientPublicKey,
+    ConsensusPublicKey,
+    ConsensusSignature,
+    EthAddress,
+    NodePublicKey,
+};
 use hp_fixed::unsigned::HpUfixed;
 use lightning_interfaces::infu_collection::Collection;
 use lightning_interfaces::types::{
@@ -16,20 +22,31 @@
     Committee,
     CommodityTypes,
     CompressionAlgorithm,
+    Delegation,
+    DepositEvent,
+    DepositId,
     Epoch,
+    EpochRewards,
     ExecutionData,
     Metadata,
     NodeIndex,
     NodeInfo,
+    NodeInfoUpdate,
+    NodeRewards,
     NodeServed,
+    PendingWithdrawal,
+    Proposal,
+    ProposalId,
     ProtocolParams,
     ReportedReputationMeasurements,
     Service,
     ServiceId,
     ServiceRevenue,
     TotalServed,
+    TransactionReceipt,
     TransactionResponse,
     Value,
+    VestingSchedule,
 };
 use lightning_interfaces::{BlockStoreInterface, IncrementalPutInterface};
 use log::warn;
@@ -41,12 +58,24 @@
 use crate::storage::{AtomoStorage, AtomoStorageBuilder};
 use crate::table::StateTables;
 
+/// The number of epochs between two full checkpoints of the state. For the epochs in between
+/// only a delta against the previous checkpoint is stored.
+pub const CHECKPOINT_INTERVAL: Epoch = 10;
+
 pub struct Env<P> {
     inner: Atomo<P, AtomoStorage>,
 }
 
 impl Env<UpdatePerm> {
-    pub fn new(config: &Config, checkpoint: Option<([u8; 32], Vec<u8>)>) -> Result<Self> {
+    /// Opens the environment, building the state from the given checkpoint and the deltas that
+    /// follow it if there is one. Fails if the state built from the deltas does not have the
+    /// state root promised by the last one.
+    pub fn new(
+        config: &Config,
+        checkpoint: Option<([u8; 32], Vec<u8>)>,
+        deltas: Vec<([u8; 32], Vec<u8>)>,
+    ) -> Result<Self> {
+        let mut promised_root = None;
         let storage = match config.storage {
             StorageConfig::RocksDb => {
                 let db_path = config
@@ -68,14 +97,20 @@ impl Env<UpdatePerm> {
                 };
                 db_options.create_if_missing(true);
                 db_options.create_missing_column_families(true);
-                match checkpoint {
+                let builder = match checkpoint {
                     Some((hash, checkpoint)) => AtomoStorageBuilder::new(Some(db_path.as_path()))
                         .with_options(db_options)
                         .from_checkpoint(hash, checkpoint),
                     None => {
                         AtomoStorageBuilder::new(Some(db_path.as_path())).with_options(db_options)
                     },
+                };
+                // The deltas promise the state root of the state they lead to, which is checked
+                // once the state tree is built.
+                if let Some((_, delta)) = deltas.last() {
+                    promised_root = Some(deserialize_delta(delta)?.result);
                 }
+                builder.with_checkpoint_deltas(deltas).with_delta_tracking()
             },
             StorageConfig::InMemory => AtomoStorageBuilder::new::<&Path>(None),
         };
@@ -83,8 +118,11 @@ impl Env<UpdatePerm> {
         let mut atomo = AtomoBuilder::<AtomoStorageBuilder, DefaultSerdeBackend>::new(storage);
         atomo = atomo
             .with_table::<Metadata, Value>("metadata")
+            .with_table::<Metadata, Value>("local_metadata")
             .with_table::<EthAddress, AccountInfo>("account")
+            .with_table::<(EthAddress, u64), VestingSchedule>("vesting_schedules")
             .with_table::<ClientPublicKey, EthAddress>("client_keys")
+            .with_table::<ClientPublicKey, HpUfixed<6>>("client_balances")
             .with_table::<NodeIndex, NodeInfo>("node")
             .with_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index")
             .with_table::<NodePublicKey, NodeIndex>("pub_key_to_index")
@@ -98,13 +136,46 @@ impl Env<UpdatePerm> {
             .with_table::<NodeIndex, NodeServed>("last_epoch_served")
             .with_table::<Epoch, TotalServed>("total_served")
             .with_table::<CommodityTypes, HpUfixed<6>>("commodity_prices")
+            .with_table::<(ServiceId, CommodityTypes), HpUfixed<6>>("service_prices")
+            .with_table::<(ServiceId, CommodityTypes), HpUfixed<6>>("service_price_updates")
             .with_table::<ServiceId, ServiceRevenue>("service_revenue")
+            .with_table::<(NodeIndex, ServiceId), ServiceRevenue>("node_service_revenue")
+            .with_table::<Epoch, EpochRewards>("epoch_rewards")
+            .with_table::<(Epoch, NodeIndex), NodeRewards>("node_rewards")
+            .with_table::<u64, PendingWithdrawal>("pending_withdrawals")
+            .with_table::<DepositId, DepositEvent>("processed_deposits")
+            .with_table::<(NodeIndex, [u8; 32]), HpUfixed<18>>("slashed_misbehaviors")
+            .with_table::<(NodeIndex, EthAddress), Delegation>("delegations")
+            .with_table::<NodeIndex, u8>("commissions")
+            .with_table::<NodeIndex, NodeInfoUpdate>("node_info_updates")
+            .with_table::<NodeIndex, ConsensusSignature>("randomness_shares")
+            .with_table::<ProposalId, Proposal>("proposals")
+            .with_table::<(ProposalId, EthAddress), bool>("proposal_votes")
+            .with_table::<(NodeIndex, Epoch), HpUfixed<18>>("node_stake_snapshots")
+            .with_table::<[u8; 32], TransactionReceipt>("transaction_receipts")
+            .with_table::<[u8; 32], Vec<[u8; 32]>>("block_transactions")
+            .with_table::<u64, [u8; 32]>("block_hashes")
+            .with_table::<Epoch, u64>("epoch_end_blocks")
+            .with_table::<(ClientPublicKey, NodeIndex), u64>("delivery_ack_sequences")
             .enable_iter("current_epoch_served")
             .enable_iter("rep_measurements")
             .enable_iter("rep_scores")
             .enable_iter("latencies")
             .enable_iter("node")
-            .enable_iter("service_revenue");
+            .enable_iter("service_revenue")
+            .enable_iter("node_service_revenue")
+            .enable_iter("pending_withdrawals")
+            .enable_iter("delegations")
+            .enable_iter("node_stake_snapshots")
+            .enable_iter("node_info_updates")
+            .enable_iter("service_prices")
+            .enable_iter("service_price_updates")
+            .enable_iter("vesting_schedules")
+            .enable_iter("randomness_shares")
+            .with_history(config.history_retention as usize)
+            .with_state_tree()
+            // The checkpoint hashes depend on the writes to the local blockstore
+            .without_state_tree("local_metadata");
 
         #[cfg(debug_assertions)]
         {
@@ -113,9 +184,14 @@ impl Env<UpdatePerm> {
                 .enable_iter("pub_key_to_index");
         }
 
-        Ok(Self {
-            inner: atomo.build()?,
-        })
+        let inner = atomo.build()?;
+        if let Some(root) = promised_root {
+            ensure!(
+                inner.state_root() == root,
+                "The state built from the deltas does not have the promised state root"
+            );
+        }
+        Ok(Self { inner })
     }
 
     #[autometrics::autometrics]
@@ -124,7 +200,7 @@ async fn run<F, P>(&mut self, block: Block, get_putter: F) -> BlockExecutionResp
         F: FnOnce() -> P,
         P: IncrementalPutInterface,
     {
-        let response = self.inner.run(move |ctx| {
+        let (mut response, block_number) = self.inner.run(move |ctx| {
             // Create the app/execution enviroment
             let backend = StateTables {
                 table_selector: ctx,
@@ -137,12 +213,32 @@ async fn run<F, P>(&mut self, block: Block, get_putter: F) -> BlockExecutionResp
                 change_epoch: false,
                 node_registry_delta: Vec::new(),
                 txn_receipts: Vec::with_capacity(block.transactions.len()),
+                state_root: Default::default(),
             };
 
+            let mut receipts = Vec::with_capacity(block.transactions.len());
+
             // Execute each transaction and add the results to the block response
-            for txn in &block.transactions {
+            for (index, txn) in block.transactions.iter().enumerate() {
                 let receipt = match app.verify_transaction(txn) {
-                    Ok(_) => app.execute_txn(txn.clone()),
+                    Ok(_) => {
+                        let receipt = app.execute_txn(txn.clone());
+                        let events = app.take_events();
+                        // Only transactions that passed the verification are kept, the others
+                        // were not signed by the sender or can be replays of executed ones.
+                        receipts.push(TransactionReceipt {
+                            block_hash: block.digest,
+                            transaction_index: index as u32,
+                            transaction_hash: txn.hash(),
+                            from: txn.sender,
+                            response: receipt.clone(),
+                            events: match receipt {
+                                TransactionResponse::Success(_) => events,
+                                TransactionResponse::Revert(_) => Vec::new(),
+                            },
+                        });
+                        receipt
+                    },
                     Err(err) => TransactionResponse::Revert(err),
                 };
 
@@ -157,15 +253,41 @@ async fn run<F, P>(&mut self, block: Block, get_putter: F) -> BlockExecutionResp
             }
             // Set the last executed block hash
             app.set_last_block(block.digest);
+            let block_number = app.increment_block_number();
+            // Store the receipts so clients can look up their transactions later
+            app.store_receipts(block_number, block.digest, receipts);
+            if response.change_epoch {
+                app.set_epoch_end_block(block_number);
+            }
 
             // Return the response
-            response
+            (response, block_number)
         });
+        if response.change_epoch {
+            // Commit the root the new epoch starts with to the state
+            self.update_epoch_state_root(self.inner.state_root());
+        }
+        // Keep the state after this block around for historical queries
+        self.inner.save_version(block_number);
+        response.state_root = self.inner.state_root();
 
         if response.change_epoch {
+            // The state is stored in full after the first epoch and then every
+            // `CHECKPOINT_INTERVAL` epochs, the epochs in between only store their changes.
+            let full_checkpoint = self.get_epoch() % CHECKPOINT_INTERVAL == 1;
             let storage = self.inner.get_storage_backend_unsafe();
+            let checkpoint = if full_checkpoint {
+                storage.serialize()
+            } else {
+                storage
+                    .serialize_delta(response.state_root)
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to serialize the state delta: {e:?}");
+                        None
+                    })
+            };
             // This will return `None` only if the InMemory backend is used.
-            if let Some(checkpoint) = storage.serialize() {
+            if let Some(checkpoint) = checkpoint {
                 let mut blockstore_put = get_putter();
                 if blockstore_put
                     .write(checkpoint.as_slice(), CompressionAlgorithm::Uncompressed)
@@ -173,7 +295,11 @@ async fn run<F, P>(&mut self, block: Block, get_putter: F) -> BlockExecutionResp
                 {
                     if let Ok(state_hash) = blockstore_put.finalize().await {
                         // Only temporary: write the checkpoint to disk directly.
-                        self.update_last_epoch_hash(state_hash);
+                        if full_checkpoint {
+                            self.update_last_epoch_hash(state_hash);
+                        } else {
+                            self.add_last_epoch_delta_hash(state_hash);
+                        }
                     } else {
                         warn!("Failed to finalize writing checkpoint to blockstore");
                     }
@@ -202,7 +328,7 @@ pub fn query_runner(&self) -> QueryRunner {
     /// Will return true if database was empty and genesis needed to be loaded or false if there was
     /// already state loaded and it didnt load genesis
     pub fn genesis(&mut self, config: &Config) -> bool {
-        self.inner.run(|ctx| {
+        let loaded = self.inner.run(|ctx| {
             let mut metadata_table = ctx.get_table::<Metadata, Value>("metadata");
 
             if metadata_table.get(Metadata::Epoch).is_some() {
@@ -240,9 +366,12 @@ pub fn genesis(&mut self, config: &Config) -> bool {
                 ctx.get_table::<(NodeIndex, NodeIndex), Duration>("latencies");
             let mut consensus_key_to_index_table = ctx.get_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index");
             let mut pub_key_to_index_table = ctx.get_table::<NodePublicKey, NodeIndex>("pub_key_to_index");
+            let mut vesting_schedules_table = ctx.get_table::<(EthAddress, u64), VestingSchedule>("vesting_schedules");
+            let mut local_metadata_table = ctx.get_table::<Metadata, Value>("local_metadata");
 
             // TODO(matthias): should we hash the genesis state instead?
-            metadata_table.insert(Metadata::LastEpochHash, Value::Hash([0; 32]));
+            local_metadata_table.insert(Metadata::LastEpochHash, Value::Hash([0; 32]));
+            metadata_table.insert(Metadata::ChainId, Value::ChainId(genesis.chain_id));
 
             metadata_table.insert(
                 Metadata::ProtocolFundAddress,
@@ -251,10 +380,14 @@ pub fn genesis(&mut self, config: &Config) -> bool {
 
             metadata_table.insert(Metadata::GovernanceAddress,
                 Value::AccountPublicKey(genesis.governance_address));
+            metadata_table.insert(Metadata::BridgeValidators,
+                Value::BridgeValidators(genesis.bridge_validators.clone()));
             let governance_account = AccountInfo {
                 flk_balance: 0u64.into(),
                 stables_balance: 0u64.into(),
                 bandwidth_balance: 0u64.into(),
+                vested_flk: 0u64.into(),
+                unvested_flk: 0u64.into(),
                 nonce: 0,
             };
             account_table.insert(genesis.governance_address,  governance_account);
@@ -270,6 +403,26 @@ pub fn genesis(&mut self, config: &Config) -> bool {
             );
             param_table.insert(ProtocolParams::MaxBoost, genesis.max_boost as u128);
             param_table.insert(ProtocolParams::MaxStakeLockTime, genesis.max_lock_time as u128);
+            param_table.insert(ProtocolParams::SlashShare, genesis.slash_share as u128);
+            param_table.insert(
+                ProtocolParams::GovernanceQuorum,
+                genesis.governance_quorum as u128,
+            );
+            param_table.insert(
+                ProtocolParams::GovernanceThreshold,
+                genesis.governance_threshold as u128,
+            );
+            param_table.insert(ProtocolParams::VotingPeriod, genesis.voting_period as u128);
+            param_table.insert(
+                ProtocolParams::JailReputationThreshold,
+                genesis.jail_reputation_threshold as u128,
+            );
+            param_table.insert(ProtocolParams::JailEpochs, genesis.jail_epochs as u128);
+            param_table.insert(ProtocolParams::UnjailCooldown, genesis.unjail_cooldown as u128);
+            param_table.insert(
+                ProtocolParams::ReceiptRetention,
+                genesis.receipt_retention as u128,
+            );
             param_table.insert(ProtocolParams::EpochTime, genesis.epoch_time as u128);
             param_table.insert(ProtocolParams::MinimumNodeStake, genesis.min_stake as u128);
             param_table.insert(ProtocolParams::LockTime, genesis.lock_time as u128);
@@ -348,15 +501,43 @@ pub fn genesis(&mut self, config: &Config) -> bool {
                 )
             }
 
+            let mut next_vesting_id = 0;
             for account in genesis.account {
-                let info = AccountInfo {
+                let mut info = AccountInfo {
                     flk_balance: account.flk_balance.into(),
                     stables_balance: account.stables_balance.into(),
                     bandwidth_balance: account.bandwidth_balance.into(),
+                    vested_flk: 0u64.into(),
+                    unvested_flk: 0u64.into(),
                     nonce: 0,
                 };
+                // The vesting FLK is part of the balance of the account, genesis schedules start
+                // at the first epoch
+                if let Some(vesting) = account.vesting {
+                    assert!(
+                        vesting.amount <= account.flk_balance,
+                        "Vesting amount of an account can not exceed its FLK balance."
+                    );
+                    let schedule = VestingSchedule {
+                        amount: vesting.amount.into(),
+                        start: 0,
+                        cliff: vesting.cliff,
+                        duration: vesting.duration,
+                    };
+                    info.vested_flk = schedule.vested_at(0);
+                    info.unvested_flk = &schedule.amount - &info.vested_flk;
+                    if info.unvested_flk > HpUfixed::zero() {
+                        vesting_schedules_table
+                            .insert((account.public_key, next_vesting_id), schedule);
+                        next_vesting_id += 1;
+                    }
+                }
                 account_table.insert(account.public_key, info);
             }
+            metadata_table.insert(
+                Metadata::NextVestingId,
+                Value::NextVestingId(next_vesting_id),
+            );
 
             // add commodity prices
             for commodity_price in genesis.commodity_prices {
@@ -387,7 +568,24 @@ pub fn genesis(&mut self, config: &Config) -> bool {
             }
 
         metadata_table.insert(Metadata::Epoch, Value::Epoch(0));
+        metadata_table.insert(Metadata::BlockNumber, Value::BlockNumber(0));
         true
+        });
+        if loaded {
+            self.update_epoch_state_root(self.inner.state_root());
+            self.inner.save_version(0);
+        }
+        loaded
+    }
+
+    // Should only be called after executing the block that changed the epoch or loading genesis
+    fn update_epoch_state_root(&mut self, state_root: [u8; 32]) {
+        self.inner.run(move |ctx| {
+            let backend = StateTables {
+                table_selector: ctx,
+            };
+            let app = State::new(backend);
+            app.set_epoch_state_root(state_root);
         })
     }
 
@@ -401,11 +599,34 @@ pub fn genesis(&mut self, config: &Config) -> bool {
             app.set_last_epoch_hash(state_hash);
         })
     }
+
+    // Should only be called after saving or loading an epoch delta
+    pub fn add_last_epoch_delta_hash(&mut self, delta_hash: [u8; 32]) {
+        self.inner.run(move |ctx| {
+            let backend = StateTables {
+                table_selector: ctx,
+            };
+            let app = State::new(backend);
+            app.add_last_epoch_delta_hash(delta_hash);
+        })
+    }
+
+    fn get_epoch(&self) -> Epoch {
+        self.inner.query().run(|ctx| {
+            match ctx
+                .get_table::<Metadata, Value>("metadata")
+                .get(Metadata::Epoch)
+            {
+                Some(Value::Epoch(epoch)) => epoch,
+                _ => 0,
+            }
+        })
+    }
 }
 
 impl Default for Env<UpdatePerm> {
     fn default() -> Self {
-        Self::new(&Config::default(), None).unwrap()
+        Self::new(&Config::default(), None, Vec::new()).unwrap()
     }
 }
 
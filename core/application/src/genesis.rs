@@ -4,6 +4,7 @@
 use anyhow::{Context, Result};
 use fleek_crypto::{ConsensusPublicKey, EthAddress, NodePublicKey};
 use lightning_interfaces::types::{
+    ChainId,
     CommodityTypes,
     Epoch,
     NodeInfo,
@@ -16,6 +17,7 @@
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Genesis {
+    pub chain_id: ChainId,
     pub epoch_start: u64,
     pub epoch_time: u64,
     pub committee_size: u64,
@@ -29,6 +31,14 @@ pub struct Genesis {
     pub consumer_rebate: u64,
     pub max_boost: u16,
     pub max_lock_time: u64,
+    pub slash_share: u16,
+    pub governance_quorum: u16,
+    pub governance_threshold: u16,
+    pub voting_period: Epoch,
+    pub jail_reputation_threshold: u8,
+    pub jail_epochs: u64,
+    pub unjail_cooldown: Epoch,
+    pub receipt_retention: u64,
     pub node_info: Vec<GenesisNode>,
     pub service: Vec<GenesisService>,
     pub account: Vec<GenesisAccount>,
@@ -36,6 +46,7 @@ pub struct Genesis {
     pub supply_at_genesis: u64,
     pub protocol_fund_address: EthAddress,
     pub governance_address: EthAddress,
+    pub bridge_validators: Vec<EthAddress>,
     pub total_served: HashMap<Epoch, TotalServed>,
     pub latencies: Option<Vec<GenesisLatency>>,
 }
@@ -46,6 +57,15 @@ pub struct GenesisAccount {
     pub flk_balance: u64,
     pub stables_balance: u64,
     pub bandwidth_balance: u64,
+    /// The part of the FLK balance that vests over time, starting at genesis.
+    pub vesting: Option<GenesisVesting>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GenesisVesting {
+    pub amount: u64,
+    pub cliff: Epoch,
+    pub duration: Epoch,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -109,6 +129,9 @@ fn from(value: &GenesisNode) -> Self {
             stake: value.stake.clone(),
             nonce: 0,
             ports: value.ports.clone(),
+            jailed_since: None,
+            low_reputation_epochs: 0,
+            missed_randomness_shares: 0,
         }
     }
 }
@@ -1,31 +1,49 @@
+use std::any::Any;
 use std::collections::HashMap;
+use std::hash::Hash;
 use std::time::Duration;
 
-use atomo::{Atomo, QueryPerm, ResolvedTableReference};
+use atomo::{Atomo, DefaultSerdeBackend, QueryPerm, ResolvedTableReference, TableSelector};
 use autometrics::autometrics;
 use fleek_crypto::{ClientPublicKey, EthAddress, NodePublicKey};
 use hp_fixed::unsigned::HpUfixed;
 use lightning_interfaces::application::SyncQueryRunnerInterface;
 use lightning_interfaces::types::{
     AccountInfo,
+    ChainId,
     Committee,
     CommodityTypes,
+    Delegation,
     Epoch,
     EpochInfo,
+    EpochRewards,
     Metadata,
     NodeIndex,
     NodeInfo,
+    NodeRewards,
     NodeServed,
+    PendingWithdrawal,
+    Proposal,
+    ProposalId,
     ProtocolParams,
     ReportedReputationMeasurements,
+    RewardsBreakdown,
     Service,
     ServiceId,
     ServiceRevenue,
+    StateChange,
+    StateDiff,
+    StateProof,
     TotalServed,
+    TransactionReceipt,
     TransactionResponse,
+    TransactionSimulation,
     UpdateRequest,
     Value,
+    VestingSchedule,
 };
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use crate::state::State;
 use crate::storage::AtomoStorage;
@@ -35,8 +53,10 @@
 pub struct QueryRunner {
     inner: Atomo<QueryPerm, AtomoStorage>,
     metadata_table: ResolvedTableReference<Metadata, Value>,
+    local_metadata_table: ResolvedTableReference<Metadata, Value>,
     account_table: ResolvedTableReference<EthAddress, AccountInfo>,
-    client_table: ResolvedTableReference<ClientPublicKey, EthAddress>,
+    vesting_schedules: ResolvedTableReference<(EthAddress, u64), VestingSchedule>,
+    client_balances: ResolvedTableReference<ClientPublicKey, HpUfixed<6>>,
     node_table: ResolvedTableReference<NodeIndex, NodeInfo>,
     pub_key_to_index: ResolvedTableReference<NodePublicKey, NodeIndex>,
     committee_table: ResolvedTableReference<Epoch, Committee>,
@@ -49,15 +69,27 @@ pub struct QueryRunner {
     _last_epoch_served: ResolvedTableReference<NodeIndex, NodeServed>,
     total_served_table: ResolvedTableReference<Epoch, TotalServed>,
     _service_revenue: ResolvedTableReference<ServiceId, ServiceRevenue>,
-    _commodity_price: ResolvedTableReference<CommodityTypes, HpUfixed<6>>,
+    commodity_prices: ResolvedTableReference<CommodityTypes, HpUfixed<6>>,
+    service_prices: ResolvedTableReference<(ServiceId, CommodityTypes), HpUfixed<6>>,
+    pending_withdrawals: ResolvedTableReference<u64, PendingWithdrawal>,
+    delegations: ResolvedTableReference<(NodeIndex, EthAddress), Delegation>,
+    proposals: ResolvedTableReference<ProposalId, Proposal>,
+    transaction_receipts: ResolvedTableReference<[u8; 32], TransactionReceipt>,
+    block_transactions: ResolvedTableReference<[u8; 32], Vec<[u8; 32]>>,
+    epoch_end_blocks: ResolvedTableReference<Epoch, u64>,
+    epoch_rewards: ResolvedTableReference<Epoch, EpochRewards>,
+    node_rewards: ResolvedTableReference<(Epoch, NodeIndex), NodeRewards>,
 }
 
 impl QueryRunner {
     pub fn init(atomo: Atomo<QueryPerm, AtomoStorage>) -> Self {
         Self {
             metadata_table: atomo.resolve::<Metadata, Value>("metadata"),
+            local_metadata_table: atomo.resolve::<Metadata, Value>("local_metadata"),
             account_table: atomo.resolve::<EthAddress, AccountInfo>("account"),
-            client_table: atomo.resolve::<ClientPublicKey, EthAddress>("client_keys"),
+            vesting_schedules: atomo
+                .resolve::<(EthAddress, u64), VestingSchedule>("vesting_schedules"),
+            client_balances: atomo.resolve::<ClientPublicKey, HpUfixed<6>>("client_balances"),
             node_table: atomo.resolve::<NodeIndex, NodeInfo>("node"),
             pub_key_to_index: atomo.resolve::<NodePublicKey, NodeIndex>("pub_key_to_index"),
             committee_table: atomo.resolve::<Epoch, Committee>("committee"),
@@ -70,8 +102,19 @@ pub fn init(atomo: Atomo<QueryPerm, AtomoStorage>) -> Self {
             rep_scores: atomo.resolve::<NodeIndex, u8>("rep_scores"),
             _last_epoch_served: atomo.resolve::<NodeIndex, NodeServed>("last_epoch_served"),
             total_served_table: atomo.resolve::<Epoch, TotalServed>("total_served"),
-            _commodity_price: atomo.resolve::<CommodityTypes, HpUfixed<6>>("commodity_prices"),
+            commodity_prices: atomo.resolve::<CommodityTypes, HpUfixed<6>>("commodity_prices"),
+            service_prices: atomo
+                .resolve::<(ServiceId, CommodityTypes), HpUfixed<6>>("service_prices"),
             _service_revenue: atomo.resolve::<ServiceId, ServiceRevenue>("service_revenue"),
+            pending_withdrawals: atomo.resolve::<u64, PendingWithdrawal>("pending_withdrawals"),
+            delegations: atomo.resolve::<(NodeIndex, EthAddress), Delegation>("delegations"),
+            proposals: atomo.resolve::<ProposalId, Proposal>("proposals"),
+            transaction_receipts: atomo
+                .resolve::<[u8; 32], TransactionReceipt>("transaction_receipts"),
+            block_transactions: atomo.resolve::<[u8; 32], Vec<[u8; 32]>>("block_transactions"),
+            epoch_end_blocks: atomo.resolve::<Epoch, u64>("epoch_end_blocks"),
+            epoch_rewards: atomo.resolve::<Epoch, EpochRewards>("epoch_rewards"),
+            node_rewards: atomo.resolve::<(Epoch, NodeIndex), NodeRewards>("node_rewards"),
             inner: atomo,
         }
     }
@@ -91,6 +134,27 @@ fn get_node_info_with_pub_key<F: FnOnce(NodeInfo) -> T, T>(
     }
 }
 
+/// Returns the changes that were made to the given table in the current run.
+fn table_changes<K, V>(
+    ctx: &TableSelector<AtomoStorage, DefaultSerdeBackend>,
+    name: &str,
+) -> Vec<StateChange<K, V>>
+where
+    K: Hash + Eq + Serialize + DeserializeOwned + Any,
+    V: Serialize + DeserializeOwned + Any,
+{
+    let table = ctx.get_table::<K, V>(name);
+    table
+        .changes()
+        .into_iter()
+        .map(|(key, after)| StateChange {
+            before: table.get_committed(&key),
+            key,
+            after,
+        })
+        .collect()
+}
+
 impl SyncQueryRunnerInterface for QueryRunner {
     fn get_account_balance(&self, account: &EthAddress) -> u128 {
         self.inner.run(|ctx| {
@@ -101,17 +165,12 @@ fn get_account_balance(&self, account: &EthAddress) -> u128 {
                 .unwrap_or(0)
         })
     }
-    fn get_client_balance(&self, client: &ClientPublicKey) -> u128 {
+    fn get_client_balance(&self, client: &ClientPublicKey) -> HpUfixed<6> {
         self.inner.run(|ctx| {
-            let client_table = self.client_table.get(ctx);
-            let account_table = self.account_table.get(ctx);
-            // Lookup the account key in the client->account table and then check the balance on the
-            // account
-            client_table
+            self.client_balances
+                .get(ctx)
                 .get(client)
-                .and_then(|key| account_table.get(key))
-                .map(|a| a.bandwidth_balance)
-                .unwrap_or(0)
+                .unwrap_or(HpUfixed::<6>::zero())
         })
     }
 
@@ -140,6 +199,15 @@ fn get_staked(&self, node: &NodePublicKey) -> HpUfixed<18> {
             .unwrap_or(HpUfixed::zero())
     }
 
+    fn get_delegation(&self, node: &NodePublicKey, delegator: &EthAddress) -> Option<Delegation> {
+        self.inner.run(|ctx| {
+            self.pub_key_to_index
+                .get(ctx)
+                .get(node)
+                .and_then(|index| self.delegations.get(ctx).get((index, *delegator)))
+        })
+    }
+
     fn get_locked(&self, node: &NodePublicKey) -> HpUfixed<18> {
         self.get_node_info_with_pub_key(node, |node_info| node_info.stake.locked)
             .unwrap_or(HpUfixed::zero())
@@ -186,21 +254,45 @@ fn get_account_info(&self, id: &EthAddress) -> Option<AccountInfo> {
         self.inner.run(|ctx| self.account_table.get(ctx).get(id))
     }
 
+    fn get_state_root(&self) -> [u8; 32] {
+        self.inner.state_root()
+    }
+
+    fn get_account_info_with_proof(&self, id: &EthAddress) -> (Option<AccountInfo>, StateProof) {
+        self.inner.get_with_proof("account", id)
+    }
+
+    fn get_node_info_with_proof(&self, index: &NodeIndex) -> (Option<NodeInfo>, StateProof) {
+        self.inner.get_with_proof("node", index)
+    }
+
+    fn get_vesting_schedules(&self, account: &EthAddress) -> Vec<VestingSchedule> {
+        self.inner.run(|ctx| {
+            self.vesting_schedules
+                .get(ctx)
+                .prefix(account)
+                .map(|(_, schedule)| schedule)
+                .collect()
+        })
+    }
+
     fn get_node_registry(&self) -> Vec<NodeInfo> {
         let staking_amount: HpUfixed<18> = self.get_staking_amount().into();
         self.inner.run(|ctx| {
-            let node_table = self.node_table.get(ctx);
-            node_table
-                .keys()
-                .map(|index| node_table.get(index).unwrap())
-                .filter(|node| node.stake.staked >= staking_amount)
+            self.node_table
+                .get(ctx)
+                .range(..)
+                .map(|(_, node)| node)
+                .filter(|node| node.stake.staked >= staking_amount && node.jailed_since.is_none())
                 .collect()
         })
     }
 
     fn is_valid_node(&self, id: &NodePublicKey) -> bool {
-        self.get_node_info(id)
-            .is_some_and(|node_info| node_info.stake.staked >= self.get_staking_amount().into())
+        self.get_node_info(id).is_some_and(|node_info| {
+            node_info.stake.staked >= self.get_staking_amount().into()
+                && node_info.jailed_since.is_none()
+        })
     }
 
     fn get_staking_amount(&self) -> u128 {
@@ -212,8 +304,22 @@ fn get_staking_amount(&self) -> u128 {
         })
     }
 
-    fn get_epoch_randomness_seed(&self) -> &[u8; 32] {
-        todo!()
+    fn get_epoch_randomness_seed(&self) -> [u8; 32] {
+        self.inner.run(
+            |ctx| match self.metadata_table.get(ctx).get(&Metadata::EpochRandomness) {
+                Some(Value::Hash(seed)) => seed,
+                _ => [0; 32],
+            },
+        )
+    }
+
+    fn get_epoch_state_root(&self) -> [u8; 32] {
+        self.inner.run(
+            |ctx| match self.metadata_table.get(ctx).get(&Metadata::EpochStateRoot) {
+                Some(Value::Hash(root)) => root,
+                _ => [0; 32],
+            },
+        )
     }
     #[autometrics]
     fn get_committee_members(&self) -> Vec<NodePublicKey> {
@@ -266,6 +372,15 @@ fn get_epoch(&self) -> Epoch {
         )
     }
 
+    fn get_chain_id(&self) -> ChainId {
+        self.inner.run(
+            |ctx| match self.metadata_table.get(ctx).get(&Metadata::ChainId) {
+                Some(Value::ChainId(chain_id)) => chain_id,
+                _ => 0,
+            },
+        )
+    }
+
     fn get_epoch_info(&self) -> EpochInfo {
         self.inner.run(|ctx| {
             let node_table = self.node_table.get(ctx);
@@ -315,6 +430,22 @@ fn get_node_served(&self, node: &NodePublicKey) -> NodeServed {
         })
     }
 
+    fn get_rewards(&self, epoch: Epoch, node: &NodePublicKey) -> Option<RewardsBreakdown> {
+        self.inner.run(|ctx| {
+            let epoch_rewards = self.epoch_rewards.get(ctx).get(epoch)?;
+            let node_rewards = self
+                .pub_key_to_index
+                .get(ctx)
+                .get(node)
+                .and_then(|index| self.node_rewards.get(ctx).get((epoch, index)))
+                .unwrap_or_default();
+            Some(RewardsBreakdown {
+                node: node_rewards,
+                epoch: epoch_rewards,
+            })
+        })
+    }
+
     fn get_total_supply(&self) -> HpUfixed<18> {
         self.inner.run(|ctx| {
             let supply = match self.metadata_table.get(ctx).get(&Metadata::TotalSupply) {
@@ -366,6 +497,59 @@ fn validate_txn(&self, txn: UpdateRequest) -> TransactionResponse {
         })
     }
 
+    fn simulate_txn(&self, txn: UpdateRequest) -> TransactionSimulation {
+        self.inner.run(|ctx| {
+            let response = {
+                // Create the app/execution enviroment, it has to be dropped before collecting the
+                // changes so the tables can be claimed again.
+                let backend = StateTables {
+                    table_selector: ctx,
+                };
+                let app = State::new(backend);
+                app.execute_txn(txn)
+            };
+            // The changes are only collected and never committed since this is a query.
+            let diff = StateDiff {
+                metadata: table_changes(ctx, "metadata"),
+                account: table_changes(ctx, "account"),
+                client_keys: table_changes(ctx, "client_keys"),
+                client_balances: table_changes(ctx, "client_balances"),
+                delivery_ack_sequences: table_changes(ctx, "delivery_ack_sequences"),
+                vesting_schedules: table_changes(ctx, "vesting_schedules"),
+                node: table_changes(ctx, "node"),
+                consensus_key_to_index: table_changes(ctx, "consensus_key_to_index"),
+                pub_key_to_index: table_changes(ctx, "pub_key_to_index"),
+                latencies: table_changes(ctx, "latencies"),
+                committee: table_changes(ctx, "committee"),
+                service: table_changes(ctx, "service"),
+                parameter: table_changes(ctx, "parameter"),
+                rep_measurements: table_changes(ctx, "rep_measurements"),
+                rep_scores: table_changes(ctx, "rep_scores"),
+                current_epoch_served: table_changes(ctx, "current_epoch_served"),
+                last_epoch_served: table_changes(ctx, "last_epoch_served"),
+                total_served: table_changes(ctx, "total_served"),
+                commodity_prices: table_changes(ctx, "commodity_prices"),
+                service_prices: table_changes(ctx, "service_prices"),
+                service_price_updates: table_changes(ctx, "service_price_updates"),
+                service_revenue: table_changes(ctx, "service_revenue"),
+                pending_withdrawals: table_changes(ctx, "pending_withdrawals"),
+                processed_deposits: table_changes(ctx, "processed_deposits"),
+                slashed_misbehaviors: table_changes(ctx, "slashed_misbehaviors"),
+                delegations: table_changes(ctx, "delegations"),
+                commissions: table_changes(ctx, "commissions"),
+                node_info_updates: table_changes(ctx, "node_info_updates"),
+                proposals: table_changes(ctx, "proposals"),
+                proposal_votes: table_changes(ctx, "proposal_votes"),
+                node_stake_snapshots: table_changes(ctx, "node_stake_snapshots"),
+                randomness_shares: table_changes(ctx, "randomness_shares"),
+                node_service_revenue: table_changes(ctx, "node_service_revenue"),
+                epoch_rewards: table_changes(ctx, "epoch_rewards"),
+                node_rewards: table_changes(ctx, "node_rewards"),
+            };
+            TransactionSimulation { response, diff }
+        })
+    }
+
     fn get_latencies(&self) -> HashMap<(NodePublicKey, NodePublicKey), Duration> {
         let keys: Vec<(u32, u32)> = self
             .inner
@@ -396,6 +580,19 @@ fn get_service_info(&self, service_id: ServiceId) -> Service {
             .run(|ctx| self.services_table.get(ctx).get(service_id).unwrap())
     }
 
+    fn get_service_price(
+        &self,
+        service_id: ServiceId,
+        commodity: CommodityTypes,
+    ) -> Option<HpUfixed<6>> {
+        self.inner.run(|ctx| {
+            self.service_prices
+                .get(ctx)
+                .get((service_id, commodity))
+                .or_else(|| self.commodity_prices.get(ctx).get(commodity))
+        })
+    }
+
     fn pubkey_to_index(&self, node: NodePublicKey) -> Option<NodeIndex> {
         self.inner
             .run(|ctx| self.pub_key_to_index.get(ctx).get(node))
@@ -411,12 +608,29 @@ fn index_to_pubkey(&self, node_index: NodeIndex) -> Option<NodePublicKey> {
     }
 
     fn get_last_epoch_hash(&self) -> [u8; 32] {
-        self.inner.run(
-            |ctx| match self.metadata_table.get(ctx).get(&Metadata::LastEpochHash) {
+        self.inner.run(|ctx| {
+            match self
+                .local_metadata_table
+                .get(ctx)
+                .get(&Metadata::LastEpochHash)
+            {
                 Some(Value::Hash(hash)) => hash,
                 _ => [0; 32],
-            },
-        )
+            }
+        })
+    }
+
+    fn get_last_epoch_delta_hashes(&self) -> Vec<[u8; 32]> {
+        self.inner.run(|ctx| {
+            match self
+                .local_metadata_table
+                .get(ctx)
+                .get(&Metadata::LastEpochDeltaHashes)
+            {
+                Some(Value::Hashes(delta_hashes)) => delta_hashes,
+                _ => Vec::new(),
+            }
+        })
     }
 
     fn is_committee(&self, node_index: u32) -> bool {
@@ -443,4 +657,62 @@ fn is_committee(&self, node_index: u32) -> bool {
             },
         )
     }
+
+    fn get_pending_withdrawals(&self) -> Vec<(u64, PendingWithdrawal)> {
+        self.inner.run(|ctx| {
+            let withdrawals_table = self.pending_withdrawals.get(ctx);
+            let mut withdrawals: Vec<(u64, PendingWithdrawal)> = withdrawals_table
+                .keys()
+                .filter_map(|id| withdrawals_table.get(id).map(|withdrawal| (id, withdrawal)))
+                .collect();
+            withdrawals.sort_by_key(|(id, _)| *id);
+            withdrawals
+        })
+    }
+
+    fn get_proposal(&self, proposal_id: ProposalId) -> Option<Proposal> {
+        self.inner
+            .run(|ctx| self.proposals.get(ctx).get(proposal_id))
+    }
+
+    fn get_transaction_receipt(&self, hash: &[u8; 32]) -> Option<TransactionReceipt> {
+        self.inner
+            .run(|ctx| self.transaction_receipts.get(ctx).get(hash))
+    }
+
+    fn get_block_transactions(&self, block_hash: &[u8; 32]) -> Vec<TransactionReceipt> {
+        self.inner.run(|ctx| {
+            let receipts_table = self.transaction_receipts.get(ctx);
+            self.block_transactions
+                .get(ctx)
+                .get(block_hash)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|hash| receipts_table.get(hash))
+                .collect()
+        })
+    }
+
+    fn get_block_number(&self) -> u64 {
+        self.inner.run(
+            |ctx| match self.metadata_table.get(ctx).get(&Metadata::BlockNumber) {
+                Some(Value::BlockNumber(number)) => number,
+                _ => 0,
+            },
+        )
+    }
+
+    fn at_block(&self, block_number: u64) -> Option<Self> {
+        self.inner.at_version(block_number).map(|inner| Self {
+            inner,
+            ..self.clone()
+        })
+    }
+
+    fn at_epoch(&self, epoch: Epoch) -> Option<Self> {
+        let block_number = self
+            .inner
+            .run(|ctx| self.epoch_end_blocks.get(ctx).get(epoch))?;
+        self.at_block(block_number)
+    }
 }
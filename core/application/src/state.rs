@@ -1,5 +1,6 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::net::IpAddr;
 use std::time::Duration;
 
@@ -7,48 +8,63 @@
 use fleek_crypto::{
     ClientPublicKey,
     ConsensusPublicKey,
+    ConsensusSignature,
     EthAddress,
     NodePublicKey,
+    PublicKey,
     TransactionSender,
 };
 use hp_fixed::unsigned::HpUfixed;
 use lazy_static::lazy_static;
 use lightning_interfaces::types::{
+    randomness_share_transcript,
     AccountInfo,
     Committee,
     CommodityTypes,
+    Delegation,
     DeliveryAcknowledgment,
+    DepositEvent,
+    DepositId,
     Epoch,
+    EpochRewards,
     ExecutionData,
     ExecutionError,
     Metadata,
     NodeIndex,
     NodeInfo,
+    NodeInfoUpdate,
     NodePorts,
+    NodeRewards,
     NodeServed,
+    PendingWithdrawal,
     ProofOfConsensus,
     ProofOfMisbehavior,
+    Proposal,
+    ProposalAction,
+    ProposalId,
     ProtocolParams,
     ReportedReputationMeasurements,
     ReputationMeasurements,
     Service,
+    ServiceBuilderRewards,
     ServiceId,
     ServiceRevenue,
     Staking,
     TestnetAdmin,
     Tokens,
     TotalServed,
+    TransactionEvent,
+    TransactionReceipt,
     TransactionResponse,
     UpdateMethod,
     UpdateRequest,
     Value,
+    VestingSchedule,
+    UPDATE_PAYLOAD_VERSION,
 };
 use lightning_interfaces::ToDigest;
 use lightning_reputation::statistics;
 use lightning_reputation::types::WeightedReputationMeasurements;
-use rand::rngs::StdRng;
-use rand::seq::SliceRandom;
-use rand::SeedableRng;
 
 use crate::table::{Backend, TableRef};
 
@@ -82,8 +98,13 @@
 /// All state changes come from Transactions and start at execute_txn
 pub struct State<B: Backend> {
     pub metadata: B::Ref<Metadata, Value>,
+    /// The metadata that is local to the node and not part of the state tree.
+    pub local_metadata: B::Ref<Metadata, Value>,
     pub account_info: B::Ref<EthAddress, AccountInfo>,
+    pub vesting_schedules: B::Ref<(EthAddress, u64), VestingSchedule>,
     pub client_keys: B::Ref<ClientPublicKey, EthAddress>,
+    pub client_balances: B::Ref<ClientPublicKey, HpUfixed<6>>,
+    pub delivery_ack_sequences: B::Ref<(ClientPublicKey, NodeIndex), u64>,
     pub node_info: B::Ref<NodeIndex, NodeInfo>,
     pub consensus_key_to_index: B::Ref<ConsensusPublicKey, NodeIndex>,
     pub pub_key_to_index: B::Ref<NodePublicKey, NodeIndex>,
@@ -97,16 +118,41 @@ pub struct State<B: Backend> {
     pub last_epoch_served: B::Ref<NodeIndex, NodeServed>,
     pub total_served: B::Ref<Epoch, TotalServed>,
     pub service_revenue: B::Ref<ServiceId, ServiceRevenue>,
+    pub node_service_revenue: B::Ref<(NodeIndex, ServiceId), ServiceRevenue>,
+    pub epoch_rewards: B::Ref<Epoch, EpochRewards>,
+    pub node_rewards: B::Ref<(Epoch, NodeIndex), NodeRewards>,
     pub commodity_prices: B::Ref<CommodityTypes, HpUfixed<6>>,
+    pub service_prices: B::Ref<(ServiceId, CommodityTypes), HpUfixed<6>>,
+    pub service_price_updates: B::Ref<(ServiceId, CommodityTypes), HpUfixed<6>>,
+    pub pending_withdrawals: B::Ref<u64, PendingWithdrawal>,
+    pub processed_deposits: B::Ref<DepositId, DepositEvent>,
+    pub slashed_misbehaviors: B::Ref<(NodeIndex, [u8; 32]), HpUfixed<18>>,
+    pub delegations: B::Ref<(NodeIndex, EthAddress), Delegation>,
+    pub commissions: B::Ref<NodeIndex, u8>,
+    pub node_info_updates: B::Ref<NodeIndex, NodeInfoUpdate>,
+    pub randomness_shares: B::Ref<NodeIndex, ConsensusSignature>,
+    pub proposals: B::Ref<ProposalId, Proposal>,
+    pub proposal_votes: B::Ref<(ProposalId, EthAddress), bool>,
+    pub node_stake_snapshots: B::Ref<(NodeIndex, Epoch), HpUfixed<18>>,
+    pub transaction_receipts: B::Ref<[u8; 32], TransactionReceipt>,
+    pub block_transactions: B::Ref<[u8; 32], Vec<[u8; 32]>>,
+    pub block_hashes: B::Ref<u64, [u8; 32]>,
+    pub epoch_end_blocks: B::Ref<Epoch, u64>,
     pub backend: B,
+    /// The events emitted by the transaction that is currently executed.
+    events: RefCell<Vec<TransactionEvent>>,
 }
 
 impl<B: Backend> State<B> {
     pub fn new(backend: B) -> Self {
         Self {
             metadata: backend.get_table_reference("metadata"),
+            local_metadata: backend.get_table_reference("local_metadata"),
             account_info: backend.get_table_reference("account"),
+            vesting_schedules: backend.get_table_reference("vesting_schedules"),
             client_keys: backend.get_table_reference("client_keys"),
+            client_balances: backend.get_table_reference("client_balances"),
+            delivery_ack_sequences: backend.get_table_reference("delivery_ack_sequences"),
             node_info: backend.get_table_reference("node"),
             consensus_key_to_index: backend.get_table_reference("consensus_key_to_index"),
             pub_key_to_index: backend.get_table_reference("pub_key_to_index"),
@@ -120,33 +166,150 @@ pub fn new(backend: B) -> Self {
             current_epoch_served: backend.get_table_reference("current_epoch_served"),
             total_served: backend.get_table_reference("total_served"),
             commodity_prices: backend.get_table_reference("commodity_prices"),
+            service_prices: backend.get_table_reference("service_prices"),
+            service_price_updates: backend.get_table_reference("service_price_updates"),
             service_revenue: backend.get_table_reference("service_revenue"),
+            node_service_revenue: backend.get_table_reference("node_service_revenue"),
+            epoch_rewards: backend.get_table_reference("epoch_rewards"),
+            node_rewards: backend.get_table_reference("node_rewards"),
+            pending_withdrawals: backend.get_table_reference("pending_withdrawals"),
+            processed_deposits: backend.get_table_reference("processed_deposits"),
+            slashed_misbehaviors: backend.get_table_reference("slashed_misbehaviors"),
+            delegations: backend.get_table_reference("delegations"),
+            commissions: backend.get_table_reference("commissions"),
+            node_info_updates: backend.get_table_reference("node_info_updates"),
+            randomness_shares: backend.get_table_reference("randomness_shares"),
+            proposals: backend.get_table_reference("proposals"),
+            proposal_votes: backend.get_table_reference("proposal_votes"),
+            node_stake_snapshots: backend.get_table_reference("node_stake_snapshots"),
+            epoch_end_blocks: backend.get_table_reference("epoch_end_blocks"),
+            transaction_receipts: backend.get_table_reference("transaction_receipts"),
+            block_transactions: backend.get_table_reference("block_transactions"),
+            block_hashes: backend.get_table_reference("block_hashes"),
             backend,
+            events: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Records the given event for the receipt of the transaction that is currently executed.
+    fn emit(&self, event: TransactionEvent) {
+        self.events.borrow_mut().push(event);
+    }
+
+    /// Returns the events emitted since the last call, this should be called after every
+    /// transaction.
+    pub fn take_events(&self) -> Vec<TransactionEvent> {
+        self.events.take()
+    }
+
+    /// Stores the receipts of a block and drops the receipts of the block that is now older than
+    /// the retention, so they do not grow the state forever.
+    // This function should only be called in the `run` method on `Env`.
+    pub fn store_receipts(
+        &self,
+        block_number: u64,
+        block_hash: [u8; 32],
+        receipts: Vec<TransactionReceipt>,
+    ) {
+        let hashes = receipts
+            .iter()
+            .map(|receipt| receipt.transaction_hash)
+            .collect();
+        for receipt in receipts {
+            self.transaction_receipts
+                .set(receipt.transaction_hash, receipt);
+        }
+        self.block_transactions.set(block_hash, hashes);
+        self.block_hashes.set(block_number, block_hash);
+
+        let retention = self
+            .parameters
+            .get(&ProtocolParams::ReceiptRetention)
+            .unwrap_or(0) as u64;
+        if retention == 0 || block_number <= retention {
+            return;
+        }
+        let expired = block_number - retention;
+        if let Some(expired_hash) = self.block_hashes.get(&expired) {
+            for hash in self
+                .block_transactions
+                .get(&expired_hash)
+                .unwrap_or_default()
+            {
+                self.transaction_receipts.remove(&hash);
+            }
+            self.block_transactions.remove(&expired_hash);
+            self.block_hashes.remove(&expired);
         }
     }
 
     /// This function is the entry point of a transaction
     pub fn execute_txn(&self, txn: UpdateRequest) -> TransactionResponse {
-        // Execute transaction
-        let response = match txn.payload.method {
+        // Execute transaction inside its own savepoint so a revert undoes its partial writes
+        let num_events = self.events.borrow().len();
+        let savepoint = self.backend.savepoint();
+        let response = self.execute_method(txn.sender, txn.payload.method);
+        if let TransactionResponse::Revert(_) = response {
+            self.backend.rollback_to(savepoint);
+            self.events.borrow_mut().truncate(num_events);
+        }
+        self.backend.release(savepoint);
+
+        #[cfg(debug_assertions)]
+        {
+            let node_info_len = self.node_info.keys().count();
+            let consensus_key_to_index_len = self.consensus_key_to_index.keys().count();
+            let pub_key_to_index_len = self.pub_key_to_index.keys().count();
+            assert_eq!(node_info_len, consensus_key_to_index_len);
+            assert_eq!(node_info_len, pub_key_to_index_len);
+            assert_eq!(pub_key_to_index_len, consensus_key_to_index_len);
+        }
+
+        // Increment nonce of the sender
+        self.increment_nonce(txn.sender);
+        // Return the response
+        response
+    }
+
+    /// Executes a single update method on behalf of the sender, without touching its nonce.
+    fn execute_method(
+        &self,
+        sender: TransactionSender,
+        method: UpdateMethod,
+    ) -> TransactionResponse {
+        match method {
             UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+                client,
                 commodity,
                 service_id,
                 proofs,
                 metadata: _,
-            } => self.submit_pod(txn.sender, commodity, service_id, proofs),
+            } => self.submit_pod(sender, client, commodity, service_id, proofs),
 
             UpdateMethod::Withdraw {
                 amount,
                 token,
                 receiving_address,
-            } => self.withdraw(txn.sender, receiving_address, amount, token),
+            } => self.withdraw(sender, receiving_address, amount, token),
+
+            UpdateMethod::Transfer { token, amount, to } => {
+                self.transfer(sender, to, amount, token)
+            },
+
+            UpdateMethod::GrantVesting {
+                recipient,
+                amount,
+                cliff,
+                duration,
+            } => self.grant_vesting(sender, recipient, amount, cliff, duration),
+
+            UpdateMethod::FundClient { client, amount } => self.fund_client(sender, client, amount),
 
             UpdateMethod::Deposit {
                 proof,
                 token,
                 amount,
-            } => self.deposit(txn.sender, proof, amount, token),
+            } => self.deposit(sender, proof, amount, token),
 
             UpdateMethod::Stake {
                 amount,
@@ -157,7 +320,7 @@ pub fn execute_txn(&self, txn: UpdateRequest) -> TransactionResponse {
                 worker_domain,
                 ports,
             } => self.stake(
-                txn.sender,
+                sender,
                 amount,
                 node_public_key,
                 consensus_key,
@@ -167,57 +330,129 @@ pub fn execute_txn(&self, txn: UpdateRequest) -> TransactionResponse {
                 ports,
             ),
             UpdateMethod::StakeLock { node, locked_for } => {
-                self.stake_lock(txn.sender, node, locked_for)
+                self.stake_lock(sender, node, locked_for)
             },
 
-            UpdateMethod::Unstake { amount, node } => self.unstake(txn.sender, amount, node),
+            UpdateMethod::Unstake { amount, node } => self.unstake(sender, amount, node),
 
             UpdateMethod::WithdrawUnstaked { node, recipient } => {
-                self.withdrawl_unstaked(txn.sender, node, recipient)
+                self.withdrawl_unstaked(sender, node, recipient)
+            },
+
+            UpdateMethod::SetCommission { node, commission } => {
+                self.set_commission(sender, node, commission)
             },
 
-            UpdateMethod::ChangeEpoch { epoch } => self.change_epoch(txn.sender, epoch),
+            UpdateMethod::UpdateNodeInfo {
+                node,
+                domain,
+                worker_domain,
+                worker_public_key,
+                ports,
+            } => self.update_node_info(
+                sender,
+                node,
+                NodeInfoUpdate {
+                    domain,
+                    worker_domain,
+                    worker_public_key,
+                    ports,
+                    consensus_key: None,
+                },
+            ),
+
+            UpdateMethod::RotateKeys {
+                node,
+                new_public_key,
+                new_consensus_key,
+            } => self.rotate_keys(sender, node, new_public_key, new_consensus_key),
+
+            UpdateMethod::Unjail { node } => self.unjail(sender, node),
+
+            UpdateMethod::ChangeEpoch { epoch } => self.change_epoch(sender, epoch),
+
+            UpdateMethod::SubmitRandomnessShare { epoch, signature } => {
+                self.submit_randomness_share(sender, epoch, signature)
+            },
 
             UpdateMethod::AddService {
                 service,
                 service_id,
-            } => self.add_service(txn.sender, service, service_id),
+            } => self.add_service(sender, service, service_id),
 
-            UpdateMethod::RemoveService { service_id } => {
-                self.remove_service(txn.sender, service_id)
-            },
+            UpdateMethod::RemoveService { service_id } => self.remove_service(sender, service_id),
+
+            UpdateMethod::SetServicePrice {
+                service_id,
+                commodity,
+                price,
+            } => self.set_service_price(sender, service_id, commodity, price),
 
             UpdateMethod::Slash {
                 service_id,
                 node,
                 proof_of_misbehavior,
-            } => self.slash(txn.sender, proof_of_misbehavior, service_id, node),
+            } => self.slash(sender, proof_of_misbehavior, service_id, node),
 
             UpdateMethod::SubmitReputationMeasurements { measurements } => {
-                self.submit_reputation_measurements(txn.sender, measurements)
+                self.submit_reputation_measurements(sender, measurements)
             },
             UpdateMethod::ChangeProtocolParam { param, value } => {
-                self.change_protocol_param(txn.sender, param, value)
+                self.change_protocol_param(sender, param, value)
             },
+            UpdateMethod::Propose { action } => self.propose(sender, action),
+            UpdateMethod::Vote {
+                proposal_id,
+                approve,
+            } => self.vote(sender, proposal_id, approve),
+            UpdateMethod::ExecuteProposal { proposal_id } => {
+                self.execute_proposal(sender, proposal_id)
+            },
+            UpdateMethod::Batch(methods) => self.execute_batch(sender, methods),
             UpdateMethod::TestnetAdmin(inner) => match inner {
-                TestnetAdmin::Kill => self.testnet_kill(txn.sender),
+                TestnetAdmin::Kill => self.testnet_kill(sender),
             },
-        };
+        }
+    }
 
-        #[cfg(debug_assertions)]
-        {
-            let node_info_len = self.node_info.keys().count();
-            let consensus_key_to_index_len = self.consensus_key_to_index.keys().count();
-            let pub_key_to_index_len = self.pub_key_to_index.keys().count();
-            assert_eq!(node_info_len, consensus_key_to_index_len);
-            assert_eq!(node_info_len, pub_key_to_index_len);
-            assert_eq!(pub_key_to_index_len, consensus_key_to_index_len);
+    /// Executes the methods of a batch in order, if any of them reverts the changes of all the
+    /// previous ones are undone and the batch reverts.
+    fn execute_batch(
+        &self,
+        sender: TransactionSender,
+        methods: Vec<UpdateMethod>,
+    ) -> TransactionResponse {
+        let is_valid = !methods.is_empty()
+            && methods.iter().all(|method| {
+                !matches!(
+                    method,
+                    UpdateMethod::Batch(_) | UpdateMethod::ChangeEpoch { .. }
+                )
+            });
+        if !is_valid {
+            return TransactionResponse::Revert(ExecutionError::InvalidBatch);
         }
 
-        // Increment nonce of the sender
-        self.increment_nonce(txn.sender);
-        // Return the response
-        response
+        let num_events = self.events.borrow().len();
+        let savepoint = self.backend.savepoint();
+        let mut results = Vec::with_capacity(methods.len());
+        for (step, method) in methods.into_iter().enumerate() {
+            match self.execute_method(sender, method) {
+                TransactionResponse::Success(data) => results.push(data),
+                TransactionResponse::Revert(error) => {
+                    self.backend.rollback_to(savepoint);
+                    self.backend.release(savepoint);
+                    self.events.borrow_mut().truncate(num_events);
+                    return TransactionResponse::Revert(ExecutionError::BatchStepFailed {
+                        step: step as u32,
+                        error: Box::new(error),
+                    });
+                },
+            }
+        }
+        self.backend.release(savepoint);
+
+        TransactionResponse::Success(ExecutionData::Batch(results))
     }
 
     /*********** External Update Functions ********** */
@@ -226,27 +461,37 @@ pub fn execute_txn(&self, txn: UpdateRequest) -> TransactionResponse {
     fn submit_pod(
         &self,
         sender: TransactionSender,
+        client: ClientPublicKey,
         commodity: u128,
         service_id: u32,
-        _acknowledgments: Vec<DeliveryAcknowledgment>,
+        acknowledgments: Vec<DeliveryAcknowledgment>,
     ) -> TransactionResponse {
-        // Todo: function not done
         let sender: NodeIndex = match self.only_node(sender) {
             Ok(index) => index,
             Err(e) => return e,
         };
-        let account_owner = match self.node_info.get(&sender) {
-            Some(node_info) => node_info.owner,
+        let node_public_key = match self.node_info.get(&sender) {
+            Some(node_info) if node_info.jailed_since.is_some() => {
+                return TransactionResponse::Revert(ExecutionError::NodeJailed);
+            },
+            Some(node_info) => node_info.public_key,
             None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
         };
 
         if self.services.get(&service_id).is_none() {
             return TransactionResponse::Revert(ExecutionError::InvalidServiceId);
         }
-        // Todo: build proof based on delivery acks
-        if !self.verify_proof_of_delivery(&account_owner, &sender, &commodity, &service_id, ()) {
+        // The escrow of the client only pays for what the client acknowledged
+        let Some(sequence) = self.verify_proof_of_delivery(
+            &client,
+            &node_public_key,
+            &sender,
+            &commodity,
+            &service_id,
+            &acknowledgments,
+        ) else {
             return TransactionResponse::Revert(ExecutionError::InvalidProof);
-        }
+        };
 
         let commodity_type = self
             .services
@@ -261,9 +506,11 @@ fn submit_pod(
 
         let mut node_served = self.current_epoch_served.get(&sender).unwrap_or_default();
         let mut total_served = self.total_served.get(&current_epoch).unwrap_or_default();
+        // Services can set their own price, otherwise the network wide commodity price is used
         let commodity_prices = self
-            .commodity_prices
-            .get(&commodity_type)
+            .service_prices
+            .get(&(service_id, commodity_type))
+            .or_else(|| self.commodity_prices.get(&commodity_type))
             .expect("Commodity price should always be set");
 
         let commodity_index = commodity_type as usize;
@@ -278,6 +525,16 @@ fn submit_pod(
         let commodity_to_big: HpUfixed<6> = commodity.into();
         let revenue = &commodity_to_big * &commodity_prices;
 
+        // The client pays for the work out of its escrow, the revenue is paid out to the nodes
+        // and service builders when the rewards of the epoch are distributed
+        let mut client_balance = self.client_balances.get(&client).unwrap_or_default();
+        if client_balance < revenue {
+            return TransactionResponse::Revert(ExecutionError::InsufficientClientBalance);
+        }
+        client_balance -= revenue.clone();
+        self.client_balances.set(client, client_balance);
+        self.delivery_ack_sequences.set((client, sender), sequence);
+
         node_served.served[commodity_index] += commodity;
         node_served.stables_revenue += revenue.clone();
 
@@ -287,29 +544,91 @@ fn submit_pod(
         // Todo: track commodity served by service to be used for service builders reward share
         // if the a services serves multiple commodity, the current logic would change
         let mut service_revenue = self.service_revenue.get(&service_id).unwrap_or_default();
-        service_revenue += revenue;
+        service_revenue += revenue.clone();
+        let mut node_service_revenue = self
+            .node_service_revenue
+            .get(&(sender, service_id))
+            .unwrap_or_default();
+        node_service_revenue += revenue;
 
         self.current_epoch_served.set(sender, node_served);
         self.total_served.set(current_epoch, total_served);
         self.service_revenue.set(service_id, service_revenue);
+        self.node_service_revenue
+            .set((sender, service_id), node_service_revenue);
 
         TransactionResponse::Success(ExecutionData::None)
     }
 
     fn withdraw(
         &self,
-        _sender: TransactionSender,
-        _reciever: EthAddress,
-        _amount: HpUfixed<18>,
-        _token: Tokens,
+        sender: TransactionSender,
+        reciever: EthAddress,
+        mut amount: HpUfixed<18>,
+        token: Tokens,
     ) -> TransactionResponse {
-        todo!()
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let mut account = self.account_info.get(&sender).unwrap_or_default();
+
+        // Check the token withdrawn and make sure the sender has enough of it
+        match token {
+            Tokens::FLK => {
+                if let Err(e) = check_spendable_flk(&account, &amount) {
+                    return e;
+                }
+                account.flk_balance -= amount.clone();
+            },
+            Tokens::USDC => {
+                let stables: HpUfixed<6> = amount.convert_precision();
+                if account.stables_balance < stables {
+                    return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+                }
+                // Stables only have 6 decimals, so only the truncated amount is debited and
+                // released on the L2.
+                amount = stables.convert_precision();
+                account.stables_balance -= stables;
+            },
+        }
+
+        // Queue the withdrawal so the bridge can release the tokens on the L2
+        let withdraw_id = match self.metadata.get(&Metadata::NextWithdrawId) {
+            Some(Value::NextWithdrawId(id)) => id,
+            _ => 0,
+        };
+        self.pending_withdrawals.set(
+            withdraw_id,
+            PendingWithdrawal {
+                receiver: reciever,
+                token: token.clone(),
+                amount: amount.clone(),
+            },
+        );
+        self.metadata.set(
+            Metadata::NextWithdrawId,
+            Value::NextWithdrawId(withdraw_id + 1),
+        );
+
+        self.account_info.set(sender, account);
+        self.emit(TransactionEvent::Withdraw {
+            withdraw_id,
+            token,
+            from: sender,
+            amount,
+        });
+        // The id of the withdrawal is returned as the receipt
+        TransactionResponse::Success(ExecutionData::UInt(withdraw_id.into()))
     }
 
-    fn deposit(
+    fn transfer(
         &self,
         sender: TransactionSender,
-        proof: ProofOfConsensus,
+        to: EthAddress,
         amount: HpUfixed<18>,
         token: Tokens,
     ) -> TransactionResponse {
@@ -320,20 +639,191 @@ fn deposit(
             Err(e) => return e,
         };
 
+        // USDC balances only have 6 decimals, the rest of the amount is not moved
+        let amount = match &token {
+            Tokens::FLK => amount,
+            Tokens::USDC => amount.convert_precision::<6>().convert_precision(),
+        };
+        if amount == HpUfixed::zero() {
+            return TransactionResponse::Revert(ExecutionError::ZeroAmount);
+        }
+
+        let mut sender_account = self.account_info.get(&sender).unwrap_or_default();
+
+        // Check the token transferred and make sure the sender has enough of it
+        match &token {
+            Tokens::FLK => {
+                if let Err(e) = check_spendable_flk(&sender_account, &amount) {
+                    return e;
+                }
+                sender_account.flk_balance -= amount.clone();
+            },
+            Tokens::USDC => {
+                let stables: HpUfixed<6> = amount.convert_precision();
+                if sender_account.stables_balance < stables {
+                    return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+                }
+                sender_account.stables_balance -= stables;
+            },
+        }
+        self.account_info.set(sender, sender_account);
+
+        // The recipient is read after the sender was updated, so sending to yourself is a no-op
+        let mut recipient_account = self.account_info.get(&to).unwrap_or_default();
+        match token {
+            Tokens::FLK => recipient_account.flk_balance += amount.clone(),
+            Tokens::USDC => recipient_account.stables_balance += amount.convert_precision(),
+        }
+        self.account_info.set(to, recipient_account);
+        self.emit(TransactionEvent::Transfer {
+            token,
+            from: sender,
+            to,
+            amount,
+        });
+
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn grant_vesting(
+        &self,
+        sender: TransactionSender,
+        recipient: EthAddress,
+        amount: HpUfixed<18>,
+        cliff: Epoch,
+        duration: Epoch,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        let mut sender_account = self.account_info.get(&sender).unwrap_or_default();
+        if let Err(e) = check_spendable_flk(&sender_account, &amount) {
+            return e;
+        }
+        sender_account.flk_balance -= amount.clone();
+        self.account_info.set(sender, sender_account);
+
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        // The recipient is read after the sender was updated, so granting to yourself only locks
+        // the tokens
+        let mut recipient_account = self.account_info.get(&recipient).unwrap_or_default();
+        recipient_account.flk_balance += amount.clone();
+        recipient_account.unvested_flk += amount.clone();
+        self.account_info.set(recipient, recipient_account);
+        // Every grant is a schedule of its own, so a grant can't change when the FLK of another
+        // grant to the same account vests
+        let vesting_id = match self.metadata.get(&Metadata::NextVestingId) {
+            Some(Value::NextVestingId(id)) => id,
+            _ => 0,
+        };
+        self.metadata.set(
+            Metadata::NextVestingId,
+            Value::NextVestingId(vesting_id + 1),
+        );
+        self.vesting_schedules.set(
+            (recipient, vesting_id),
+            VestingSchedule {
+                amount: amount.clone(),
+                start: current_epoch,
+                cliff,
+                duration,
+            },
+        );
+        self.update_vesting(recipient, current_epoch);
+
+        self.emit(TransactionEvent::Transfer {
+            token: Tokens::FLK,
+            from: sender,
+            to: recipient,
+            amount,
+        });
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn fund_client(
+        &self,
+        sender: TransactionSender,
+        client: ClientPublicKey,
+        amount: u128,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let mut sender_account = self.account_info.get(&sender).unwrap_or_default();
+        if sender_account.bandwidth_balance < amount {
+            return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+        }
+        sender_account.bandwidth_balance -= amount;
+        self.account_info.set(sender, sender_account);
+
+        let mut client_balance = self.client_balances.get(&client).unwrap_or_default();
+        client_balance += HpUfixed::<6>::from(amount);
+        self.client_balances.set(client, client_balance);
+        self.emit(TransactionEvent::FundClient {
+            from: sender,
+            client,
+            amount,
+        });
+
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn deposit(
+        &self,
+        sender: TransactionSender,
+        proof: ProofOfConsensus,
+        amount: HpUfixed<18>,
+        token: Tokens,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        if let Err(e) = self.only_account_owner(sender) {
+            return e;
+        }
+
+        // The deposit being claimed has to match the event the bridge validators signed
+        if proof.event.token != token || proof.event.amount != amount {
+            return TransactionResponse::Revert(ExecutionError::InvalidProof);
+        }
+
         // Verify the proof from the bridge
-        if !self.verify_proof_of_consensus(proof) {
+        if !self.verify_proof_of_consensus(&proof) {
             return TransactionResponse::Revert(ExecutionError::InvalidProof);
         }
 
-        let mut account = self.account_info.get(&sender).unwrap_or_default();
+        // Make sure the same deposit can not be claimed twice
+        let deposit_id = proof.event.id();
+        if self.processed_deposits.get(&deposit_id).is_some() {
+            return TransactionResponse::Revert(ExecutionError::DepositAlreadyProcessed);
+        }
+
+        let recipient = proof.event.recipient;
+        let mut account = self.account_info.get(&recipient).unwrap_or_default();
 
         // Check the token bridged and increment that amount
         match token {
-            Tokens::FLK => account.flk_balance += amount,
-            Tokens::USDC => account.bandwidth_balance += TryInto::<u128>::try_into(amount).unwrap(),
+            Tokens::FLK => account.flk_balance += amount.clone(),
+            Tokens::USDC => {
+                account.bandwidth_balance += TryInto::<u128>::try_into(amount.clone()).unwrap()
+            },
         }
 
-        self.account_info.set(sender, account);
+        self.account_info.set(recipient, account);
+        self.processed_deposits.set(deposit_id, proof.event);
+        self.emit(TransactionEvent::Deposit {
+            token,
+            to: recipient,
+            amount,
+        });
         TransactionResponse::Success(ExecutionData::None)
     }
 
@@ -376,6 +866,10 @@ fn stake(
             if self.consensus_key_to_index.get(&consensus_key) != node_index {
                 return TransactionResponse::Revert(ExecutionError::ConsensusKeyAlreadyIndexed);
             }
+            // A new node can't take a consensus key that another node is rotating to
+            if node_index.is_none() && self.is_consensus_key_taken(&consensus_key) {
+                return TransactionResponse::Revert(ExecutionError::ConsensusKeyAlreadyIndexed);
+            }
         }
 
         match node_index {
@@ -385,7 +879,35 @@ fn stake(
                     // This should be impossible to hit
                     None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
                 };
-                // Todo(dalton): should we stop people from staking on a node they do not own??
+
+                // Staking on a node someone else owns delegates the stake to that node
+                if sender != node.owner {
+                    if node_consensus_key.is_some()
+                        || node_domain.is_some()
+                        || worker_public_key.is_some()
+                        || worker_domain.is_some()
+                        || ports.is_some()
+                    {
+                        return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
+                    }
+                    let mut delegation = self.delegations.get(&(index, sender)).unwrap_or_default();
+                    delegation.staked += amount.clone();
+                    self.delegations.set((index, sender), delegation);
+
+                    self.snapshot_node_stake(index, &node.stake.staked);
+                    node.stake.staked += amount.clone();
+                    node.stake.delegated += amount.clone();
+                    self.node_info.set(index, node);
+
+                    owner.flk_balance -= amount.clone();
+                    self.account_info.set(sender, owner);
+                    self.emit(TransactionEvent::Stake {
+                        node: node_public_key,
+                        account: sender,
+                        amount,
+                    });
+                    return TransactionResponse::Success(ExecutionData::None);
+                }
 
                 if let Some(primary_domain) = node_domain {
                     node.domain = primary_domain;
@@ -401,6 +923,7 @@ fn stake(
                 }
 
                 // Increase the nodes stake by the amount being staked
+                self.snapshot_node_stake(index, &node.stake.staked);
                 node.stake.staked += amount.clone();
                 self.node_info.set(index, node);
             },
@@ -434,8 +957,16 @@ fn stake(
                         worker_domain,
                         ports,
                         nonce: 0,
+                        jailed_since: None,
+                        low_reputation_epochs: 0,
+                        missed_randomness_shares: 0,
                     };
-                    self.create_node(node);
+                    if self.create_node(node) {
+                        // The node had no stake before this epoch
+                        if let Some(index) = self.pub_key_to_index.get(&node_public_key) {
+                            self.snapshot_node_stake(index, &HpUfixed::zero());
+                        }
+                    }
                 } else {
                     return TransactionResponse::Revert(ExecutionError::InsufficientNodeDetails);
                 }
@@ -443,10 +974,15 @@ fn stake(
         }
 
         // decrement the owners balance
-        owner.flk_balance -= amount;
+        owner.flk_balance -= amount.clone();
 
         // Commit changes to the owner
         self.account_info.set(sender, owner);
+        self.emit(TransactionEvent::Stake {
+            node: node_public_key,
+            account: sender,
+            amount,
+        });
         TransactionResponse::Success(ExecutionData::None)
     }
 
@@ -518,37 +1054,64 @@ fn unstake(
             None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
         };
 
-        // Make sure the caller is the owner of the node
-        if sender != node.owner {
-            return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
-        }
-
         let current_epoch = match self.metadata.get(&Metadata::Epoch) {
             Some(Value::Epoch(epoch)) => epoch,
             _ => 0,
         };
+        let lock_time = self.parameters.get(&ProtocolParams::LockTime).unwrap_or(0);
+
+        // If the caller is not the owner of the node they are undelegating their own stake
+        if sender != node.owner {
+            let mut delegation = match self.delegations.get(&(index, sender)) {
+                Some(delegation) => delegation,
+                None => return TransactionResponse::Revert(ExecutionError::NotNodeOwner),
+            };
+            if delegation.staked < amount {
+                return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+            }
+            delegation.staked -= amount.clone();
+            delegation.locked += amount.clone();
+            delegation.locked_until = current_epoch + lock_time as u64;
+            self.delegations.set((index, sender), delegation);
+
+            self.snapshot_node_stake(index, &node.stake.staked);
+            node.stake.staked -= amount.clone();
+            node.stake.delegated -= amount.clone();
+            self.node_info.set(index, node);
+            self.emit(TransactionEvent::Unstake {
+                node: node_public_key,
+                account: sender,
+                amount,
+            });
+            return TransactionResponse::Success(ExecutionData::None);
+        }
 
         // Make sure the stakes are not locked
         if node.stake.stake_locked_until > current_epoch {
             return TransactionResponse::Revert(ExecutionError::LockedTokensUnstakeForbidden);
         }
 
-        // Make sure the node has atleast that much staked
-        if node.stake.staked < amount {
+        // Make sure the owner has atleast that much staked, the delegated stake belongs to the
+        // delegators
+        if &node.stake.staked - &node.stake.delegated < amount {
             return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
         }
 
-        let lock_time = self.parameters.get(&ProtocolParams::LockTime).unwrap_or(0);
-
         // decrease the stake, add to the locked amount, and set the locked time for the withdrawl
         // current epoch + lock time todo(dalton): we should be storing unstaked tokens in a
         // list so we can have multiple locked stakes with dif lock times
+        self.snapshot_node_stake(index, &node.stake.staked);
         node.stake.staked -= amount.clone();
-        node.stake.locked += amount;
+        node.stake.locked += amount.clone();
         node.stake.locked_until = current_epoch + lock_time as u64;
 
         // Save the changed node state and return success
         self.node_info.set(index, node);
+        self.emit(TransactionEvent::Unstake {
+            node: node_public_key,
+            account: sender,
+            amount,
+        });
         TransactionResponse::Success(ExecutionData::None)
     }
 
@@ -570,15 +1133,48 @@ fn withdrawl_unstaked(
             None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
         };
 
-        // Make sure the caller is the owner of the node
-        if sender_public_key != node.owner {
-            return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
-        }
-
         let current_epoch = match self.metadata.get(&Metadata::Epoch) {
             Some(Value::Epoch(epoch)) => epoch,
             _ => 0,
         };
+
+        // If the caller is not the owner of the node they are withdrawing their undelegated stake
+        if sender_public_key != node.owner {
+            let mut delegation = match self.delegations.get(&(index, sender_public_key)) {
+                Some(delegation) => delegation,
+                None => return TransactionResponse::Revert(ExecutionError::NotNodeOwner),
+            };
+            if delegation.locked == HpUfixed::zero() {
+                return TransactionResponse::Revert(ExecutionError::NoLockedTokens);
+            }
+            if delegation.locked_until > current_epoch {
+                return TransactionResponse::Revert(ExecutionError::TokensLocked);
+            }
+
+            let recipient = recipient.unwrap_or(sender_public_key);
+            if let Err(e) =
+                self.check_unstaked_recipient(sender_public_key, recipient, &delegation.locked)
+            {
+                return e;
+            }
+            let mut reciever = self.account_info.get(&recipient).unwrap_or_default();
+            reciever.flk_balance += delegation.locked.clone();
+            self.emit(TransactionEvent::WithdrawUnstaked {
+                node: node_public_key,
+                recipient,
+                amount: delegation.locked,
+            });
+            delegation.locked = HpUfixed::zero();
+
+            self.account_info.set(recipient, reciever);
+            // Remove the delegation once nothing is left of it
+            if delegation.staked == HpUfixed::zero() {
+                self.delegations.remove(&(index, sender_public_key));
+            } else {
+                self.delegations.set((index, sender_public_key), delegation);
+            }
+            return TransactionResponse::Success(ExecutionData::None);
+        }
         // Make sure the node has locked tokens and that the lock time is passed
         if node.stake.locked == HpUfixed::zero() {
             return TransactionResponse::Revert(ExecutionError::NoLockedTokens);
@@ -589,12 +1185,22 @@ fn withdrawl_unstaked(
 
         // if there is no recipient the owner will recieve the withdrawl
         let recipient = recipient.unwrap_or(sender_public_key);
+        if let Err(e) =
+            self.check_unstaked_recipient(sender_public_key, recipient, &node.stake.locked)
+        {
+            return e;
+        }
         let mut reciever = self.account_info.get(&recipient).unwrap_or_default();
 
         // add the withdrawn tokens to the recipient and reset the nodes locked stake state
         // no need to reset locked_until on the node because that will get adjusted the next time
         // the node unstakes
-        reciever.flk_balance += node.stake.locked;
+        reciever.flk_balance += node.stake.locked.clone();
+        self.emit(TransactionEvent::WithdrawUnstaked {
+            node: node_public_key,
+            recipient,
+            amount: node.stake.locked,
+        });
         node.stake.locked = HpUfixed::zero();
 
         // Todo(dalton): if the nodes stake+locked are equal to 0 here should we remove him from the
@@ -606,6 +1212,193 @@ fn withdrawl_unstaked(
         TransactionResponse::Success(ExecutionData::None)
     }
 
+    fn set_commission(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        commission: u8,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let (index, node) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        // Make sure the caller is the owner of the node
+        if sender != node.owner {
+            return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
+        }
+        // The commission is a percentage
+        if commission > 100 {
+            return TransactionResponse::Revert(ExecutionError::InvalidCommission);
+        }
+
+        self.commissions.set(index, commission);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn update_node_info(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        update: NodeInfoUpdate,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let (index, node) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        // Make sure the caller is the owner of the node
+        if sender != node.owner {
+            return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
+        }
+
+        if update == NodeInfoUpdate::default() {
+            return TransactionResponse::Revert(ExecutionError::InsufficientNodeDetails);
+        }
+        if !update
+            .domain
+            .iter()
+            .chain(update.worker_domain.iter())
+            .all(is_valid_node_address)
+        {
+            return TransactionResponse::Revert(ExecutionError::InvalidInternetAddress);
+        }
+        if !update.ports.iter().all(are_valid_node_ports) {
+            return TransactionResponse::Revert(ExecutionError::InvalidPorts);
+        }
+
+        // The update is applied when the epoch changes so the committee, topology and DHT see the
+        // same node details for a whole epoch. Multiple updates in the same epoch are merged.
+        let pending = self.node_info_updates.get(&index).unwrap_or_default();
+        self.node_info_updates.set(
+            index,
+            NodeInfoUpdate {
+                domain: update.domain.or(pending.domain),
+                worker_domain: update.worker_domain.or(pending.worker_domain),
+                worker_public_key: update.worker_public_key.or(pending.worker_public_key),
+                ports: update.ports.or(pending.ports),
+                consensus_key: pending.consensus_key,
+            },
+        );
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn rotate_keys(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        new_public_key: Option<NodePublicKey>,
+        new_consensus_key: Option<ConsensusPublicKey>,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let (index, node) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        // Make sure the caller is the owner of the node
+        if sender != node.owner {
+            return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
+        }
+
+        if new_public_key.is_none() && new_consensus_key.is_none() {
+            return TransactionResponse::Revert(ExecutionError::InsufficientNodeDetails);
+        }
+        // The new keys must not be used by any node, including this one
+        if let Some(key) = new_public_key {
+            if self.pub_key_to_index.get(&key).is_some() {
+                return TransactionResponse::Revert(ExecutionError::NodeKeyAlreadyIndexed);
+            }
+        }
+        if let Some(key) = new_consensus_key {
+            if self.is_consensus_key_taken(&key) {
+                return TransactionResponse::Revert(ExecutionError::ConsensusKeyAlreadyIndexed);
+            }
+        }
+
+        if let Some(key) = new_public_key {
+            self.rotate_node_key(index, node, key);
+        }
+        // The committee of the current epoch was formed with the old consensus key, so the new
+        // one is applied with the other node info updates when the epoch changes.
+        if let Some(key) = new_consensus_key {
+            let pending = self.node_info_updates.get(&index).unwrap_or_default();
+            self.node_info_updates.set(
+                index,
+                NodeInfoUpdate {
+                    consensus_key: Some(key),
+                    ..pending
+                },
+            );
+        }
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn unjail(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let (index, mut node) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        // Make sure the caller is the owner of the node
+        if sender != node.owner {
+            return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
+        }
+
+        let jailed_since = match node.jailed_since {
+            Some(epoch) => epoch,
+            None => return TransactionResponse::Revert(ExecutionError::NodeNotJailed),
+        };
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        let cooldown = self
+            .parameters
+            .get(&ProtocolParams::UnjailCooldown)
+            .unwrap_or(0);
+        if ((current_epoch - jailed_since) as u128) < cooldown {
+            return TransactionResponse::Revert(ExecutionError::UnjailCooldownNotPassed);
+        }
+
+        node.jailed_since = None;
+        node.low_reputation_epochs = 0;
+        node.missed_randomness_shares = 0;
+        self.node_info.set(index, node);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
     fn change_epoch(&self, sender: TransactionSender, epoch: Epoch) -> TransactionResponse {
         // Only Nodes can call this function
         let index = match self.only_node(sender) {
@@ -641,7 +1434,10 @@ fn change_epoch(&self, sender: TransactionSender, epoch: Epoch) -> TransactionRe
         if current_committee.ready_to_change.len() > (2 * current_committee.members.len() / 3) {
             // Todo: Reward nodes, choose new committee, increment epoch.
             self.calculate_reputation_scores();
+            self.update_jailed_nodes();
             self.distribute_rewards();
+            self.apply_node_info_updates();
+            self.apply_service_price_updates();
 
             // calculate the next epoch endstamp
             let epoch_duration = self.parameters.get(&ProtocolParams::EpochTime).unwrap_or(1);
@@ -650,6 +1446,8 @@ fn change_epoch(&self, sender: TransactionSender, epoch: Epoch) -> TransactionRe
 
             // Save the old committee so we can see who signaled
             self.committee_info.set(current_epoch, current_committee);
+            // The randomness of the next epoch is used to choose its committee
+            self.update_epoch_randomness(current_epoch);
             // Get new committee
             let new_committee = self.choose_new_committee();
             // increment epoch
@@ -666,6 +1464,14 @@ fn change_epoch(&self, sender: TransactionSender, epoch: Epoch) -> TransactionRe
 
             self.metadata
                 .set(Metadata::Epoch, Value::Epoch(current_epoch));
+            let accounts: BTreeSet<EthAddress> = self
+                .vesting_schedules
+                .keys()
+                .map(|(account, _)| account)
+                .collect();
+            for account in accounts {
+                self.update_vesting(account, current_epoch);
+            }
             TransactionResponse::Success(ExecutionData::EpochChange)
         } else {
             self.committee_info.set(current_epoch, current_committee);
@@ -673,6 +1479,50 @@ fn change_epoch(&self, sender: TransactionSender, epoch: Epoch) -> TransactionRe
         }
     }
 
+    fn submit_randomness_share(
+        &self,
+        sender: TransactionSender,
+        epoch: Epoch,
+        signature: ConsensusSignature,
+    ) -> TransactionResponse {
+        // Only Nodes can call this function
+        let index = match self.only_node(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+
+        match epoch.cmp(&current_epoch) {
+            Ordering::Less => {
+                return TransactionResponse::Revert(ExecutionError::EpochAlreadyChanged);
+            },
+            Ordering::Greater => {
+                return TransactionResponse::Revert(ExecutionError::EpochHasNotStarted);
+            },
+            _ => (),
+        }
+
+        let current_committee = self.committee_info.get(&current_epoch).unwrap_or_default();
+        if !current_committee.members.contains(&index) {
+            return TransactionResponse::Revert(ExecutionError::NotCommitteeMember);
+        } else if self.randomness_shares.get(&index).is_some() {
+            return TransactionResponse::Revert(ExecutionError::AlreadySignaled);
+        }
+
+        // The share has to be the signature of the node's consensus key over the current seed
+        let node = self.node_info.get(&index).unwrap();
+        let digest = randomness_share_transcript(epoch, &self.get_epoch_randomness()).hash();
+        if !node.consensus_key.verify(&signature, &digest) {
+            return TransactionResponse::Revert(ExecutionError::InvalidSignature);
+        }
+
+        self.randomness_shares.set(index, signature);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
     fn calculate_reputation_scores(&self) {
         let mut rep_scores = HashMap::new();
         self.rep_scores.keys().for_each(|node| {
@@ -736,6 +1586,51 @@ fn calculate_reputation_scores(&self) {
         nodes.for_each(|node| self.rep_measurements.remove(&node));
     }
 
+    /// Counts the consecutive epochs every node spent below the reputation threshold and jails
+    /// the nodes that stayed below it for too many epochs.
+    fn update_jailed_nodes(&self) {
+        let threshold = self
+            .parameters
+            .get(&ProtocolParams::JailReputationThreshold)
+            .unwrap_or(0);
+        let jail_epochs = self
+            .parameters
+            .get(&ProtocolParams::JailEpochs)
+            .unwrap_or(0);
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+
+        for index in self.node_info.keys() {
+            let Some(mut node) = self.node_info.get(&index) else {
+                continue;
+            };
+            if node.jailed_since.is_some() {
+                continue;
+            }
+            // Nodes without a reputation score have not been measured and are not penalized
+            let low_reputation = self
+                .rep_scores
+                .get(&index)
+                .is_some_and(|score| (score as u128) < threshold);
+            let low_reputation_epochs = if low_reputation {
+                node.low_reputation_epochs + 1
+            } else {
+                0
+            };
+            if low_reputation_epochs == node.low_reputation_epochs {
+                continue;
+            }
+
+            node.low_reputation_epochs = low_reputation_epochs;
+            if low_reputation_epochs as u128 >= jail_epochs {
+                node.jailed_since = Some(current_epoch);
+            }
+            self.node_info.set(index, node);
+        }
+    }
+
     fn update_latencies(&self) {
         // Remove latency measurements from invalid nodes.
         let node_registry = self.get_node_registry();
@@ -781,47 +1676,319 @@ fn update_latencies(&self) {
         }
     }
 
-    // This method can panic if the governance address wasn't previously stored in the application
-    // state. The governance address should be seeded though the genesis.
-    fn change_protocol_param(
+    // This method can panic if the governance address wasn't previously stored in the application
+    // state. The governance address should be seeded though the genesis.
+    fn change_protocol_param(
+        &self,
+        sender: TransactionSender,
+        param: ProtocolParams,
+        value: u128,
+    ) -> TransactionResponse {
+        if let Err(e) = self.only_governance(sender) {
+            return e;
+        }
+        self.execute_governance_action(ProposalAction::ChangeProtocolParam { param, value })
+    }
+
+    fn propose(&self, sender: TransactionSender, action: ProposalAction) -> TransactionResponse {
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        // Only node owners with stake can submit proposals
+        let voting_power = self.get_voting_power_at(current_epoch);
+        if voting_power.get(&sender).cloned().unwrap_or_default() == HpUfixed::zero() {
+            return TransactionResponse::Revert(ExecutionError::NoVotingPower);
+        }
+
+        let voting_period = self
+            .parameters
+            .get(&ProtocolParams::VotingPeriod)
+            .unwrap_or(0);
+        let proposal_id = match self.metadata.get(&Metadata::NextProposalId) {
+            Some(Value::NextProposalId(id)) => id,
+            _ => 0,
+        };
+        self.proposals.set(
+            proposal_id,
+            Proposal {
+                proposer: sender,
+                action,
+                voting_starts: current_epoch,
+                voting_ends: current_epoch + voting_period as u64,
+                votes_for: HpUfixed::zero(),
+                votes_against: HpUfixed::zero(),
+                executed: false,
+                total_voting_power: voting_power
+                    .values()
+                    .fold(HpUfixed::zero(), |total, power| total + power.clone()),
+            },
+        );
+        self.metadata.set(
+            Metadata::NextProposalId,
+            Value::NextProposalId(proposal_id + 1),
+        );
+        // The id of the proposal is returned as the receipt
+        TransactionResponse::Success(ExecutionData::UInt(proposal_id.into()))
+    }
+
+    fn vote(
+        &self,
+        sender: TransactionSender,
+        proposal_id: ProposalId,
+        approve: bool,
+    ) -> TransactionResponse {
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        let mut proposal = match self.proposals.get(&proposal_id) {
+            Some(proposal) => proposal,
+            None => return TransactionResponse::Revert(ExecutionError::ProposalDoesNotExist),
+        };
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        if current_epoch >= proposal.voting_ends {
+            return TransactionResponse::Revert(ExecutionError::VotingEnded);
+        }
+        if self.proposal_votes.get(&(proposal_id, sender)).is_some() {
+            return TransactionResponse::Revert(ExecutionError::AlreadyVoted);
+        }
+        // Votes count with the stake at the start of the voting, so moving stake to another
+        // owner during the voting period does not give it a second vote
+        let voting_power = self
+            .get_voting_power_at(proposal.voting_starts)
+            .remove(&sender)
+            .unwrap_or_default();
+        if voting_power == HpUfixed::zero() {
+            return TransactionResponse::Revert(ExecutionError::NoVotingPower);
+        }
+
+        if approve {
+            proposal.votes_for += voting_power;
+        } else {
+            proposal.votes_against += voting_power;
+        }
+        self.proposal_votes.set((proposal_id, sender), approve);
+        self.proposals.set(proposal_id, proposal);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn execute_proposal(
         &self,
         sender: TransactionSender,
-        param: ProtocolParams,
-        value: u128,
+        proposal_id: ProposalId,
     ) -> TransactionResponse {
-        let sender = match self.only_account_owner(sender) {
-            Ok(account) => account,
-            Err(e) => return e,
+        // Anyone can execute a proposal that passed
+        if let Err(e) = self.only_account_owner(sender) {
+            return e;
+        }
+        let mut proposal = match self.proposals.get(&proposal_id) {
+            Some(proposal) => proposal,
+            None => return TransactionResponse::Revert(ExecutionError::ProposalDoesNotExist),
         };
-        // TODO(matthias): should be panic here or revert? Since the governance address will be
-        // seeded though genesis, this should never happen.
-        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
-            Some(Value::AccountPublicKey(address)) => address,
-            _ => panic!("Governance address is missing from state."),
+        if proposal.executed {
+            return TransactionResponse::Revert(ExecutionError::ProposalAlreadyExecuted);
+        }
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
         };
-        if sender != governance_address {
-            return TransactionResponse::Revert(ExecutionError::OnlyGovernance);
+        if current_epoch < proposal.voting_ends {
+            return TransactionResponse::Revert(ExecutionError::VotingNotEnded);
+        }
+
+        // The proposal passes if enough of the total stake voted on it and enough of the votes
+        // approve it
+        let quorum: HpUfixed<18> = self
+            .parameters
+            .get(&ProtocolParams::GovernanceQuorum)
+            .unwrap_or(0)
+            .into();
+        let threshold: HpUfixed<18> = self
+            .parameters
+            .get(&ProtocolParams::GovernanceThreshold)
+            .unwrap_or(0)
+            .into();
+        let total_stake = &proposal.total_voting_power;
+        let total_votes = &proposal.votes_for + &proposal.votes_against;
+        if total_votes == HpUfixed::zero()
+            || &total_votes * &(*BIG_HUNDRED) < total_stake * &quorum
+            || &proposal.votes_for * &(*BIG_HUNDRED) <= &total_votes * &threshold
+        {
+            return TransactionResponse::Revert(ExecutionError::ProposalNotPassed);
+        }
+
+        let response = self.execute_governance_action(proposal.action.clone());
+        if let TransactionResponse::Success(_) = response {
+            proposal.executed = true;
+            self.proposals.set(proposal_id, proposal);
+        }
+        response
+    }
+
+    /// Applies a change to the network that was either submitted by the governance address or
+    /// passed a vote.
+    fn execute_governance_action(&self, action: ProposalAction) -> TransactionResponse {
+        match action {
+            ProposalAction::ChangeProtocolParam { param, value } => {
+                let valid = match param {
+                    // Shares are percentages
+                    ProtocolParams::SlashShare
+                    | ProtocolParams::GovernanceQuorum
+                    | ProtocolParams::GovernanceThreshold
+                    | ProtocolParams::JailReputationThreshold => value <= 100,
+                    // Proposals that can't be voted on could never pass
+                    ProtocolParams::VotingPeriod => value > 0,
+                    _ => true,
+                };
+                if !valid {
+                    return TransactionResponse::Revert(ExecutionError::InvalidProtocolParamValue);
+                }
+                self.parameters.set(param, value);
+            },
+            ProposalAction::AddService {
+                service,
+                service_id,
+            } => {
+                if self.services.get(&service_id).is_some() {
+                    return TransactionResponse::Revert(ExecutionError::ServiceAlreadyExists);
+                }
+                self.services.set(service_id, service);
+            },
+            ProposalAction::RemoveService { service_id } => {
+                if self.services.get(&service_id).is_none() {
+                    return TransactionResponse::Revert(ExecutionError::NonExistingService);
+                }
+                // Nodes that served this service in the current epoch are paid out from its
+                // revenue at the end of the epoch, so the service can only be removed once that
+                // happened.
+                if self.service_revenue.get(&service_id).is_some() {
+                    return TransactionResponse::Revert(ExecutionError::ServiceHasPendingRevenue);
+                }
+                self.services.remove(&service_id);
+                // Drop the prices too, so a service that is added later under the same id
+                // doesn't inherit them.
+                let prices: Vec<(ServiceId, CommodityTypes)> = self
+                    .service_prices
+                    .prefix(&service_id)
+                    .map(|(key, _)| key)
+                    .collect();
+                for key in prices {
+                    self.service_prices.remove(&key);
+                }
+                let price_updates: Vec<(ServiceId, CommodityTypes)> = self
+                    .service_price_updates
+                    .prefix(&service_id)
+                    .map(|(key, _)| key)
+                    .collect();
+                for key in price_updates {
+                    self.service_price_updates.remove(&key);
+                }
+            },
+            ProposalAction::SetServicePrice {
+                service_id,
+                commodity,
+                price,
+            } => {
+                if self.services.get(&service_id).is_none() {
+                    return TransactionResponse::Revert(ExecutionError::NonExistingService);
+                }
+                // Work is paid with the price that applied during the epoch it was done in, so
+                // the new price only applies from the next epoch on.
+                self.service_price_updates
+                    .set((service_id, commodity), price);
+            },
         }
-        self.parameters.set(param, value);
         TransactionResponse::Success(ExecutionData::None)
     }
 
+    /// Returns the voting power of every account at the start of the given epoch, which is the
+    /// stake of all the nodes it owns. Delegated stake votes with the owner of the node it is
+    /// delegated to.
+    fn get_voting_power_at(&self, epoch: Epoch) -> BTreeMap<EthAddress, HpUfixed<18>> {
+        let mut voting_power = BTreeMap::new();
+        for (index, node) in self
+            .node_info
+            .keys()
+            .filter_map(|index| self.node_info.get(&index).map(|node| (index, node)))
+        {
+            // The first snapshot taken since the epoch started holds the stake the node had at its
+            // start, without one the stake did not change since then
+            let staked = self
+                .node_stake_snapshots
+                .prefix(&index)
+                .filter(|((_, snapshot_epoch), _)| *snapshot_epoch >= epoch)
+                .min_by_key(|((_, snapshot_epoch), _)| *snapshot_epoch)
+                .map(|(_, staked)| staked)
+                .unwrap_or(node.stake.staked);
+            *voting_power
+                .entry(node.owner)
+                .or_insert_with(HpUfixed::zero) += staked;
+        }
+        voting_power
+    }
+
+    /// Records the stake of a node at the start of the current epoch, this has to be called before
+    /// its stake changes so votes can be counted with the stake the node had back then.
+    fn snapshot_node_stake(&self, index: NodeIndex, staked: &HpUfixed<18>) {
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        if self
+            .node_stake_snapshots
+            .get(&(index, current_epoch))
+            .is_none()
+        {
+            self.node_stake_snapshots
+                .set((index, current_epoch), staked.clone());
+        }
+    }
+
     fn get_node_registry(&self) -> BTreeMap<NodeIndex, NodeInfo> {
         let minimum_stake = self
             .parameters
             .get(&ProtocolParams::MinimumNodeStake)
             .unwrap_or(0);
         self.node_info
-            .keys()
-            .filter_map(|key| self.node_info.get(&key).map(|node| (key, node)))
-            .filter(|(_, node)| node.stake.staked >= minimum_stake.into())
+            .range(..)
+            .filter(|(_, node)| {
+                node.stake.staked >= minimum_stake.into() && node.jailed_since.is_none()
+            })
             .collect()
     }
 
     // This function should only be called in the `run` method on `Env`.
     pub fn set_last_epoch_hash(&self, state_hash: [u8; 32]) {
-        self.metadata
+        self.local_metadata
             .set(Metadata::LastEpochHash, Value::Hash(state_hash));
+        // The deltas are taken against the new checkpoint from now on.
+        self.local_metadata
+            .set(Metadata::LastEpochDeltaHashes, Value::Hashes(Vec::new()));
+    }
+
+    // This function should only be called in the `run` method on `Env`.
+    pub fn add_last_epoch_delta_hash(&self, delta_hash: [u8; 32]) {
+        let mut delta_hashes = match self.local_metadata.get(&Metadata::LastEpochDeltaHashes) {
+            Some(Value::Hashes(delta_hashes)) => delta_hashes,
+            _ => Vec::new(),
+        };
+        delta_hashes.push(delta_hash);
+        self.local_metadata
+            .set(Metadata::LastEpochDeltaHashes, Value::Hashes(delta_hashes));
+    }
+
+    // This function should only be called in the `run` method on `Env`.
+    pub fn set_epoch_state_root(&self, state_root: [u8; 32]) {
+        self.metadata
+            .set(Metadata::EpochStateRoot, Value::Hash(state_root));
     }
 
     // This function should only be called in the `run` method on `Env`.
@@ -830,47 +1997,159 @@ fn get_node_registry(&self) -> BTreeMap<NodeIndex, NodeInfo> {
             .set(Metadata::LastBlockHash, Value::Hash(block_hash));
     }
 
+    /// Increments the block number and returns the number of the block that is executed.
+    // This function should only be called in the `run` method on `Env`.
+    pub fn increment_block_number(&self) -> u64 {
+        let block_number = match self.metadata.get(&Metadata::BlockNumber) {
+            Some(Value::BlockNumber(number)) => number + 1,
+            _ => 1,
+        };
+        self.metadata
+            .set(Metadata::BlockNumber, Value::BlockNumber(block_number));
+        block_number
+    }
+
+    /// Records the given block as the one in which the previous epoch ended.
+    // This function should only be called in the `run` method on `Env`.
+    pub fn set_epoch_end_block(&self, block_number: u64) {
+        let epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        self.epoch_end_blocks
+            .set(epoch.saturating_sub(1), block_number);
+    }
+
     fn add_service(
         &self,
-        _sender: TransactionSender,
-        _service: Service,
-        _service_id: ServiceId,
+        sender: TransactionSender,
+        service: Service,
+        service_id: ServiceId,
     ) -> TransactionResponse {
-        todo!()
+        if let Err(e) = self.only_governance(sender) {
+            return e;
+        }
+        self.execute_governance_action(ProposalAction::AddService {
+            service,
+            service_id,
+        })
     }
 
     fn remove_service(
         &self,
-        _sender: TransactionSender,
-        _service_id: ServiceId,
+        sender: TransactionSender,
+        service_id: ServiceId,
+    ) -> TransactionResponse {
+        if let Err(e) = self.only_governance(sender) {
+            return e;
+        }
+        self.execute_governance_action(ProposalAction::RemoveService { service_id })
+    }
+
+    fn set_service_price(
+        &self,
+        sender: TransactionSender,
+        service_id: ServiceId,
+        commodity: CommodityTypes,
+        price: HpUfixed<6>,
     ) -> TransactionResponse {
-        todo!()
+        let is_governance = self.only_governance(sender).is_ok();
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        let service = match self.services.get(&service_id) {
+            Some(service) => service,
+            None => return TransactionResponse::Revert(ExecutionError::NonExistingService),
+        };
+        // The service owner can price its own service, governance can price any service
+        if sender != service.owner && !is_governance {
+            return TransactionResponse::Revert(ExecutionError::NotServiceOwner);
+        }
+        self.execute_governance_action(ProposalAction::SetServicePrice {
+            service_id,
+            commodity,
+            price,
+        })
     }
 
     fn slash(
         &self,
         _sender: TransactionSender,
-        _proof: ProofOfMisbehavior,
+        proof: ProofOfMisbehavior,
         _service_id: ServiceId,
-        _node: NodePublicKey,
+        node: NodePublicKey,
     ) -> TransactionResponse {
-        todo!()
+        // Anyone can report a misbehaving node since the proof is signed by the node itself
+        let index = match self.pub_key_to_index.get(&node) {
+            Some(index) => index,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+        if !proof.verify(&node) {
+            return TransactionResponse::Revert(ExecutionError::InvalidProof);
+        }
+
+        // Make sure the same misbehavior can not be slashed twice
+        let misbehavior = (index, proof.to_digest());
+        if self.slashed_misbehaviors.get(&misbehavior).is_some() {
+            return TransactionResponse::Revert(ExecutionError::AlreadySlashed);
+        }
+
+        let mut node_info = self.node_info.get(&index).unwrap();
+        // The share is validated when it is set, clamping it keeps a bad genesis value from
+        // slashing more than the stake.
+        let slash_share: HpUfixed<18> = &(self
+            .parameters
+            .get(&ProtocolParams::SlashShare)
+            .unwrap_or_default()
+            .min(100)
+            .into())
+            / &(*BIG_HUNDRED);
+        let own_stake = &node_info.stake.staked - &node_info.stake.delegated;
+        let mut slashed_staked = &own_stake * &slash_share;
+        // Stake that was unstaked but not withdrawn yet is slashed as well, otherwise unstaking
+        // would be a way out of the punishment
+        let mut slashed_locked = &node_info.stake.locked * &slash_share;
+        node_info.stake.locked -= slashed_locked.clone();
+
+        // Delegators share the slashing in proportion to their stake
+        for ((node, delegator), mut delegation) in self.delegations.prefix(&index) {
+            let slashed_delegation = &delegation.staked * &slash_share;
+            let slashed_delegation_locked = &delegation.locked * &slash_share;
+            delegation.staked -= slashed_delegation.clone();
+            delegation.locked -= slashed_delegation_locked.clone();
+            node_info.stake.delegated -= slashed_delegation.clone();
+            slashed_staked += slashed_delegation;
+            slashed_locked += slashed_delegation_locked;
+            self.delegations.set((node, delegator), delegation);
+        }
+        self.snapshot_node_stake(index, &node_info.stake.staked);
+        node_info.stake.staked -= slashed_staked.clone();
+        self.node_info.set(index, node_info);
+        let slashed_amount = slashed_staked + slashed_locked;
+
+        // The slashed stake goes to the protocol fund
+        let protocol_owner = match self.metadata.get(&Metadata::ProtocolFundAddress) {
+            Some(Value::AccountPublicKey(owner)) => owner,
+            _ => panic!("ProtocolFundAddress is added at Genesis and should exist"),
+        };
+        let mut protocol_account = self.account_info.get(&protocol_owner).unwrap_or_default();
+        protocol_account.flk_balance += slashed_amount.clone();
+        self.account_info.set(protocol_owner, protocol_account);
+
+        self.slashed_misbehaviors
+            .set(misbehavior, slashed_amount.clone());
+        self.emit(TransactionEvent::Slash {
+            node,
+            amount: slashed_amount,
+        });
+        TransactionResponse::Success(ExecutionData::None)
     }
 
     // only for testnet
     fn testnet_kill(&self, sender: TransactionSender) -> TransactionResponse {
-        let sender = match self.only_account_owner(sender) {
-            Ok(account) => account,
-            Err(e) => return e,
-        };
-        // Since the governance address will be
-        // seeded though genesis, this should never happen.
-        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
-            Some(Value::AccountPublicKey(address)) => address,
-            _ => panic!("Governance address is missing from state."),
-        };
-        if sender != governance_address {
-            return TransactionResponse::Revert(ExecutionError::OnlyGovernance);
+        if let Err(e) = self.only_governance(sender) {
+            return e;
         }
         panic!("This testnet phase is over. Thank you for participating.");
     }
@@ -936,6 +2215,19 @@ fn distribute_rewards(&self) {
             .unwrap_or_default()
             .reward_pool;
 
+        // The revenue of every node per service is only kept to record the rewards of the epoch
+        let mut node_service_revenue: HashMap<NodeIndex, BTreeMap<ServiceId, ServiceRevenue>> =
+            HashMap::new();
+        for (node, service_id) in self.node_service_revenue.keys() {
+            if let Some(revenue) = self.node_service_revenue.get(&(node, service_id)) {
+                node_service_revenue
+                    .entry(node)
+                    .or_default()
+                    .insert(service_id, revenue);
+            }
+            self.node_service_revenue.remove(&(node, service_id));
+        }
+
         // if reward is 0, no commodity under any service was served
         if reward_pool == HpUfixed::zero() {
             return;
@@ -953,11 +2245,17 @@ fn distribute_rewards(&self) {
         let mut total_reward_share: HpUfixed<18> = HpUfixed::from(0_u64);
         let mut local_shares_map: HashMap<NodeIndex, HpUfixed<18>> = HashMap::new();
         let mut node_info_map: HashMap<NodeIndex, NodeInfo> = HashMap::new();
+        let mut node_rewards_map: HashMap<NodeIndex, NodeRewards> = HashMap::new();
 
         for node in self.current_epoch_served.keys() {
             // safe to unwrap since all the nodes in current_epoch_served table are in node info
             // this is checked in submit_pod contract/function
             let node_info = self.node_info.get(&node).unwrap();
+            // Nodes that were jailed at this epoch change do not get rewards for the epoch
+            if node_info.jailed_since.is_some() {
+                self.current_epoch_served.remove(&node);
+                continue;
+            }
             node_info_map.insert(node, node_info.clone());
 
             let stables_revenue: HpUfixed<6> = self
@@ -978,19 +2276,50 @@ fn distribute_rewards(&self) {
             let local_share = node_service_proportion * &local_boost.convert_precision();
             total_reward_share = total_reward_share + &local_share;
             local_shares_map.insert(node, local_share);
+
+            let stables = node_service_revenue
+                .remove(&node)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(service_id, revenue)| {
+                    (service_id, revenue * &node_share.convert_precision())
+                })
+                .collect();
+            node_rewards_map.insert(
+                node,
+                NodeRewards {
+                    flk: HpUfixed::zero(),
+                    boost: local_boost,
+                    stables,
+                },
+            );
         }
 
-        let base_reward = &emissions_for_node / &total_reward_share;
+        let base_reward = if node_info_map.is_empty() {
+            HpUfixed::zero()
+        } else {
+            &emissions_for_node / &total_reward_share
+        };
 
         for (node, node_info) in node_info_map.iter() {
             let local_share = local_shares_map.get(node).unwrap();
             let flk_rewards = &base_reward * local_share;
 
+            let mut node_rewards = node_rewards_map.remove(node).unwrap_or_default();
+            node_rewards.flk = flk_rewards.clone();
+            self.node_rewards.set((epoch, *node), node_rewards);
+
             // todo: add service builders and protocols share in stables too
-            self.mint_and_transfer_flk(flk_rewards, node_info.owner);
+            self.distribute_node_flk_rewards(*node, node_info, flk_rewards);
             self.current_epoch_served.remove(node);
         }
 
+        let mut epoch_rewards = EpochRewards {
+            emissions: emissions.clone(),
+            reward_pool: reward_pool.clone(),
+            ..Default::default()
+        };
+
         // todo: add service builders revenue
         let service_share: HpUfixed<18> = &(self
             .parameters
@@ -1005,14 +2334,13 @@ fn distribute_rewards(&self) {
             let service_revenue = self.service_revenue.get(&service_id).unwrap_or_default();
             let revenue_proportion: HpUfixed<18> =
                 &service_revenue.convert_precision() / &reward_pool.convert_precision();
-            self.mint_and_transfer_stables(
-                &services_stable_reward_pool * &revenue_proportion.convert_precision(),
-                service_owner,
-            );
-            self.mint_and_transfer_flk(
-                &services_flk_reward_pool * &revenue_proportion.convert_precision(),
-                service_owner,
-            );
+            let rewards = ServiceBuilderRewards {
+                flk: &services_flk_reward_pool * &revenue_proportion.convert_precision(),
+                stables: &services_stable_reward_pool * &revenue_proportion.convert_precision(),
+            };
+            self.mint_and_transfer_stables(rewards.stables.clone(), service_owner);
+            self.mint_and_transfer_flk(rewards.flk.clone(), service_owner);
+            epoch_rewards.service_builders.insert(service_id, rewards);
             self.service_revenue.remove(&service_id);
         }
 
@@ -1028,11 +2356,11 @@ fn distribute_rewards(&self) {
             Some(Value::AccountPublicKey(owner)) => owner,
             _ => panic!("ProtocolFundAddress is added at Genesis and should exist"),
         };
-        self.mint_and_transfer_stables(
-            &reward_pool * &protocol_share.convert_precision(),
-            protocol_owner,
-        );
-        self.mint_and_transfer_flk(&emissions * &protocol_share, protocol_owner);
+        epoch_rewards.protocol_stables = &reward_pool * &protocol_share.convert_precision();
+        epoch_rewards.protocol_flk = &emissions * &protocol_share;
+        self.mint_and_transfer_stables(epoch_rewards.protocol_stables.clone(), protocol_owner);
+        self.mint_and_transfer_flk(epoch_rewards.protocol_flk.clone(), protocol_owner);
+        self.epoch_rewards.set(epoch, epoch_rewards);
     }
 
     fn calculate_emissions(&self) -> HpUfixed<18> {
@@ -1053,11 +2381,44 @@ fn calculate_emissions(&self) -> HpUfixed<18> {
         (&inflation * &supply_at_year_start.convert_precision()) / &365.0.into()
     }
 
+    /// Splits the FLK rewards of a node between the owner and the delegators of the node. The owner
+    /// first takes their commission, the rest is shared in proportion to the stake of everyone.
+    fn distribute_node_flk_rewards(
+        &self,
+        node: NodeIndex,
+        node_info: &NodeInfo,
+        flk_rewards: HpUfixed<18>,
+    ) {
+        if node_info.stake.delegated == HpUfixed::zero() {
+            self.mint_and_transfer_flk(flk_rewards, node_info.owner);
+            return;
+        }
+
+        let commission: HpUfixed<18> =
+            (self.commissions.get(&node).unwrap_or_default() as u64).into();
+        let delegators_rewards =
+            &flk_rewards * &(&(&(*BIG_HUNDRED) - &commission) / &(*BIG_HUNDRED));
+
+        let mut owner_rewards = flk_rewards;
+        for ((_, delegator), delegation) in self.delegations.prefix(&node) {
+            let delegator_rewards =
+                &delegators_rewards * &(&delegation.staked / &node_info.stake.staked);
+            owner_rewards -= delegator_rewards.clone();
+            self.mint_and_transfer_flk(delegator_rewards, delegator);
+        }
+        self.mint_and_transfer_flk(owner_rewards, node_info.owner);
+    }
+
     fn mint_and_transfer_stables(&self, amount: HpUfixed<6>, owner: EthAddress) {
         let mut account = self.account_info.get(&owner).unwrap_or_default();
 
-        account.stables_balance += amount;
+        account.stables_balance += amount.clone();
         self.account_info.set(owner, account);
+        self.emit(TransactionEvent::Mint {
+            token: Tokens::USDC,
+            to: owner,
+            amount: amount.convert_precision(),
+        });
     }
 
     fn mint_and_transfer_flk(&self, amount: HpUfixed<18>, owner: EthAddress) {
@@ -1065,6 +2426,11 @@ fn mint_and_transfer_flk(&self, amount: HpUfixed<18>, owner: EthAddress) {
         account.flk_balance += amount.clone();
 
         self.account_info.set(owner, account);
+        self.emit(TransactionEvent::Mint {
+            token: Tokens::FLK,
+            to: owner,
+            amount: amount.clone(),
+        });
 
         let mut current_supply = match self.metadata.get(&Metadata::TotalSupply) {
             Some(Value::HpUfixed(supply)) => supply,
@@ -1128,53 +2494,145 @@ fn get_boost(&self, locked_until: u64, current_epoch: &Epoch) -> HpUfixed<3> {
     }
 
     fn choose_new_committee(&self) -> Vec<NodeIndex> {
-        let epoch = match self.metadata.get(&Metadata::Epoch) {
-            Some(Value::Epoch(epoch)) => epoch,
-            _ => 0,
-        };
         let num_of_nodes = match self.metadata.get(&Metadata::NextNodeIndex) {
             Some(Value::NextNodeIndex(index)) => index,
             _ => 0,
         };
         let committee_size = self.parameters.get(&ProtocolParams::CommitteeSize).unwrap();
-        // if total number of nodes are less than committee size, all nodes are part of committee
+        // if total number of nodes are less than committee size, all nodes that are not jailed
+        // are part of committee
         if committee_size >= num_of_nodes.into() {
-            return (0..num_of_nodes).collect();
-        }
-
-        let committee = self.committee_info.get(&epoch).unwrap();
-        let epoch_end = committee.epoch_end_timestamp;
-        let public_key = {
-            if !committee.members.is_empty() {
-                let mid_index = committee.members.len() / 2;
-                self.node_info
-                    .get(&committee.members[mid_index])
-                    .unwrap()
-                    .public_key
+            return (0..num_of_nodes)
+                .filter(|index| {
+                    self.node_info
+                        .get(index)
+                        .is_some_and(|node| node.jailed_since.is_none())
+                })
+                .collect();
+        }
+
+        // Every node is weighted by its stake and reputation, nodes without a reputation score
+        // get the same default score that is used when computing reputation scores.
+        let rep_scores: Vec<u8> = self
+            .rep_scores
+            .keys()
+            .filter_map(|node| self.rep_scores.get(&node))
+            .collect();
+        let default_score =
+            statistics::approx_quantile(rep_scores, DEFAULT_REP_QUANTILE).unwrap_or(15);
+        // The weights are whole FLK that fit in a u64 times a score below 256, so the weights of
+        // all the nodes add up exactly in a u128 and every weight is at least one.
+        let mut candidates: Vec<(NodeIndex, u128)> = self
+            .get_node_registry()
+            .into_iter()
+            .map(|(index, node)| {
+                let stake: u64 = node.stake.staked.try_into().unwrap_or(u64::MAX);
+                let score = self.rep_scores.get(&index).unwrap_or(default_score);
+                (index, stake.max(1) as u128 * (score as u128 + 1))
+            })
+            .collect();
+        let mut total_weight: u128 = candidates.iter().map(|(_, weight)| weight).sum();
+
+        // Sample the committee without replacement, the randomness of every draw is derived from
+        // the epoch seed so anyone can reproduce the committee from the state.
+        let seed = self.get_epoch_randomness();
+        let mut committee = Vec::new();
+        for draw in 0..committee_size.min(candidates.len() as u128) {
+            let mut hasher = Hasher::new();
+            hasher.update(&seed);
+            hasher.update(&(draw as u64).to_be_bytes());
+            let result = hasher.finalize();
+            let target =
+                u128::from_be_bytes(result.as_bytes()[0..16].try_into().unwrap()) % total_weight;
+
+            let mut cumulative_weight = 0u128;
+            let position = candidates
+                .iter()
+                .position(|(_, weight)| {
+                    cumulative_weight += weight;
+                    target < cumulative_weight
+                })
+                .unwrap_or(candidates.len() - 1);
+            let (index, weight) = candidates.remove(position);
+            total_weight -= weight;
+            committee.push(index);
+        }
+        committee
+    }
+
+    /// Returns the randomness seed of the current epoch.
+    fn get_epoch_randomness(&self) -> [u8; 32] {
+        match self.metadata.get(&Metadata::EpochRandomness) {
+            Some(Value::Hash(seed)) => seed,
+            _ => [0; 32],
+        }
+    }
+
+    /// Called internally at the end of an epoch to derive the randomness seed of the next epoch
+    /// from the current seed and the shares submitted by the committee.
+    ///
+    /// BLS signatures are unique, so the consensus key of a member commits it to its share and the
+    /// only way to bias the seed is to withhold the share once the others are known. Members that
+    /// didn't reveal their share before the epoch changed are jailed.
+    fn update_epoch_randomness(&self, epoch: Epoch) {
+        // Members that did not reveal their share are left out of the randomness. A single miss
+        // can be an outage, only nodes that keep missing their shares are jailed.
+        let jail_epochs = self
+            .parameters
+            .get(&ProtocolParams::JailEpochs)
+            .unwrap_or(0);
+        let committee = self.committee_info.get(&epoch).unwrap_or_default();
+        for index in committee.members {
+            let Some(mut node) = self.node_info.get(&index) else {
+                continue;
+            };
+            let missed_randomness_shares = if self.randomness_shares.get(&index).is_some() {
+                0
             } else {
-                NodePublicKey([1u8; 32])
+                node.missed_randomness_shares + 1
+            };
+            if missed_randomness_shares == node.missed_randomness_shares {
+                continue;
             }
-        };
+            node.missed_randomness_shares = missed_randomness_shares;
+            if missed_randomness_shares as u128 >= jail_epochs && node.jailed_since.is_none() {
+                node.jailed_since = Some(epoch);
+            }
+            self.node_info.set(index, node);
+        }
+
+        let mut shares: Vec<NodeIndex> = self.randomness_shares.keys().collect();
+        shares.sort();
 
         let mut hasher = Hasher::new();
-        hasher.update(&public_key.0);
-        hasher.update(&epoch_end.to_be_bytes());
+        hasher.update(&self.get_epoch_randomness());
+        hasher.update(&epoch.to_be_bytes());
+        for index in shares {
+            if let Some(signature) = self.randomness_shares.get(&index) {
+                hasher.update(&index.to_be_bytes());
+                hasher.update(&signature.0);
+            }
+            self.randomness_shares.remove(&index);
+        }
         let result = hasher.finalize();
-        let mut seed = [0u8; 32];
-        seed.copy_from_slice(&result.as_bytes()[0..32]);
-        let mut rng: StdRng = SeedableRng::from_seed(seed);
-        let mut node_registry: Vec<NodeIndex> = self.get_node_registry().keys().copied().collect();
-        node_registry.shuffle(&mut rng);
-        node_registry
-            .into_iter()
-            .take(committee_size.try_into().unwrap())
-            .collect()
+        self.metadata
+            .set(Metadata::EpochRandomness, Value::Hash(*result.as_bytes()));
     }
 
     /// This function takes in the Transaction and verifies the Signature matches the Sender. It
-    /// also checks the nonce of the sender and makes sure it is equal to the account nonce + 1,
-    /// to prevent replay attacks and enforce ordering
+    /// checks that the payload was signed for this network and in a supported version, and that
+    /// the nonce of the sender is equal to the account nonce + 1, to prevent replay attacks and
+    /// enforce ordering
     pub fn verify_transaction(&self, txn: &UpdateRequest) -> Result<(), ExecutionError> {
+        // Check that the transaction was signed for this network and in a format we understand
+        if txn.payload.version != UPDATE_PAYLOAD_VERSION {
+            return Err(ExecutionError::UnsupportedPayloadVersion);
+        }
+        match self.metadata.get(&Metadata::ChainId) {
+            Some(Value::ChainId(chain_id)) if chain_id == txn.payload.chain_id => {},
+            _ => return Err(ExecutionError::InvalidChainId),
+        }
+
         // Check nonce
         match txn.sender {
             // Todo Sunday(dalton): Clean up this match nesting
@@ -1220,21 +2678,58 @@ pub fn verify_transaction(&self, txn: &UpdateRequest) -> Result<(), ExecutionErr
         Ok(())
     }
 
-    /// Takes in a zk Proof Of Delivery and returns true if valid
+    /// Takes in the delivery acknowledgments of a proof of delivery and returns the sequence
+    /// number of the last one if there are any, they are all signed by the client, acknowledge
+    /// the given commodity of the service to the node in total, and have not been used before.
     fn verify_proof_of_delivery(
         &self,
-        _client: &EthAddress,
-        _provider: &NodeIndex,
-        _commodity: &u128,
-        _service_id: &u32,
-        _proof: (),
-    ) -> bool {
-        true
+        client: &ClientPublicKey,
+        provider_key: &NodePublicKey,
+        provider: &NodeIndex,
+        commodity: &u128,
+        service_id: &u32,
+        acknowledgments: &[DeliveryAcknowledgment],
+    ) -> Option<u64> {
+        // An empty aggregation proves nothing, it would only probe the escrow
+        if acknowledgments.is_empty() {
+            return None;
+        }
+        let mut sequence = self.delivery_ack_sequences.get(&(*client, *provider));
+        let mut total: u128 = 0;
+        for ack in acknowledgments {
+            if ack.node != *provider_key
+                || ack.service_id != *service_id
+                || sequence.is_some_and(|sequence| ack.sequence <= sequence)
+                || !client.verify(&ack.signature, &ack.to_digest())
+            {
+                return None;
+            }
+            sequence = Some(ack.sequence);
+            total = total.checked_add(ack.commodity)?;
+        }
+        (total == *commodity).then_some(sequence).flatten()
     }
 
-    /// Takes in a zk Proof Of Consensus and returns true if valid
-    fn verify_proof_of_consensus(&self, _proof: ProofOfConsensus) -> bool {
-        true
+    /// Takes in a Proof Of Consensus and returns true if more than 2/3 of the bridge validators
+    /// signed the deposit event.
+    fn verify_proof_of_consensus(&self, proof: &ProofOfConsensus) -> bool {
+        let validators = match self.metadata.get(&Metadata::BridgeValidators) {
+            Some(Value::BridgeValidators(validators)) => validators,
+            _ => return false,
+        };
+        if validators.is_empty() {
+            return false;
+        }
+
+        let digest = proof.event.to_digest();
+        let mut signers = BTreeSet::new();
+        for (validator, signature) in &proof.signatures {
+            if !validators.contains(validator) || !validator.verify(signature, &digest) {
+                return false;
+            }
+            signers.insert(*validator);
+        }
+        signers.len() > 2 * validators.len() / 3
     }
 
     /// Creates a new node. A new node should only be created through this function.
@@ -1279,6 +2774,107 @@ fn remove_node(&self, node: NodePublicKey) -> bool {
         }
     }
 
+    /// Replaces the public key of a node. The public key of a node should only be changed
+    /// through this function.
+    fn rotate_node_key(&self, index: NodeIndex, mut node: NodeInfo, key: NodePublicKey) {
+        self.pub_key_to_index.remove(&node.public_key);
+        self.pub_key_to_index.set(key, index);
+        node.public_key = key;
+        self.node_info.set(index, node);
+    }
+
+    /// Returns true if the consensus key is used by a node or is waiting to replace the
+    /// consensus key of a node at the next epoch.
+    fn is_consensus_key_taken(&self, key: &ConsensusPublicKey) -> bool {
+        self.consensus_key_to_index.get(key).is_some()
+            || self
+                .node_info_updates
+                .keys()
+                .filter_map(|index| self.node_info_updates.get(&index))
+                .any(|update| update.consensus_key.as_ref() == Some(key))
+    }
+
+    /// Called internally at the start of every epoch to apply the node info updates that were
+    /// submitted during the previous epoch.
+    fn apply_node_info_updates(&self) {
+        let pending: Vec<NodeIndex> = self.node_info_updates.keys().collect();
+        for index in pending {
+            let update = self.node_info_updates.get(&index).unwrap_or_default();
+            self.node_info_updates.remove(&index);
+            // The node might have been removed in the meantime.
+            let Some(mut node) = self.node_info.get(&index) else {
+                continue;
+            };
+            if let Some(domain) = update.domain {
+                node.domain = domain;
+            }
+            if let Some(worker_domain) = update.worker_domain {
+                node.worker_domain = worker_domain;
+            }
+            if let Some(worker_public_key) = update.worker_public_key {
+                node.worker_public_key = worker_public_key;
+            }
+            if let Some(ports) = update.ports {
+                node.ports = ports;
+            }
+            if let Some(consensus_key) = update.consensus_key {
+                self.consensus_key_to_index.remove(&node.consensus_key);
+                self.consensus_key_to_index.set(consensus_key, index);
+                node.consensus_key = consensus_key;
+            }
+            self.node_info.set(index, node);
+        }
+    }
+
+    /// Makes sure unstaked FLK withdrawn to another account doesn't carry unvested FLK with it.
+    /// Withdrawing to another account is treated like withdrawing to the owner followed by a
+    /// transfer, so the owner's balance has to keep covering its unvested FLK.
+    fn check_unstaked_recipient(
+        &self,
+        owner: EthAddress,
+        recipient: EthAddress,
+        amount: &HpUfixed<18>,
+    ) -> Result<(), TransactionResponse> {
+        if recipient == owner {
+            return Ok(());
+        }
+        let mut account = self.account_info.get(&owner).unwrap_or_default();
+        account.flk_balance += amount.clone();
+        check_spendable_flk(&account, amount)
+    }
+
+    /// Refreshes the vested and unvested FLK of an account from its vesting schedules, a
+    /// schedule is removed once everything in it has vested.
+    fn update_vesting(&self, account: EthAddress, epoch: Epoch) {
+        let mut unvested = HpUfixed::<18>::zero();
+        let schedules: Vec<((EthAddress, u64), VestingSchedule)> =
+            self.vesting_schedules.prefix(&account).collect();
+        for (key, schedule) in schedules {
+            let left = &schedule.amount - &schedule.vested_at(epoch);
+            if left == HpUfixed::zero() {
+                self.vesting_schedules.remove(&key);
+            } else {
+                unvested += left;
+            }
+        }
+        let mut account_info = self.account_info.get(&account).unwrap_or_default();
+        account_info.vested_flk += &account_info.unvested_flk - &unvested;
+        account_info.unvested_flk = unvested;
+        self.account_info.set(account, account_info);
+    }
+
+    /// Called internally at the start of every epoch to apply the service prices that were set
+    /// during the previous epoch.
+    fn apply_service_price_updates(&self) {
+        let pending: Vec<(ServiceId, CommodityTypes)> = self.service_price_updates.keys().collect();
+        for key in pending {
+            if let Some(price) = self.service_price_updates.get(&key) {
+                self.service_prices.set(key, price);
+            }
+            self.service_price_updates.remove(&key);
+        }
+    }
+
     /// Called internally at the end of every transaction to increment the senders nonce.
     /// This happens even if the transaction reverts unless it reverts do to an invalid signature or
     /// nonce This function reverts if the sender does not exist so should be created before
@@ -1298,7 +2894,7 @@ fn increment_nonce(&self, sender: TransactionSender) {
                 self.node_info.set(index, node_info);
             },
             TransactionSender::AccountOwner(account) => {
-                let mut account_info = self.account_info.get(&account).unwrap();
+                let mut account_info = self.account_info.get(&account).unwrap_or_default();
                 account_info.nonce += 1;
                 self.account_info.set(account, account_info);
             },
@@ -1336,6 +2932,22 @@ fn only_node(&self, sender: TransactionSender) -> Result<NodeIndex, TransactionR
             _ => Err(TransactionResponse::Revert(ExecutionError::OnlyNode)),
         }
     }
+    // Useful for transactions that only the governance address can call
+    // Panics if the governance address is missing from state, it is seeded through the genesis
+    fn only_governance(
+        &self,
+        sender: TransactionSender,
+    ) -> Result<EthAddress, TransactionResponse> {
+        let sender = self.only_account_owner(sender)?;
+        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
+            Some(Value::AccountPublicKey(address)) => address,
+            _ => panic!("Governance address is missing from state."),
+        };
+        if sender != governance_address {
+            return Err(TransactionResponse::Revert(ExecutionError::OnlyGovernance));
+        }
+        Ok(sender)
+    }
 
     fn get_node_info(&self, sender: TransactionSender) -> Option<(NodeIndex, NodeInfo)> {
         match sender {
@@ -1354,3 +2966,48 @@ fn get_node_info(&self, sender: TransactionSender) -> Option<(NodeIndex, NodeInf
         }
     }
 }
+
+/// Makes sure the account can spend the given amount of FLK. The unvested part of the balance can
+/// be staked but not transferred or withdrawn.
+fn check_spendable_flk(
+    account: &AccountInfo,
+    amount: &HpUfixed<18>,
+) -> Result<(), TransactionResponse> {
+    if account.flk_balance < *amount {
+        return Err(TransactionResponse::Revert(
+            ExecutionError::InsufficientBalance,
+        ));
+    }
+    if &account.flk_balance - amount < account.unvested_flk {
+        return Err(TransactionResponse::Revert(
+            ExecutionError::InsufficientVestedBalance,
+        ));
+    }
+    Ok(())
+}
+
+/// Returns true if the address can be used by other nodes to reach a node.
+fn is_valid_node_address(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            !(address.is_unspecified() || address.is_multicast() || address.is_broadcast())
+        },
+        IpAddr::V6(address) => !(address.is_unspecified() || address.is_multicast()),
+    }
+}
+
+/// Returns true if none of the ports is zero and every endpoint uses its own port.
+fn are_valid_node_ports(ports: &NodePorts) -> bool {
+    let ports = [
+        ports.primary,
+        ports.worker,
+        ports.mempool,
+        ports.rpc,
+        ports.pool,
+        ports.dht,
+        ports.handshake,
+        ports.blockstore,
+    ];
+    let unique: BTreeSet<u16> = ports.iter().copied().collect();
+    !ports.contains(&0) && unique.len() == ports.len()
+}
@@ -1,6 +1,7 @@
+use std::ops::Bound;
 use std::path::PathBuf;
 
-use atomo::{InMemoryStorage, StorageBackend, StorageBackendConstructor};
+use atomo::{InMemoryStorage, StorageBackend, StorageBackendConstructor, StorageIterator};
 use atomo_rocks::{Options, RocksBackend, RocksBackendBuilder};
 
 pub enum AtomoStorageBuilder {
@@ -52,6 +53,28 @@ pub fn with_table_option(self, name: &str, opts: Options) -> Self {
             },
         }
     }
+
+    #[inline(always)]
+    pub fn with_checkpoint_deltas(self, deltas: Vec<([u8; 32], Vec<u8>)>) -> Self {
+        match self {
+            AtomoStorageBuilder::InMemory(builder) => AtomoStorageBuilder::InMemory(builder),
+            AtomoStorageBuilder::RocksDb(builder) => {
+                let builder = builder.with_checkpoint_deltas(deltas);
+                AtomoStorageBuilder::RocksDb(builder)
+            },
+        }
+    }
+
+    #[inline(always)]
+    pub fn with_delta_tracking(self) -> Self {
+        match self {
+            AtomoStorageBuilder::InMemory(builder) => AtomoStorageBuilder::InMemory(builder),
+            AtomoStorageBuilder::RocksDb(builder) => {
+                let builder = builder.with_delta_tracking();
+                AtomoStorageBuilder::RocksDb(builder)
+            },
+        }
+    }
 }
 
 impl StorageBackendConstructor for AtomoStorageBuilder {
@@ -92,6 +115,15 @@ pub fn serialize(&self) -> Option<Vec<u8>> {
             AtomoStorage::RocksDb(storage) => Some(storage.serialize()),
         }
     }
+
+    /// Serializes the changes since the last checkpoint, promising the given state root for the
+    /// state they lead to.
+    pub fn serialize_delta(&self, state_root: [u8; 32]) -> anyhow::Result<Option<Vec<u8>>> {
+        match &self {
+            AtomoStorage::InMemory(_storage) => Ok(None),
+            AtomoStorage::RocksDb(storage) => storage.serialize_delta(state_root).map(Some),
+        }
+    }
 }
 
 impl StorageBackend for AtomoStorage {
@@ -109,6 +141,13 @@ fn keys(&self, tid: u8) -> Vec<atomo::batch::BoxedVec> {
         }
     }
 
+    fn range<'a>(&'a self, tid: u8, from: Bound<&[u8]>, to: Bound<&[u8]>) -> StorageIterator<'a> {
+        match &self {
+            AtomoStorage::InMemory(storage) => storage.range(tid, from, to),
+            AtomoStorage::RocksDb(storage) => storage.range(tid, from, to),
+        }
+    }
+
     fn get(&self, tid: u8, key: &[u8]) -> Option<Vec<u8>> {
         match &self {
             AtomoStorage::InMemory(storage) => storage.get(tid, key),
@@ -1,8 +1,16 @@
 use std::any::Any;
 use std::cell::RefCell;
 use std::hash::Hash;
+use std::ops::RangeBounds;
 
-use atomo::{KeyIterator, SerdeBackend, StorageBackend, TableRef as AtomoTableRef, TableSelector};
+use atomo::{
+    KeyIterator,
+    Savepoint,
+    SerdeBackend,
+    StorageBackend,
+    TableRef as AtomoTableRef,
+    TableSelector,
+};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
@@ -17,12 +25,27 @@ fn get_table_reference<
         &self,
         id: &str,
     ) -> Self::Ref<K, V>;
+
+    /// Marks the current state of the tables so the writes made after it can be undone using
+    /// [`Backend::rollback_to`]. Savepoints can be nested.
+    fn savepoint(&self) -> Savepoint;
+
+    /// Undoes the writes made since the savepoint was created, the savepoint stays active.
+    fn rollback_to(&self, savepoint: Savepoint);
+
+    /// Keeps the writes made since the savepoint was created and drops the savepoint.
+    fn release(&self, savepoint: Savepoint);
 }
 
 pub trait TableRef<K, V> {
     fn set(&self, key: K, value: V);
     fn get(&self, key: &K) -> Option<V>;
     fn keys(&self) -> KeyIterator<K>;
+    /// Returns the entries whose keys are within the range, ordered by the serialized keys.
+    fn range(&self, range: impl RangeBounds<K>) -> Box<dyn Iterator<Item = (K, V)> + '_>;
+    /// Returns the entries whose serialized keys start with the serialized prefix, for tuple keys
+    /// these are the entries that share their leading elements.
+    fn prefix<P: Serialize>(&self, prefix: &P) -> Box<dyn Iterator<Item = (K, V)> + '_>;
     fn remove(&self, key: &K);
 }
 
@@ -45,6 +68,18 @@ fn get_table_reference<
     ) -> Self::Ref<K, V> {
         AtomoTable(RefCell::new(self.table_selector.get_table(id)))
     }
+
+    fn savepoint(&self) -> Savepoint {
+        self.table_selector.savepoint()
+    }
+
+    fn rollback_to(&self, savepoint: Savepoint) {
+        self.table_selector.rollback_to(savepoint)
+    }
+
+    fn release(&self, savepoint: Savepoint) {
+        self.table_selector.release(savepoint)
+    }
 }
 
 pub struct AtomoTable<
@@ -75,6 +110,14 @@ fn keys(&self) -> KeyIterator<K> {
         self.0.borrow_mut().keys()
     }
 
+    fn range(&self, range: impl RangeBounds<K>) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        Box::new(self.0.borrow().range(range))
+    }
+
+    fn prefix<P: Serialize>(&self, prefix: &P) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        Box::new(self.0.borrow().prefix(prefix))
+    }
+
     fn remove(&self, key: &K) {
         self.0.borrow_mut().remove(key)
     }
@@ -1,35 +1,64 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
 use std::time::SystemTime;
 use std::vec;
 
 use affair::Socket;
 use anyhow::{anyhow, Result};
+use atomo::{DefaultSerdeBackend, SerdeBackend};
+use fleek_blake3::Hasher;
 use fleek_crypto::{
     AccountOwnerSecretKey,
+    ClientPublicKey,
+    ClientSignature,
     ConsensusPublicKey,
     ConsensusSecretKey,
+    EthAddress,
     NodePublicKey,
     NodeSecretKey,
     SecretKey,
 };
 use hp_fixed::unsigned::HpUfixed;
+use lazy_static::lazy_static;
 use lightning_interfaces::application::ExecutionEngineSocket;
 use lightning_interfaces::infu_collection::Collection;
 use lightning_interfaces::types::{
+    randomness_share_transcript,
+    sequenced_broadcast_transcript,
+    AccountInfo,
     Block,
     BlockExecutionResponse,
+    ChainId,
+    CommodityTypes,
     DeliveryAcknowledgment,
+    DepositEvent,
     Epoch,
+    ExecutionData,
     ExecutionError,
+    KeyPrefix,
+    NodeInfo,
     NodePorts,
+    NodeRewards,
+    PendingWithdrawal,
     ProofOfConsensus,
+    ProofOfMisbehavior,
+    ProposalAction,
     ProtocolParams,
+    Service,
+    SignedBroadcastMessage,
+    StateChange,
+    TableEntry,
     Tokens,
+    Topic,
     TotalServed,
+    TransactionEvent,
+    TransactionReceipt,
     TransactionResponse,
     UpdateMethod,
     UpdatePayload,
     UpdateRequest,
+    VestingSchedule,
+    UPDATE_PAYLOAD_VERSION,
 };
 use lightning_interfaces::{partial, ApplicationInterface, SyncQueryRunnerInterface, ToDigest};
 use lightning_test_utils::{random, reputation};
@@ -37,13 +66,19 @@
 
 use crate::app::Application;
 use crate::config::{Config, Mode, StorageConfig};
-use crate::genesis::{Genesis, GenesisNode};
+use crate::env::Env;
+use crate::genesis::{Genesis, GenesisAccount, GenesisNode, GenesisVesting};
 use crate::query_runner::QueryRunner;
 
 partial!(TestBinding {
     ApplicationInterface = Application<Self>;
 });
 
+lazy_static! {
+    static ref BRIDGE_VALIDATORS: Vec<AccountOwnerSecretKey> =
+        (0..4).map(|_| AccountOwnerSecretKey::generate()).collect();
+}
+
 pub struct Params {
     epoch_time: Option<u64>,
     max_inflation: Option<u16>,
@@ -56,9 +91,9 @@ pub struct Params {
 
 #[derive(Clone)]
 struct GenesisCommitteeKeystore {
-    _owner_secret_key: AccountOwnerSecretKey,
+    owner_secret_key: AccountOwnerSecretKey,
     node_secret_key: NodeSecretKey,
-    _consensus_secret_key: ConsensusSecretKey,
+    consensus_secret_key: ConsensusSecretKey,
     _worker_secret_key: NodeSecretKey,
 }
 
@@ -113,10 +148,10 @@ fn add_to_committee(
         true,
     ));
     keystore.push(GenesisCommitteeKeystore {
-        _owner_secret_key: owner_secret_key,
+        owner_secret_key,
         _worker_secret_key: node_secret_key.clone(),
         node_secret_key,
-        _consensus_secret_key: consensus_secret_key,
+        consensus_secret_key,
     });
 }
 
@@ -149,17 +184,31 @@ fn get_new_committee(
 // Init the app and return the execution engine socket that would go to narwhal and the query socket
 // that could go to anyone
 fn init_app(config: Option<Config>) -> (ExecutionEngineSocket, QueryRunner) {
-    let config = config.or(Some(Config {
-        genesis: None,
-        mode: Mode::Dev,
-        testnet: false,
-        storage: StorageConfig::InMemory,
-        db_path: None,
-        db_options: None,
-    }));
+    let mut config = config.unwrap_or_else(|| {
+        let mut genesis = Genesis::load().unwrap();
+        genesis.epoch_start = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        Config {
+            genesis: Some(genesis),
+            mode: Mode::Test,
+            testnet: false,
+            storage: StorageConfig::InMemory,
+            db_path: None,
+            db_options: None,
+            history_retention: 0,
+        }
+    });
+    // Every test network trusts the same bridge validators so deposits can be signed.
+    if let Some(genesis) = config.genesis.as_mut() {
+        genesis.bridge_validators = BRIDGE_VALIDATORS
+            .iter()
+            .map(|secret_key| secret_key.to_pk().into())
+            .collect();
+    }
     let app =
-        Application::<TestBinding>::init(config.unwrap(), Default::default(), Default::default())
-            .unwrap();
+        Application::<TestBinding>::init(config, Default::default(), Default::default()).unwrap();
 
     (app.transaction_executor(), app.sync_query())
 }
@@ -212,6 +261,7 @@ fn init_app_with_params(
         storage: StorageConfig::InMemory,
         db_path: None,
         db_options: None,
+        history_retention: 0,
     };
 
     init_app(Some(config))
@@ -223,6 +273,30 @@ async fn simple_epoch_change(
     update_socket: &Socket<Block, BlockExecutionResponse>,
     query_runner: &QueryRunner,
 ) -> Result<()> {
+    // Reveal the randomness shares first like the consensus does, members that keep missing them
+    // are jailed
+    let seed = query_runner.get_epoch_randomness_seed();
+    let members = query_runner.get_committee_members();
+    for node in committee_keystore
+        .iter()
+        .filter(|node| members.contains(&node.node_secret_key.to_pk()))
+    {
+        let nonce = query_runner
+            .get_node_info(&node.node_secret_key.to_pk())
+            .unwrap()
+            .nonce
+            + 1;
+        let signature = node
+            .consensus_secret_key
+            .sign(&randomness_share_transcript(epoch, &seed).hash());
+        let req = get_update_request_node(
+            UpdateMethod::SubmitRandomnessShare { epoch, signature },
+            &node.node_secret_key,
+            nonce,
+        );
+        run_transaction(vec![req], update_socket).await?;
+    }
+
     let required_signals = 2 * committee_keystore.len() / 3 + 1;
     // make call epoch change for 2/3rd committe members
     for (index, node) in committee_keystore.iter().enumerate().take(required_signals) {
@@ -253,7 +327,12 @@ fn get_update_request_node(
     secret_key: &NodeSecretKey,
     nonce: u64,
 ) -> UpdateRequest {
-    let payload = UpdatePayload { nonce, method };
+    let payload = UpdatePayload {
+        version: UPDATE_PAYLOAD_VERSION,
+        chain_id: TEST_CHAIN_ID,
+        nonce,
+        method,
+    };
     let digest = payload.to_digest();
     let signature = secret_key.sign(&digest);
     UpdateRequest {
@@ -269,7 +348,12 @@ fn get_update_request_account(
     secret_key: &AccountOwnerSecretKey,
     nonce: u64,
 ) -> UpdateRequest {
-    let payload = UpdatePayload { nonce, method };
+    let payload = UpdatePayload {
+        version: UPDATE_PAYLOAD_VERSION,
+        chain_id: TEST_CHAIN_ID,
+        nonce,
+        method,
+    };
     let digest = payload.to_digest();
     let signature = secret_key.sign(&digest);
     UpdateRequest {
@@ -284,20 +368,67 @@ fn get_genesis() -> (Genesis, Vec<GenesisNode>) {
 
     (genesis.clone(), genesis.node_info)
 }
+/// The key of the client paying for the pods built by [`pod_request`].
+const TEST_CLIENT_SECRET_KEY: &str = "-----BEGIN LIGHTNING BLS12_381 PRIVATE KEY-----
+BwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwc=
+-----END LIGHTNING BLS12_381 PRIVATE KEY-----
+";
+/// The chain id of the genesis used by the tests.
+const TEST_CHAIN_ID: ChainId = 1337;
+
 // Helper methods for tests
 // Passing the private key around like this should only be done for
 // testing.
+fn test_client() -> ClientPublicKey {
+    ClientPublicKey(
+        ConsensusSecretKey::decode_pem(TEST_CLIENT_SECRET_KEY)
+            .unwrap()
+            .to_pk()
+            .0,
+    )
+}
+
+// Helper method to get an acknowledgment of the test client. Passing the private key around like
+// this should only be done for testing.
+fn delivery_acknowledgment(
+    secret_key: &ConsensusSecretKey,
+    node: NodePublicKey,
+    commodity: u128,
+    service_id: u32,
+    sequence: u64,
+) -> DeliveryAcknowledgment {
+    let mut ack = DeliveryAcknowledgment {
+        node,
+        service_id,
+        commodity,
+        sequence,
+        signature: ClientSignature([0; 48]),
+    };
+    ack.signature = ClientSignature(secret_key.sign(&ack.to_digest()).0);
+    ack
+}
+
 fn pod_request(
     secret_key: &NodeSecretKey,
     commodity: u128,
     service_id: u32,
     nonce: u64,
 ) -> UpdateRequest {
+    // The nonce of the node increases with every transaction, so it can be used as the sequence
+    // of the acknowledgment.
+    let ack = delivery_acknowledgment(
+        &ConsensusSecretKey::decode_pem(TEST_CLIENT_SECRET_KEY).unwrap(),
+        secret_key.to_pk(),
+        commodity,
+        service_id,
+        nonce,
+    );
     get_update_request_node(
         UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+            client: test_client(),
             commodity,  // units of data served
             service_id, // service 0 serving bandwidth
-            proofs: vec![DeliveryAcknowledgment],
+            proofs: vec![ack],
             metadata: None,
         },
         secret_key,
@@ -319,6 +450,29 @@ async fn run_transaction(
     Ok(res)
 }
 
+// Returns a proof for a new deposit event on the L2 that is signed by the given bridge
+// validators.
+fn get_proof_of_consensus(
+    recipient: EthAddress,
+    token: Tokens,
+    amount: HpUfixed<18>,
+    validators: &[AccountOwnerSecretKey],
+) -> ProofOfConsensus {
+    let event = DepositEvent {
+        tx_hash: rand::random(),
+        log_index: 0,
+        recipient,
+        token,
+        amount,
+    };
+    let digest = event.to_digest();
+    let signatures = validators
+        .iter()
+        .map(|secret_key| (secret_key.to_pk().into(), secret_key.sign(&digest)))
+        .collect();
+    ProofOfConsensus { event, signatures }
+}
+
 async fn deposit(
     amount: HpUfixed<18>,
     token: Tokens,
@@ -329,7 +483,12 @@ async fn deposit(
     // Deposit some FLK into account 1
     let req = get_update_request_account(
         UpdateMethod::Deposit {
-            proof: ProofOfConsensus {},
+            proof: get_proof_of_consensus(
+                secret_key.to_pk().into(),
+                token.clone(),
+                amount.clone(),
+                &BRIDGE_VALIDATORS,
+            ),
             token,
             amount,
         },
@@ -339,6 +498,21 @@ async fn deposit(
     run_transaction(vec![req], update_socket).await.unwrap();
 }
 
+/// Deposits stables for a new account and moves them into the escrow of [`test_client`].
+async fn fund_test_client(amount: u128, update_socket: &Socket<Block, BlockExecutionResponse>) {
+    let secret_key = AccountOwnerSecretKey::generate();
+    deposit(amount.into(), Tokens::USDC, &secret_key, update_socket, 1).await;
+    let req = get_update_request_account(
+        UpdateMethod::FundClient {
+            client: test_client(),
+            amount,
+        },
+        &secret_key,
+        2,
+    );
+    run_transaction(vec![req], update_socket).await.unwrap();
+}
+
 async fn stake_lock(
     locked_for: u64,
     node: NodePublicKey,
@@ -413,6 +587,7 @@ async fn test_epoch_change() {
         storage: StorageConfig::InMemory,
         db_path: None,
         db_options: None,
+        history_retention: 0,
     }));
 
     let required_signals = 2 * committee_size / 3 + 1;
@@ -433,6 +608,10 @@ async fn test_epoch_change() {
     // check that the current epoch is still 0
     assert_eq!(query_runner.get_epoch_info().epoch, 0);
 
+    // The state root the epoch started with is committed to the state
+    let genesis_state_root = query_runner.get_epoch_state_root();
+    assert_ne!(genesis_state_root, [0; 32]);
+
     // Have the last needed committee member signal the epoch change and make sure it changes
     let req = get_update_request_node(
         UpdateMethod::ChangeEpoch { epoch: 0 },
@@ -444,6 +623,7 @@ async fn test_epoch_change() {
 
     // Query epoch info and make sure it incremented to new epoch
     assert_eq!(query_runner.get_epoch_info().epoch, 1);
+    assert_ne!(query_runner.get_epoch_state_root(), genesis_state_root);
 }
 
 #[test]
@@ -457,7 +637,12 @@ async fn test_stake() {
     // Deposit some FLK into account 1
     let update1 = get_update_request_account(
         UpdateMethod::Deposit {
-            proof: ProofOfConsensus {},
+            proof: get_proof_of_consensus(
+                owner_secret_key.to_pk().into(),
+                Tokens::FLK,
+                1_000_u64.into(),
+                &BRIDGE_VALIDATORS,
+            ),
             token: Tokens::FLK,
             amount: 1_000_u64.into(),
         },
@@ -466,7 +651,12 @@ async fn test_stake() {
     );
     let update2 = get_update_request_account(
         UpdateMethod::Deposit {
-            proof: ProofOfConsensus {},
+            proof: get_proof_of_consensus(
+                owner_secret_key.to_pk().into(),
+                Tokens::FLK,
+                1_000_u64.into(),
+                &BRIDGE_VALIDATORS,
+            ),
             token: Tokens::FLK,
             amount: 1_000_u64.into(),
         },
@@ -701,8 +891,11 @@ async fn test_pod_without_proof() {
         storage: StorageConfig::InMemory,
         db_path: None,
         db_options: None,
+        history_retention: 0,
     }));
 
+    fund_test_client(1_000_000, &update_socket).await;
+
     let bandwidth_pod = pod_request(&keystore[0].node_secret_key, 1000, 0, 1);
     let compute_pod = pod_request(&keystore[0].node_secret_key, 2000, 1, 2);
 
@@ -727,6 +920,184 @@ async fn test_pod_without_proof() {
     );
 }
 
+#[test]
+async fn test_client_escrow() {
+    let (committee, keystore) = get_genesis_committee(4);
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    deposit(
+        1_000_u64.into(),
+        Tokens::USDC,
+        &owner_secret_key,
+        &update_socket,
+        1,
+    )
+    .await;
+
+    // The escrow can not be funded with more than the bandwidth balance of the account.
+    let fund = |amount, nonce| {
+        get_update_request_account(
+            UpdateMethod::FundClient {
+                client: test_client(),
+                amount,
+            },
+            &owner_secret_key,
+            nonce,
+        )
+    };
+    let res = run_transaction(vec![fund(1_001, 2)], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InsufficientBalance)
+    );
+
+    let res = run_transaction(vec![fund(150, 3)], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(query_runner.get_account_balance(&owner), 850);
+    assert_eq!(
+        query_runner.get_client_balance(&test_client()),
+        HpUfixed::<6>::from(150_u64)
+    );
+
+    // Serving 1000 bandwidth costs 100, which the escrow can pay for once.
+    let node_secret_key = &keystore[0].node_secret_key;
+    let res = run_transaction(
+        vec![
+            pod_request(node_secret_key, 1000, 0, 1),
+            pod_request(node_secret_key, 1000, 0, 2),
+        ],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(
+        res.txn_receipts[1],
+        TransactionResponse::Revert(ExecutionError::InsufficientClientBalance)
+    );
+    assert_eq!(
+        query_runner.get_client_balance(&test_client()),
+        HpUfixed::<6>::from(50_u64)
+    );
+    assert_eq!(
+        query_runner
+            .get_node_served(&node_secret_key.to_pk())
+            .served,
+        vec![1000]
+    );
+
+    let client_secret_key = ConsensusSecretKey::decode_pem(TEST_CLIENT_SECRET_KEY).unwrap();
+    let other_secret_key = ConsensusSecretKey::generate();
+    let node = node_secret_key.to_pk();
+    let submit = |client, ack: DeliveryAcknowledgment, commodity, nonce| {
+        get_update_request_node(
+            UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+                client,
+                commodity,
+                service_id: 0,
+                proofs: vec![ack],
+                metadata: None,
+            },
+            node_secret_key,
+            nonce,
+        )
+    };
+    let res = run_transaction(
+        vec![
+            // Clients that never funded their escrow can not be served.
+            submit(
+                ClientPublicKey(other_secret_key.to_pk().0),
+                delivery_acknowledgment(&other_secret_key, node, 10, 0, 1),
+                10,
+                3,
+            ),
+            // The escrow only pays for work the client acknowledged itself.
+            submit(
+                test_client(),
+                delivery_acknowledgment(&other_secret_key, node, 10, 0, 4),
+                10,
+                4,
+            ),
+            submit(
+                test_client(),
+                delivery_acknowledgment(&client_secret_key, node, 10, 0, 5),
+                20,
+                5,
+            ),
+            submit(
+                test_client(),
+                delivery_acknowledgment(
+                    &client_secret_key,
+                    keystore[1].node_secret_key.to_pk(),
+                    10,
+                    0,
+                    6,
+                ),
+                10,
+                6,
+            ),
+            // An acknowledgment can only be used once.
+            submit(
+                test_client(),
+                delivery_acknowledgment(&client_secret_key, node, 1000, 0, 1),
+                1000,
+                7,
+            ),
+            // An empty aggregation proves nothing.
+            get_update_request_node(
+                UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+                    client: test_client(),
+                    commodity: 0,
+                    service_id: 0,
+                    proofs: vec![],
+                    metadata: None,
+                },
+                node_secret_key,
+                8,
+            ),
+        ],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InsufficientClientBalance)
+    );
+    for receipt in &res.txn_receipts[1..] {
+        assert_eq!(
+            *receipt,
+            TransactionResponse::Revert(ExecutionError::InvalidProof)
+        );
+    }
+    assert_eq!(
+        query_runner.get_client_balance(&test_client()),
+        HpUfixed::<6>::from(50_u64)
+    );
+}
+
 #[test]
 async fn test_distribute_rewards() {
     let (committee, keystore) = get_genesis_committee(4);
@@ -750,6 +1121,8 @@ async fn test_distribute_rewards() {
         Some(committee),
     );
 
+    fund_test_client(1_000_000, &update_socket).await;
+
     // get params for emission calculations
     let percentage_divisor: HpUfixed<18> = 100_u16.into();
     let supply_at_year_start: HpUfixed<18> = supply_at_genesis.into();
@@ -891,6 +1264,66 @@ async fn test_distribute_rewards() {
                 * &service_proportions[s as usize].convert_precision()
         );
     }
+
+    // assert the rewards ledger of the epoch
+    let rewards1 = query_runner
+        .get_rewards(0, &node_secret_key1.to_pk())
+        .unwrap();
+    assert_eq!(rewards1.node.flk, node_flk_rewards1);
+    assert_eq!(rewards1.node.boost, HpUfixed::<3>::from(1_u64));
+    assert_eq!(
+        rewards1.node.stables,
+        BTreeMap::from([
+            (
+                0,
+                HpUfixed::<6>::from(1_280_u64) * node_share.convert_precision()
+            ),
+            (
+                1,
+                HpUfixed::<6>::from(720_u64) * node_share.convert_precision()
+            ),
+        ])
+    );
+    let rewards2 = query_runner
+        .get_rewards(0, &node_secret_key2.to_pk())
+        .unwrap();
+    assert_eq!(rewards2.node.flk, node_flk_rewards2);
+    assert_eq!(rewards2.node.boost, HpUfixed::<3>::from(4_u64));
+    assert_eq!(
+        rewards2.node.stables,
+        BTreeMap::from([(
+            1,
+            HpUfixed::<6>::from(1_000_u64) * node_share.convert_precision()
+        )])
+    );
+    assert_eq!(rewards1.epoch, rewards2.epoch);
+    assert_eq!(rewards1.epoch.emissions, emissions);
+    assert_eq!(rewards1.epoch.reward_pool, reward_pool);
+    assert_eq!(rewards1.epoch.protocol_flk, protocol_rewards);
+    assert_eq!(rewards1.epoch.protocol_stables, protocol_stables_balance);
+    for s in 0..2 {
+        let service_owner = query_runner.get_service_info(s).owner;
+        let service_rewards = &rewards1.epoch.service_builders[&s];
+        assert_eq!(
+            service_rewards.flk,
+            query_runner.get_flk_balance(&service_owner)
+        );
+        assert_eq!(
+            service_rewards.stables,
+            query_runner.get_stables_balance(&service_owner)
+        );
+    }
+
+    // nodes that did not serve anything have no rewards
+    let rewards = query_runner
+        .get_rewards(0, &keystore[0].node_secret_key.to_pk())
+        .unwrap();
+    assert_eq!(rewards.node, NodeRewards::default());
+    assert!(
+        query_runner
+            .get_rewards(1, &node_secret_key1.to_pk())
+            .is_none()
+    );
 }
 
 #[test]
@@ -905,6 +1338,7 @@ async fn test_submit_rep_measurements() {
         storage: StorageConfig::InMemory,
         db_path: None,
         db_options: None,
+        history_retention: 0,
     }));
 
     let mut map = BTreeMap::new();
@@ -955,6 +1389,7 @@ async fn test_rep_scores() {
         storage: StorageConfig::InMemory,
         db_path: None,
         db_options: None,
+        history_retention: 0,
     }));
     let required_signals = 2 * committee_len / 3 + 1;
 
@@ -1038,6 +1473,8 @@ async fn test_supply_across_epoch() {
         Some(committee.clone()),
     );
 
+    fund_test_client(1_000_000, &update_socket).await;
+
     // get params for emission calculations
     let percentage_divisor: HpUfixed<18> = 100_u16.into();
     let supply_at_year_start: HpUfixed<18> = supply_at_genesis.into();
@@ -1134,6 +1571,7 @@ async fn test_validate_txn() {
         storage: StorageConfig::InMemory,
         db_path: None,
         db_options: None,
+        history_retention: 0,
     }));
 
     // Submit a ChangeEpoch transaction that will revert (EpochHasNotStarted) and ensure that the
@@ -1237,6 +1675,7 @@ async fn test_get_node_registry() {
         storage: StorageConfig::InMemory,
         db_path: None,
         db_options: None,
+        history_retention: 0,
     }));
 
     let owner_secret_key1 = AccountOwnerSecretKey::generate();
@@ -1344,6 +1783,7 @@ async fn test_change_protocol_params() {
         storage: StorageConfig::InMemory,
         db_path: None,
         db_options: None,
+        history_retention: 0,
     }));
 
     let update_method = UpdateMethod::ChangeProtocolParam {
@@ -1400,4 +1840,2710 @@ async fn test_change_protocol_params() {
         query_runner.get_protocol_params(ProtocolParams::LockTime),
         8
     );
+
+    // The slash share is a percentage.
+    let slash_share = query_runner.get_protocol_params(ProtocolParams::SlashShare);
+    let update_method = UpdateMethod::ChangeProtocolParam {
+        param: ProtocolParams::SlashShare,
+        value: 101,
+    };
+    let update_request = get_update_request_account(update_method, &governance_secret_key, 3);
+    let response = run_transaction(vec![update_request], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InvalidProtocolParamValue)
+    );
+    assert_eq!(
+        query_runner.get_protocol_params(ProtocolParams::SlashShare),
+        slash_share
+    );
+
+    // So are the governance quorum and threshold, and a proposal needs time to be voted on.
+    let invalid = [
+        (ProtocolParams::GovernanceQuorum, 101),
+        (ProtocolParams::GovernanceThreshold, 101),
+        (ProtocolParams::VotingPeriod, 0),
+    ];
+    let updates = invalid
+        .iter()
+        .zip(4..)
+        .map(|((param, value), nonce)| {
+            get_update_request_account(
+                UpdateMethod::ChangeProtocolParam {
+                    param: param.clone(),
+                    value: *value,
+                },
+                &governance_secret_key,
+                nonce,
+            )
+        })
+        .collect();
+    let response = run_transaction(updates, &update_socket).await.unwrap();
+    assert_eq!(
+        response.txn_receipts,
+        vec![TransactionResponse::Revert(ExecutionError::InvalidProtocolParamValue); 3]
+    );
+    for (param, value) in invalid {
+        assert_ne!(query_runner.get_protocol_params(param), value);
+    }
+}
+
+#[test]
+async fn test_withdraw() {
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let receiver: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1_000,
+        stables_balance: 100,
+        bandwidth_balance: 0,
+        vesting: None,
+    });
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    // Withdraw some FLK and make sure it gets queued with the first withdraw id.
+    let update = get_update_request_account(
+        UpdateMethod::Withdraw {
+            amount: 400_u64.into(),
+            token: Tokens::FLK,
+            receiving_address: receiver,
+        },
+        &owner_secret_key,
+        1,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::UInt(0))
+    );
+    assert_eq!(query_runner.get_flk_balance(&owner), 600_u64.into());
+
+    // Withdraw stables, this should get the next withdraw id. Stables only have 6 decimals so the
+    // rest of the amount is neither debited nor queued.
+    let update = get_update_request_account(
+        UpdateMethod::Withdraw {
+            amount: "100000000000000000001".parse().unwrap(),
+            token: Tokens::USDC,
+            receiving_address: receiver,
+        },
+        &owner_secret_key,
+        2,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::UInt(1))
+    );
+    assert_eq!(query_runner.get_stables_balance(&owner), HpUfixed::zero());
+
+    // Withdrawing more than the balance should revert and not be queued.
+    let update = get_update_request_account(
+        UpdateMethod::Withdraw {
+            amount: 601_u64.into(),
+            token: Tokens::FLK,
+            receiving_address: receiver,
+        },
+        &owner_secret_key,
+        3,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InsufficientBalance)
+    );
+    assert_eq!(query_runner.get_flk_balance(&owner), 600_u64.into());
+
+    assert_eq!(
+        query_runner.get_pending_withdrawals(),
+        vec![
+            (
+                0,
+                PendingWithdrawal {
+                    receiver,
+                    token: Tokens::FLK,
+                    amount: 400_u64.into(),
+                }
+            ),
+            (
+                1,
+                PendingWithdrawal {
+                    receiver,
+                    token: Tokens::USDC,
+                    amount: 100_u64.into(),
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+async fn test_deposit() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    // A deposit signed by more than 2/3 of the bridge validators is credited to the recipient.
+    let proof = get_proof_of_consensus(
+        owner,
+        Tokens::FLK,
+        1_000_u64.into(),
+        &BRIDGE_VALIDATORS[..3],
+    );
+    let update = get_update_request_account(
+        UpdateMethod::Deposit {
+            proof: proof.clone(),
+            token: Tokens::FLK,
+            amount: 1_000_u64.into(),
+        },
+        &owner_secret_key,
+        1,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(query_runner.get_flk_balance(&owner), 1_000_u64.into());
+
+    // The same deposit can not be claimed twice.
+    let update = get_update_request_account(
+        UpdateMethod::Deposit {
+            proof,
+            token: Tokens::FLK,
+            amount: 1_000_u64.into(),
+        },
+        &owner_secret_key,
+        2,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::DepositAlreadyProcessed)
+    );
+
+    // Not enough bridge validators signed the deposit.
+    let proof = get_proof_of_consensus(
+        owner,
+        Tokens::FLK,
+        1_000_u64.into(),
+        &BRIDGE_VALIDATORS[..2],
+    );
+    let update = get_update_request_account(
+        UpdateMethod::Deposit {
+            proof,
+            token: Tokens::FLK,
+            amount: 1_000_u64.into(),
+        },
+        &owner_secret_key,
+        3,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InvalidProof)
+    );
+
+    // Signatures from keys that are not bridge validators are rejected.
+    let outsiders: Vec<_> = (0..4).map(|_| AccountOwnerSecretKey::generate()).collect();
+    let proof = get_proof_of_consensus(owner, Tokens::FLK, 1_000_u64.into(), &outsiders);
+    let update = get_update_request_account(
+        UpdateMethod::Deposit {
+            proof,
+            token: Tokens::FLK,
+            amount: 1_000_u64.into(),
+        },
+        &owner_secret_key,
+        4,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InvalidProof)
+    );
+
+    // The claimed amount has to match the signed deposit event.
+    let proof = get_proof_of_consensus(owner, Tokens::FLK, 1_000_u64.into(), &BRIDGE_VALIDATORS);
+    let update = get_update_request_account(
+        UpdateMethod::Deposit {
+            proof,
+            token: Tokens::FLK,
+            amount: 2_000_u64.into(),
+        },
+        &owner_secret_key,
+        5,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InvalidProof)
+    );
+
+    assert_eq!(query_runner.get_flk_balance(&owner), 1_000_u64.into());
+}
+
+#[test]
+async fn test_add_and_remove_service() {
+    let (committee, keystore) = get_genesis_committee(4);
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    genesis.governance_address = governance_secret_key.to_pk().into();
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    fund_test_client(1_000_000, &update_socket).await;
+
+    let service = Service {
+        owner: AccountOwnerSecretKey::generate().to_pk().into(),
+        commodity_type: CommodityTypes::Bandwidth,
+        slashing: (),
+    };
+
+    // Only the governance address can add services.
+    let some_secret_key = AccountOwnerSecretKey::generate();
+    let update = get_update_request_account(
+        UpdateMethod::AddService {
+            service,
+            service_id: 2,
+        },
+        &some_secret_key,
+        1,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::OnlyGovernance)
+    );
+
+    let update = get_update_request_account(
+        UpdateMethod::AddService {
+            service,
+            service_id: 2,
+        },
+        &governance_secret_key,
+        1,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(query_runner.get_service_info(2), service);
+
+    // A service id can not be registered twice.
+    let update = get_update_request_account(
+        UpdateMethod::AddService {
+            service,
+            service_id: 0,
+        },
+        &governance_secret_key,
+        2,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::ServiceAlreadyExists)
+    );
+
+    // Serve the new service, this makes the service owe the node rewards for this epoch.
+    let res = run_transaction(
+        vec![pod_request(&keystore[0].node_secret_key, 1000, 2, 1)],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+
+    let update = get_update_request_account(
+        UpdateMethod::RemoveService { service_id: 2 },
+        &governance_secret_key,
+        3,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::ServiceHasPendingRevenue)
+    );
+
+    // Once the rewards are distributed at the end of the epoch, the service can be removed.
+    simple_epoch_change(0, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    let update = get_update_request_account(
+        UpdateMethod::RemoveService { service_id: 2 },
+        &governance_secret_key,
+        4,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+
+    let update = get_update_request_account(
+        UpdateMethod::RemoveService { service_id: 2 },
+        &governance_secret_key,
+        5,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::NonExistingService)
+    );
+
+    // Delivery acknowledgments for a removed service are rejected.
+    let nonce = query_runner
+        .get_node_info(&keystore[0].node_secret_key.to_pk())
+        .unwrap()
+        .nonce
+        + 1;
+    let res = run_transaction(
+        vec![pod_request(&keystore[0].node_secret_key, 1000, 2, nonce)],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InvalidServiceId)
+    );
+}
+
+#[test]
+async fn test_slash() {
+    let (committee, keystore) = get_genesis_committee(4);
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    let slash_share = genesis.slash_share;
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    let reporter_secret_key = AccountOwnerSecretKey::generate();
+    let offender_secret_key = &keystore[0].node_secret_key;
+    let offender = offender_secret_key.to_pk();
+    let protocol_fund = query_runner.get_protocol_fund_address();
+    let initial_fund_balance = query_runner.get_flk_balance(&protocol_fund);
+
+    // Unstaked tokens that are still locked are slashed as well.
+    let update = get_update_request_account(
+        UpdateMethod::Unstake {
+            amount: 100_u64.into(),
+            node: offender,
+        },
+        &keystore[0].owner_secret_key,
+        1,
+    );
+    run_transaction(vec![update], &update_socket).await.unwrap();
+    let initial_stake = query_runner.get_staked(&offender);
+    let initial_locked = query_runner.get_locked(&offender);
+    assert_eq!(initial_locked, 100_u64.into());
+
+    let table_entry = |value: Vec<u8>| {
+        let mut entry = TableEntry {
+            prefix: KeyPrefix::ContentRegistry,
+            key: vec![0, 1, 2],
+            value,
+            source: offender,
+            signature: None,
+        };
+        entry.signature = Some(offender_secret_key.sign(&entry.to_digest()));
+        entry
+    };
+
+    // Signing the same value twice is not a misbehavior.
+    let update = get_update_request_account(
+        UpdateMethod::Slash {
+            service_id: 0,
+            node: offender,
+            proof_of_misbehavior: ProofOfMisbehavior::DoubleSignedTableEntry {
+                first: table_entry(vec![3]),
+                second: table_entry(vec![3]),
+            },
+        },
+        &reporter_secret_key,
+        1,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InvalidProof)
+    );
+
+    // The proof has to be signed by the reported node.
+    let update = get_update_request_account(
+        UpdateMethod::Slash {
+            service_id: 0,
+            node: keystore[1].node_secret_key.to_pk(),
+            proof_of_misbehavior: ProofOfMisbehavior::DoubleSignedTableEntry {
+                first: table_entry(vec![3]),
+                second: table_entry(vec![4]),
+            },
+        },
+        &reporter_secret_key,
+        2,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InvalidProof)
+    );
+
+    let update = get_update_request_account(
+        UpdateMethod::Slash {
+            service_id: 0,
+            node: offender,
+            proof_of_misbehavior: ProofOfMisbehavior::DoubleSignedTableEntry {
+                first: table_entry(vec![3]),
+                second: table_entry(vec![4]),
+            },
+        },
+        &reporter_secret_key,
+        3,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    let share = HpUfixed::<18>::from(slash_share) / HpUfixed::<18>::from(100_u64);
+    let slashed_stake: HpUfixed<18> = &initial_stake * &share;
+    let slashed_locked: HpUfixed<18> = &initial_locked * &share;
+    assert_eq!(
+        query_runner.get_staked(&offender),
+        &initial_stake - &slashed_stake
+    );
+    assert_eq!(
+        query_runner.get_locked(&offender),
+        &initial_locked - &slashed_locked
+    );
+    assert_eq!(
+        query_runner.get_flk_balance(&protocol_fund),
+        &initial_fund_balance + &slashed_stake + slashed_locked
+    );
+
+    // Replaying the proof for the same offense is rejected, even with other conflicting values.
+    let update = get_update_request_account(
+        UpdateMethod::Slash {
+            service_id: 0,
+            node: offender,
+            proof_of_misbehavior: ProofOfMisbehavior::DoubleSignedTableEntry {
+                first: table_entry(vec![5]),
+                second: table_entry(vec![3]),
+            },
+        },
+        &reporter_secret_key,
+        4,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::AlreadySlashed)
+    );
+
+    // Signing two different payloads for the same sequence number of a topic gets slashed.
+    let broadcast_message = |payload: Vec<u8>| {
+        let digest = sequenced_broadcast_transcript(Topic::Debug, 42, &payload).hash();
+        SignedBroadcastMessage {
+            topic: Topic::Debug,
+            sequence: 42,
+            payload,
+            signature: offender_secret_key.sign(&digest),
+        }
+    };
+    let stake_before = query_runner.get_staked(&offender);
+    let update = get_update_request_account(
+        UpdateMethod::Slash {
+            service_id: 0,
+            node: offender,
+            proof_of_misbehavior: ProofOfMisbehavior::EquivocatingBroadcast {
+                first: broadcast_message(vec![1]),
+                second: broadcast_message(vec![2]),
+            },
+        },
+        &reporter_secret_key,
+        5,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert!(query_runner.get_staked(&offender) < stake_before);
+}
+
+#[test]
+async fn test_transfer() {
+    let sender_secret_key = AccountOwnerSecretKey::generate();
+    let sender: EthAddress = sender_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: sender,
+        flk_balance: 1_000,
+        stables_balance: 100,
+        bandwidth_balance: 0,
+        vesting: None,
+    });
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    let update = get_update_request_account(
+        UpdateMethod::Transfer {
+            token: Tokens::FLK,
+            amount: 400_u64.into(),
+            to: recipient,
+        },
+        &sender_secret_key,
+        1,
+    );
+    let update2 = get_update_request_account(
+        UpdateMethod::Transfer {
+            token: Tokens::USDC,
+            amount: 40_u64.into(),
+            to: recipient,
+        },
+        &sender_secret_key,
+        2,
+    );
+    let res = run_transaction(vec![update, update2], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Success(ExecutionData::None)
+        ]
+    );
+    assert_eq!(query_runner.get_flk_balance(&sender), 600_u64.into());
+    assert_eq!(query_runner.get_flk_balance(&recipient), 400_u64.into());
+    assert_eq!(query_runner.get_stables_balance(&sender), 60_u64.into());
+    assert_eq!(query_runner.get_stables_balance(&recipient), 40_u64.into());
+
+    // Transferring to yourself does not change the balance.
+    let update = get_update_request_account(
+        UpdateMethod::Transfer {
+            token: Tokens::FLK,
+            amount: 600_u64.into(),
+            to: sender,
+        },
+        &sender_secret_key,
+        3,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(query_runner.get_flk_balance(&sender), 600_u64.into());
+
+    // Transferring more than the balance reverts, but still consumes the nonce.
+    let update = get_update_request_account(
+        UpdateMethod::Transfer {
+            token: Tokens::FLK,
+            amount: 601_u64.into(),
+            to: recipient,
+        },
+        &sender_secret_key,
+        4,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InsufficientBalance)
+    );
+    assert_eq!(query_runner.get_flk_balance(&sender), 600_u64.into());
+    assert_eq!(query_runner.get_account_info(&sender).unwrap().nonce, 4);
+
+    // Only the USDC that fits into its 6 decimals is moved and reported.
+    let amount = HpUfixed::<18>::from(1_u64) + HpUfixed::<18>::new(1_u64.into());
+    let update = get_update_request_account(
+        UpdateMethod::Transfer {
+            token: Tokens::USDC,
+            amount,
+            to: recipient,
+        },
+        &sender_secret_key,
+        5,
+    );
+    let hash = update.hash();
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(query_runner.get_stables_balance(&sender), 59_u64.into());
+    assert_eq!(query_runner.get_stables_balance(&recipient), 41_u64.into());
+    assert_eq!(
+        query_runner.get_transaction_receipt(&hash).unwrap().events,
+        vec![TransactionEvent::Transfer {
+            token: Tokens::USDC,
+            from: sender,
+            to: recipient,
+            amount: 1_u64.into(),
+        }]
+    );
+
+    // Transfers of nothing revert, including USDC amounts below its precision.
+    for (nonce, token, amount) in [
+        (6, Tokens::FLK, HpUfixed::zero()),
+        (7, Tokens::USDC, HpUfixed::<18>::new(1_u64.into())),
+    ] {
+        let update = get_update_request_account(
+            UpdateMethod::Transfer {
+                token,
+                amount,
+                to: recipient,
+            },
+            &sender_secret_key,
+            nonce,
+        );
+        let res = run_transaction(vec![update], &update_socket).await.unwrap();
+        assert_eq!(
+            res.txn_receipts[0],
+            TransactionResponse::Revert(ExecutionError::ZeroAmount)
+        );
+    }
+}
+
+#[test]
+async fn test_delegated_staking() {
+    let (committee, keystore) = get_genesis_committee(4);
+    let owner_secret_key = &keystore[0].owner_secret_key;
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let node = keystore[0].node_secret_key.to_pk();
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    let delegator: EthAddress = delegator_secret_key.to_pk().into();
+
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    genesis.lock_time = 1;
+    for account in [owner, delegator] {
+        genesis.account.push(GenesisAccount {
+            public_key: account,
+            flk_balance: 1_000,
+            stables_balance: 0,
+            bandwidth_balance: 0,
+            vesting: None,
+        });
+    }
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    fund_test_client(1_000_000, &update_socket).await;
+
+    let stake_method = |amount: u64| UpdateMethod::Stake {
+        amount: amount.into(),
+        node_public_key: node,
+        consensus_key: None,
+        node_domain: None,
+        worker_public_key: None,
+        worker_domain: None,
+        ports: None,
+    };
+
+    // The owner stakes on their own node and the delegator stakes on top of it.
+    let initial_stake = query_runner.get_staked(&node);
+    let owner_stake = get_update_request_account(stake_method(1_000), owner_secret_key, 1);
+    let delegator_stake = get_update_request_account(stake_method(1_000), &delegator_secret_key, 1);
+    let res = run_transaction(vec![owner_stake, delegator_stake], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Success(ExecutionData::None)
+        ]
+    );
+    assert_eq!(
+        query_runner.get_staked(&node),
+        &initial_stake + &2_000_u64.into()
+    );
+    assert_eq!(query_runner.get_flk_balance(&delegator), HpUfixed::zero());
+    assert_eq!(
+        query_runner
+            .get_delegation(&node, &delegator)
+            .unwrap()
+            .staked,
+        1_000_u64.into()
+    );
+
+    // Only the owner can change the node details or the commission.
+    let update = get_update_request_account(
+        UpdateMethod::Stake {
+            amount: 0_u64.into(),
+            node_public_key: node,
+            consensus_key: None,
+            node_domain: Some("127.0.0.1".parse().unwrap()),
+            worker_public_key: None,
+            worker_domain: None,
+            ports: None,
+        },
+        &delegator_secret_key,
+        2,
+    );
+    let update2 = get_update_request_account(
+        UpdateMethod::SetCommission {
+            node,
+            commission: 20,
+        },
+        &delegator_secret_key,
+        3,
+    );
+    let update3 = get_update_request_account(
+        UpdateMethod::SetCommission {
+            node,
+            commission: 101,
+        },
+        owner_secret_key,
+        2,
+    );
+    let update4 = get_update_request_account(
+        UpdateMethod::SetCommission {
+            node,
+            commission: 20,
+        },
+        owner_secret_key,
+        3,
+    );
+    let res = run_transaction(vec![update, update2, update3, update4], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Revert(ExecutionError::NotNodeOwner),
+            TransactionResponse::Revert(ExecutionError::NotNodeOwner),
+            TransactionResponse::Revert(ExecutionError::InvalidCommission),
+            TransactionResponse::Success(ExecutionData::None)
+        ]
+    );
+
+    // The owner can not unstake the delegated stake.
+    let update = get_update_request_account(
+        UpdateMethod::Unstake {
+            amount: &initial_stake + &1_001_u64.into(),
+            node,
+        },
+        owner_secret_key,
+        4,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InsufficientBalance)
+    );
+
+    // Rewards are split between the delegator and the owner according to the commission.
+    let pod = pod_request(&keystore[0].node_secret_key, 1_000, 0, 1);
+    run_transaction(vec![pod], &update_socket).await.unwrap();
+    simple_epoch_change(0, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    let delegator_rewards = query_runner.get_flk_balance(&delegator);
+    let node_rewards = &query_runner.get_flk_balance(&owner) + &delegator_rewards;
+    assert!(delegator_rewards > HpUfixed::zero());
+    assert_eq!(
+        delegator_rewards,
+        &(&node_rewards * &(HpUfixed::<18>::from(80_u64) / HpUfixed::<18>::from(100_u64)))
+            * &(HpUfixed::<18>::from(1_000_u64) / (&initial_stake + &2_000_u64.into()))
+    );
+
+    // Undelegated stake is locked for the lock time before it can be withdrawn.
+    let update = get_update_request_account(
+        UpdateMethod::Unstake {
+            amount: 400_u64.into(),
+            node,
+        },
+        &delegator_secret_key,
+        4,
+    );
+    let update2 = get_update_request_account(
+        UpdateMethod::WithdrawUnstaked {
+            node,
+            recipient: None,
+        },
+        &delegator_secret_key,
+        5,
+    );
+    let res = run_transaction(vec![update, update2], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Revert(ExecutionError::TokensLocked)
+        ]
+    );
+    let delegation = query_runner.get_delegation(&node, &delegator).unwrap();
+    assert_eq!(delegation.staked, 600_u64.into());
+    assert_eq!(delegation.locked, 400_u64.into());
+    assert_eq!(
+        query_runner.get_staked(&node),
+        &initial_stake + &1_600_u64.into()
+    );
+
+    simple_epoch_change(1, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    let update = get_update_request_account(
+        UpdateMethod::WithdrawUnstaked {
+            node,
+            recipient: None,
+        },
+        &delegator_secret_key,
+        6,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(
+        query_runner.get_flk_balance(&delegator),
+        &delegator_rewards + &400_u64.into()
+    );
+    assert_eq!(
+        query_runner
+            .get_delegation(&node, &delegator)
+            .unwrap()
+            .locked,
+        HpUfixed::zero()
+    );
+}
+
+#[test]
+async fn test_governance_proposals() {
+    let (committee, keystore) = get_genesis_committee(4);
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    genesis.governance_quorum = 33;
+    genesis.governance_threshold = 50;
+    genesis.voting_period = 2;
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+    let owners: Vec<&AccountOwnerSecretKey> =
+        keystore.iter().map(|key| &key.owner_secret_key).collect();
+    let lock_time = ProposalAction::ChangeProtocolParam {
+        param: ProtocolParams::LockTime,
+        value: 20,
+    };
+
+    // Only node owners can submit proposals.
+    let update = get_update_request_account(
+        UpdateMethod::Propose {
+            action: lock_time.clone(),
+        },
+        &AccountOwnerSecretKey::generate(),
+        1,
+    );
+    let update2 =
+        get_update_request_account(UpdateMethod::Propose { action: lock_time }, owners[0], 1);
+    let res = run_transaction(vec![update, update2], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Revert(ExecutionError::NoVotingPower),
+            TransactionResponse::Success(ExecutionData::UInt(0))
+        ]
+    );
+
+    let vote = |owner: usize, approve: bool, nonce: u64| {
+        get_update_request_account(
+            UpdateMethod::Vote {
+                proposal_id: 0,
+                approve,
+            },
+            owners[owner],
+            nonce,
+        )
+    };
+    let execute = get_update_request_account(
+        UpdateMethod::ExecuteProposal { proposal_id: 0 },
+        owners[3],
+        1,
+    );
+    // Votes count with the stake at the start of the voting, so unstaking before voting does not
+    // change the vote.
+    let unstake = get_update_request_account(
+        UpdateMethod::Unstake {
+            amount: 100_u64.into(),
+            node: keystore[2].node_secret_key.to_pk(),
+        },
+        owners[2],
+        1,
+    );
+    let res = run_transaction(
+        vec![
+            vote(0, true, 2),
+            vote(1, true, 1),
+            vote(1, false, 2),
+            unstake,
+            vote(2, false, 2),
+            execute,
+        ],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Revert(ExecutionError::AlreadyVoted),
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Revert(ExecutionError::VotingNotEnded)
+        ]
+    );
+    let proposal = query_runner.get_proposal(0).unwrap();
+    let node_stake = query_runner.get_staked(&keystore[0].node_secret_key.to_pk());
+    assert_eq!(proposal.votes_for, &node_stake + &node_stake);
+    assert_eq!(proposal.votes_against, node_stake);
+    assert_eq!(
+        proposal.total_voting_power,
+        &(&node_stake + &node_stake) + &(&node_stake + &node_stake)
+    );
+    assert_eq!(proposal.voting_ends, 2);
+
+    simple_epoch_change(0, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    simple_epoch_change(1, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+
+    // Once the voting period is over no votes are accepted and the proposal can be executed.
+    let execute = |nonce: u64| {
+        get_update_request_account(
+            UpdateMethod::ExecuteProposal { proposal_id: 0 },
+            owners[3],
+            nonce,
+        )
+    };
+    let res = run_transaction(
+        vec![vote(3, true, 2), execute(3), execute(4)],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Revert(ExecutionError::VotingEnded),
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Revert(ExecutionError::ProposalAlreadyExecuted)
+        ]
+    );
+    assert_eq!(
+        query_runner.get_protocol_params(ProtocolParams::LockTime),
+        20
+    );
+    assert!(query_runner.get_proposal(0).unwrap().executed);
+
+    // A proposal that not enough of the stake voted on does not pass.
+    let service = Service {
+        owner: AccountOwnerSecretKey::generate().to_pk().into(),
+        commodity_type: CommodityTypes::Bandwidth,
+        slashing: (),
+    };
+    let update = get_update_request_account(
+        UpdateMethod::Propose {
+            action: ProposalAction::AddService {
+                service,
+                service_id: 5,
+            },
+        },
+        owners[3],
+        5,
+    );
+    let update2 = get_update_request_account(
+        UpdateMethod::Vote {
+            proposal_id: 1,
+            approve: true,
+        },
+        owners[3],
+        6,
+    );
+    let res = run_transaction(vec![update, update2], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Success(ExecutionData::UInt(1)),
+            TransactionResponse::Success(ExecutionData::None)
+        ]
+    );
+    // Stake added after the voting started doesn't count.
+    deposit(100_u64.into(), Tokens::FLK, owners[0], &update_socket, 3).await;
+    let update = get_update_request_account(
+        UpdateMethod::Stake {
+            amount: 100_u64.into(),
+            node_public_key: keystore[0].node_secret_key.to_pk(),
+            consensus_key: None,
+            node_domain: None,
+            worker_public_key: None,
+            worker_domain: None,
+            ports: None,
+        },
+        owners[0],
+        4,
+    );
+    let update2 = get_update_request_account(
+        UpdateMethod::Vote {
+            proposal_id: 1,
+            approve: false,
+        },
+        owners[0],
+        5,
+    );
+    let res = run_transaction(vec![update, update2], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Success(ExecutionData::None)
+        ]
+    );
+    assert_eq!(
+        query_runner.get_proposal(1).unwrap().votes_against,
+        node_stake
+    );
+    simple_epoch_change(2, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    simple_epoch_change(3, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    let update = get_update_request_account(
+        UpdateMethod::ExecuteProposal { proposal_id: 1 },
+        owners[3],
+        7,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::ProposalNotPassed)
+    );
+    assert!(!query_runner.get_proposal(1).unwrap().executed);
+}
+
+#[test]
+async fn test_transaction_receipts() {
+    let mut genesis = Genesis::load().unwrap();
+    genesis.receipt_retention = 2;
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    let sender_secret_key = AccountOwnerSecretKey::generate();
+    let sender: EthAddress = sender_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let deposit = get_update_request_account(
+        UpdateMethod::Deposit {
+            proof: get_proof_of_consensus(
+                sender,
+                Tokens::FLK,
+                1_000_u64.into(),
+                &BRIDGE_VALIDATORS,
+            ),
+            token: Tokens::FLK,
+            amount: 1_000_u64.into(),
+        },
+        &sender_secret_key,
+        1,
+    );
+    let transfer = get_update_request_account(
+        UpdateMethod::Transfer {
+            token: Tokens::FLK,
+            amount: 400_u64.into(),
+            to: recipient,
+        },
+        &sender_secret_key,
+        2,
+    );
+    let failed_transfer = get_update_request_account(
+        UpdateMethod::Transfer {
+            token: Tokens::FLK,
+            amount: 601_u64.into(),
+            to: recipient,
+        },
+        &sender_secret_key,
+        3,
+    );
+    // Replaying a transaction fails the verification and does not overwrite its receipt.
+    let replay = transfer.clone();
+    let block_hash = [1; 32];
+    update_socket
+        .run(Block {
+            transactions: vec![
+                deposit.clone(),
+                transfer.clone(),
+                failed_transfer.clone(),
+                replay,
+            ],
+            digest: block_hash,
+        })
+        .await
+        .unwrap();
+
+    let receipt = query_runner
+        .get_transaction_receipt(&transfer.hash())
+        .unwrap();
+    assert_eq!(
+        receipt,
+        TransactionReceipt {
+            block_hash,
+            transaction_index: 1,
+            transaction_hash: transfer.hash(),
+            from: sender.into(),
+            response: TransactionResponse::Success(ExecutionData::None),
+            events: vec![TransactionEvent::Transfer {
+                token: Tokens::FLK,
+                from: sender,
+                to: recipient,
+                amount: 400_u64.into(),
+            }],
+        }
+    );
+
+    let receipt = query_runner
+        .get_transaction_receipt(&failed_transfer.hash())
+        .unwrap();
+    assert_eq!(
+        receipt.response,
+        TransactionResponse::Revert(ExecutionError::InsufficientBalance)
+    );
+    assert!(receipt.events.is_empty());
+
+    let receipts = query_runner.get_block_transactions(&block_hash);
+    assert_eq!(receipts.len(), 3);
+    assert_eq!(receipts[0].transaction_hash, deposit.hash());
+    assert_eq!(
+        receipts[0].events,
+        vec![TransactionEvent::Deposit {
+            token: Tokens::FLK,
+            to: sender,
+            amount: 1_000_u64.into(),
+        }]
+    );
+    assert_eq!(receipts[2].transaction_hash, failed_transfer.hash());
+
+    assert!(query_runner.get_transaction_receipt(&[0; 32]).is_none());
+    assert!(query_runner.get_block_transactions(&[2; 32]).is_empty());
+
+    // The receipts are dropped once the block is older than the retention.
+    for digest in [[2; 32], [3; 32]] {
+        assert!(
+            query_runner
+                .get_transaction_receipt(&deposit.hash())
+                .is_some()
+        );
+        update_socket
+            .run(Block {
+                transactions: vec![],
+                digest,
+            })
+            .await
+            .unwrap();
+    }
+    assert!(
+        query_runner
+            .get_transaction_receipt(&deposit.hash())
+            .is_none()
+    );
+    assert!(query_runner.get_block_transactions(&block_hash).is_empty());
+}
+
+#[test]
+async fn test_historical_queries() {
+    let committee_size = 4;
+    let (committee, keystore) = get_genesis_committee(committee_size);
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    genesis.epoch_start = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 10,
+    }));
+
+    let sender_secret_key = AccountOwnerSecretKey::generate();
+    let sender: EthAddress = sender_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    deposit(
+        1_000_u64.into(),
+        Tokens::FLK,
+        &sender_secret_key,
+        &update_socket,
+        1,
+    )
+    .await;
+    let transfer = |amount: u64, nonce| {
+        get_update_request_account(
+            UpdateMethod::Transfer {
+                token: Tokens::FLK,
+                amount: amount.into(),
+                to: recipient,
+            },
+            &sender_secret_key,
+            nonce,
+        )
+    };
+    run_transaction(vec![transfer(400, 2)], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(query_runner.get_block_number(), 2);
+
+    let genesis_state = query_runner.at_block(0).unwrap();
+    assert_eq!(
+        genesis_state.get_flk_balance(&sender),
+        HpUfixed::<18>::zero()
+    );
+    assert_eq!(genesis_state.get_block_number(), 0);
+    assert_eq!(
+        query_runner.at_block(1).unwrap().get_flk_balance(&sender),
+        1_000_u64.into()
+    );
+    assert_eq!(
+        query_runner.at_block(2).unwrap().get_flk_balance(&sender),
+        600_u64.into()
+    );
+    assert!(query_runner.at_block(3).is_none());
+
+    // The epoch ends with the last change epoch signal.
+    simple_epoch_change(0, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    let epoch_end_block = query_runner.get_block_number();
+    run_transaction(vec![transfer(100, 3)], &update_socket)
+        .await
+        .unwrap();
+
+    let end_of_epoch = query_runner.at_epoch(0).unwrap();
+    assert_eq!(end_of_epoch.get_block_number(), epoch_end_block);
+    assert_eq!(end_of_epoch.get_flk_balance(&sender), 600_u64.into());
+    assert_eq!(end_of_epoch.get_flk_balance(&recipient), 400_u64.into());
+    assert_eq!(query_runner.get_flk_balance(&sender), 500_u64.into());
+    assert!(query_runner.at_epoch(1).is_none());
+
+    // Only the configured number of blocks is retained.
+    for nonce in 4..14 {
+        run_transaction(vec![transfer(1, nonce)], &update_socket)
+            .await
+            .unwrap();
+    }
+    assert!(query_runner.at_epoch(0).is_none());
+    assert!(
+        query_runner
+            .at_block(query_runner.get_block_number() - 10)
+            .is_none()
+    );
+    assert!(
+        query_runner
+            .at_block(query_runner.get_block_number() - 9)
+            .is_some()
+    );
+}
+
+#[test]
+async fn test_simulate_txn() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let sender_secret_key = AccountOwnerSecretKey::generate();
+    let sender: EthAddress = sender_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    deposit(
+        1_000_u64.into(),
+        Tokens::FLK,
+        &sender_secret_key,
+        &update_socket,
+        1,
+    )
+    .await;
+    let account_before = query_runner.get_account_info(&sender).unwrap();
+
+    let transfer = get_update_request_account(
+        UpdateMethod::Transfer {
+            token: Tokens::FLK,
+            amount: 400_u64.into(),
+            to: recipient,
+        },
+        &sender_secret_key,
+        2,
+    );
+    let simulation = query_runner.simulate_txn(transfer.clone());
+    assert_eq!(
+        simulation.response,
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    let mut account_changes = simulation.diff.account;
+    account_changes.sort_by_key(|change| change.key != sender);
+    assert_eq!(
+        account_changes,
+        vec![
+            StateChange {
+                key: sender,
+                before: Some(account_before.clone()),
+                after: Some(AccountInfo {
+                    flk_balance: 600_u64.into(),
+                    nonce: 2,
+                    ..account_before.clone()
+                }),
+            },
+            StateChange {
+                key: recipient,
+                before: None,
+                after: Some(AccountInfo {
+                    flk_balance: 400_u64.into(),
+                    stables_balance: HpUfixed::zero(),
+                    bandwidth_balance: 0,
+                    vested_flk: HpUfixed::zero(),
+                    unvested_flk: HpUfixed::zero(),
+                    nonce: 0,
+                }),
+            },
+        ]
+    );
+    assert!(simulation.diff.node.is_empty());
+    assert!(simulation.diff.metadata.is_empty());
+
+    // Nothing is committed, so the same transaction can still be executed.
+    assert_eq!(query_runner.get_account_info(&sender), Some(account_before));
+    let res = run_transaction(vec![transfer], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+
+    // A failing transaction only spends the nonce.
+    let failed_transfer = get_update_request_account(
+        UpdateMethod::Transfer {
+            token: Tokens::FLK,
+            amount: 601_u64.into(),
+            to: recipient,
+        },
+        &sender_secret_key,
+        3,
+    );
+    let simulation = query_runner.simulate_txn(failed_transfer);
+    assert_eq!(
+        simulation.response,
+        TransactionResponse::Revert(ExecutionError::InsufficientBalance)
+    );
+    assert_eq!(simulation.diff.account.len(), 1);
+    assert_eq!(simulation.diff.account[0].after.as_ref().unwrap().nonce, 3);
+}
+
+#[test]
+async fn test_batch_transactions() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let node_public_key = NodeSecretKey::generate().to_pk();
+    let consensus_key = ConsensusSecretKey::generate().to_pk();
+    let minimum_stake: HpUfixed<18> = query_runner
+        .get_protocol_params(ProtocolParams::MinimumNodeStake)
+        .into();
+    let deposit_amount = minimum_stake.clone() + minimum_stake.clone();
+
+    // Deposit, stake and lock the stake of a new node in a single transaction.
+    let batch = UpdateMethod::Batch(vec![
+        UpdateMethod::Deposit {
+            proof: get_proof_of_consensus(
+                owner,
+                Tokens::FLK,
+                deposit_amount.clone(),
+                &BRIDGE_VALIDATORS,
+            ),
+            token: Tokens::FLK,
+            amount: deposit_amount,
+        },
+        UpdateMethod::Stake {
+            amount: minimum_stake.clone(),
+            node_public_key,
+            consensus_key: Some(consensus_key),
+            node_domain: Some("127.0.0.1".parse().unwrap()),
+            worker_public_key: Some([0; 32].into()),
+            worker_domain: Some("127.0.0.1".parse().unwrap()),
+            ports: Some(NodePorts::default()),
+        },
+        UpdateMethod::StakeLock {
+            node: node_public_key,
+            locked_for: 365,
+        },
+    ]);
+    let update = get_update_request_account(batch, &owner_secret_key, 1);
+    let res = run_transaction(vec![update.clone()], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::Batch(vec![
+            ExecutionData::None,
+            ExecutionData::None,
+            ExecutionData::None
+        ]))
+    );
+    assert_eq!(query_runner.get_staked(&node_public_key), minimum_stake);
+    assert_eq!(query_runner.get_stake_locked_until(&node_public_key), 365);
+    assert_eq!(query_runner.get_flk_balance(&owner), minimum_stake);
+    let receipt = query_runner
+        .get_transaction_receipt(&update.hash())
+        .unwrap();
+    // Locking the stake does not emit an event.
+    assert_eq!(receipt.events.len(), 2);
+
+    // The transfer is undone when unstaking the locked tokens fails.
+    let batch = UpdateMethod::Batch(vec![
+        UpdateMethod::Transfer {
+            token: Tokens::FLK,
+            amount: minimum_stake.clone(),
+            to: recipient,
+        },
+        UpdateMethod::Unstake {
+            amount: minimum_stake.clone(),
+            node: node_public_key,
+        },
+    ]);
+    let update = get_update_request_account(batch, &owner_secret_key, 2);
+    let res = run_transaction(vec![update.clone()], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::BatchStepFailed {
+            step: 1,
+            error: Box::new(ExecutionError::LockedTokensUnstakeForbidden),
+        })
+    );
+    assert_eq!(query_runner.get_flk_balance(&owner), minimum_stake);
+    assert!(query_runner.get_account_info(&recipient).is_none());
+    assert_eq!(query_runner.get_account_info(&owner).unwrap().nonce, 2);
+    let receipt = query_runner
+        .get_transaction_receipt(&update.hash())
+        .unwrap();
+    assert!(receipt.events.is_empty());
+
+    // Batches can not be empty, nested or change the epoch.
+    for (nonce, methods) in [
+        vec![],
+        vec![UpdateMethod::Batch(vec![])],
+        vec![UpdateMethod::ChangeEpoch { epoch: 0 }],
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let update = get_update_request_account(
+            UpdateMethod::Batch(methods),
+            &owner_secret_key,
+            nonce as u64 + 3,
+        );
+        let res = run_transaction(vec![update], &update_socket).await.unwrap();
+        assert_eq!(
+            res.txn_receipts[0],
+            TransactionResponse::Revert(ExecutionError::InvalidBatch)
+        );
+    }
+}
+
+#[test]
+async fn test_batch_digest_covers_steps() {
+    let transfer = |amount: u64| UpdateMethod::Transfer {
+        token: Tokens::FLK,
+        amount: amount.into(),
+        to: EthAddress([0; 20]),
+    };
+    let digest = |methods| {
+        UpdatePayload {
+            version: UPDATE_PAYLOAD_VERSION,
+            chain_id: TEST_CHAIN_ID,
+            nonce: 1,
+            method: UpdateMethod::Batch(methods),
+        }
+        .to_digest()
+    };
+    let batch_digest = digest(vec![transfer(1), transfer(2)]);
+    assert_ne!(batch_digest, digest(vec![transfer(2), transfer(1)]));
+    assert_ne!(batch_digest, digest(vec![transfer(1)]));
+    assert_ne!(
+        batch_digest,
+        digest(vec![transfer(1), transfer(2), transfer(2)])
+    );
+}
+
+#[test]
+async fn test_update_node_info() {
+    let committee_size = 4;
+    let (committee, keystore) = get_genesis_committee(committee_size);
+    let (update_socket, query_runner) = init_app_with_params(
+        Params {
+            epoch_time: None,
+            max_inflation: None,
+            protocol_share: None,
+            node_share: None,
+            service_builder_share: None,
+            max_boost: None,
+            supply_at_genesis: None,
+        },
+        Some(committee),
+    );
+
+    let owner_secret_key = &keystore[0].owner_secret_key;
+    let node_public_key = keystore[0].node_secret_key.to_pk();
+    let node_before = query_runner.get_node_info(&node_public_key).unwrap();
+    let ports = NodePorts {
+        primary: 18000,
+        worker: 18001,
+        mempool: 18002,
+        rpc: 18003,
+        pool: 18004,
+        dht: 18005,
+        handshake: 18006,
+        blockstore: 18007,
+    };
+    let update_node_info =
+        |domain: Option<&str>, ports: Option<NodePorts>| UpdateMethod::UpdateNodeInfo {
+            node: node_public_key,
+            domain: domain.map(|domain| domain.parse().unwrap()),
+            worker_domain: None,
+            worker_public_key: None,
+            ports,
+        };
+
+    // Only the owner of the node can update it.
+    let update = get_update_request_account(
+        update_node_info(Some("10.0.0.1"), None),
+        &keystore[1].owner_secret_key,
+        1,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::NotNodeOwner)
+    );
+
+    let invalid_updates = [
+        (
+            update_node_info(None, None),
+            ExecutionError::InsufficientNodeDetails,
+        ),
+        (
+            update_node_info(Some("0.0.0.0"), None),
+            ExecutionError::InvalidInternetAddress,
+        ),
+        (
+            update_node_info(Some("224.0.0.1"), None),
+            ExecutionError::InvalidInternetAddress,
+        ),
+        (
+            update_node_info(
+                None,
+                Some(NodePorts {
+                    worker: ports.primary,
+                    ..ports.clone()
+                }),
+            ),
+            ExecutionError::InvalidPorts,
+        ),
+        (
+            update_node_info(
+                None,
+                Some(NodePorts {
+                    rpc: 0,
+                    ..ports.clone()
+                }),
+            ),
+            ExecutionError::InvalidPorts,
+        ),
+    ];
+    let mut nonce = 1;
+    for (method, error) in invalid_updates {
+        let update = get_update_request_account(method, owner_secret_key, nonce);
+        nonce += 1;
+        let res = run_transaction(vec![update], &update_socket).await.unwrap();
+        assert_eq!(res.txn_receipts[0], TransactionResponse::Revert(error));
+    }
+
+    // Updates in the same epoch are merged.
+    let update = get_update_request_account(
+        update_node_info(Some("10.0.0.1"), None),
+        owner_secret_key,
+        nonce,
+    );
+    let ports_update = get_update_request_account(
+        update_node_info(None, Some(ports.clone())),
+        owner_secret_key,
+        nonce + 1,
+    );
+    let res = run_transaction(vec![update, ports_update], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Success(ExecutionData::None),
+            TransactionResponse::Success(ExecutionData::None)
+        ]
+    );
+
+    // The changes only take effect in the next epoch.
+    assert_eq!(
+        query_runner.get_node_info(&node_public_key).unwrap(),
+        node_before
+    );
+    simple_epoch_change(0, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    let node_after = query_runner.get_node_info(&node_public_key).unwrap();
+    assert_eq!(node_after.domain, "10.0.0.1".parse::<IpAddr>().unwrap());
+    assert_eq!(node_after.ports, ports);
+    assert_eq!(node_after.worker_domain, node_before.worker_domain);
+    assert_eq!(node_after.worker_public_key, node_before.worker_public_key);
+}
+
+#[test]
+async fn test_rotate_keys() {
+    let committee_size = 4;
+    let (committee, mut keystore) = get_genesis_committee(committee_size);
+    let (update_socket, query_runner) = init_app_with_params(
+        Params {
+            epoch_time: None,
+            max_inflation: None,
+            protocol_share: None,
+            node_share: None,
+            service_builder_share: None,
+            max_boost: None,
+            supply_at_genesis: None,
+        },
+        Some(committee),
+    );
+
+    let owner_secret_key = keystore[0].owner_secret_key.clone();
+    let old_public_key = keystore[0].node_secret_key.to_pk();
+    let other_node = query_runner
+        .get_node_info(&keystore[1].node_secret_key.to_pk())
+        .unwrap();
+    let node_before = query_runner.get_node_info(&old_public_key).unwrap();
+    let index = query_runner.pubkey_to_index(old_public_key).unwrap();
+
+    let new_node_secret_key = NodeSecretKey::generate();
+    let new_consensus_secret_key = ConsensusSecretKey::generate();
+    let rotate_keys = |new_public_key: Option<NodePublicKey>,
+                       new_consensus_key: Option<ConsensusPublicKey>| {
+        UpdateMethod::RotateKeys {
+            node: old_public_key,
+            new_public_key,
+            new_consensus_key,
+        }
+    };
+
+    // Only the owner of the node can rotate its keys.
+    let update = get_update_request_account(
+        rotate_keys(Some(new_node_secret_key.to_pk()), None),
+        &keystore[1].owner_secret_key,
+        1,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::NotNodeOwner)
+    );
+
+    let invalid_rotations = [
+        (
+            rotate_keys(None, None),
+            ExecutionError::InsufficientNodeDetails,
+        ),
+        (
+            rotate_keys(Some(other_node.public_key), None),
+            ExecutionError::NodeKeyAlreadyIndexed,
+        ),
+        (
+            rotate_keys(Some(old_public_key), None),
+            ExecutionError::NodeKeyAlreadyIndexed,
+        ),
+        (
+            rotate_keys(None, Some(other_node.consensus_key)),
+            ExecutionError::ConsensusKeyAlreadyIndexed,
+        ),
+    ];
+    let mut nonce = 1;
+    for (method, error) in invalid_rotations {
+        let update = get_update_request_account(method, &owner_secret_key, nonce);
+        nonce += 1;
+        let res = run_transaction(vec![update], &update_socket).await.unwrap();
+        assert_eq!(res.txn_receipts[0], TransactionResponse::Revert(error));
+    }
+
+    let update = get_update_request_account(
+        rotate_keys(
+            Some(new_node_secret_key.to_pk()),
+            Some(new_consensus_secret_key.to_pk()),
+        ),
+        &owner_secret_key,
+        nonce,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+
+    // The node keeps its index, stake and history under the new keys.
+    assert_eq!(query_runner.get_node_info(&old_public_key), None);
+    assert_eq!(query_runner.pubkey_to_index(old_public_key), None);
+    assert_eq!(
+        query_runner.pubkey_to_index(new_node_secret_key.to_pk()),
+        Some(index)
+    );
+    let node_after = query_runner
+        .get_node_info(&new_node_secret_key.to_pk())
+        .unwrap();
+    assert_eq!(
+        NodeInfo {
+            public_key: old_public_key,
+            ..node_after
+        },
+        node_before
+    );
+
+    // The new consensus key is reserved but only replaces the old one at the next epoch.
+    let update = get_update_request_account(
+        UpdateMethod::RotateKeys {
+            node: other_node.public_key,
+            new_public_key: None,
+            new_consensus_key: Some(new_consensus_secret_key.to_pk()),
+        },
+        &keystore[1].owner_secret_key,
+        2,
+    );
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::ConsensusKeyAlreadyIndexed)
+    );
+
+    // The new key can sign transactions as the node.
+    keystore[0].node_secret_key = new_node_secret_key.clone();
+    simple_epoch_change(0, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+
+    let node_after = query_runner
+        .get_node_info(&new_node_secret_key.to_pk())
+        .unwrap();
+    assert_eq!(node_after.consensus_key, new_consensus_secret_key.to_pk());
+}
+
+#[test]
+async fn test_epoch_randomness() {
+    let (committee, keystore) = get_genesis_committee(6);
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    genesis.committee_size = 4;
+    genesis.epoch_start = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    let seed = query_runner.get_epoch_randomness_seed();
+    assert_eq!(seed, [0; 32]);
+    let share = |member: &GenesisCommitteeKeystore, epoch: Epoch| {
+        member
+            .consensus_secret_key
+            .sign(&randomness_share_transcript(epoch, &seed).hash())
+    };
+    let submit_share = |member: &GenesisCommitteeKeystore, epoch, signature| {
+        let nonce = query_runner
+            .get_node_info(&member.node_secret_key.to_pk())
+            .unwrap()
+            .nonce
+            + 1;
+        get_update_request_node(
+            UpdateMethod::SubmitRandomnessShare { epoch, signature },
+            &member.node_secret_key,
+            nonce,
+        )
+    };
+
+    // The share has to be signed over the current epoch and seed.
+    let update = submit_share(&keystore[0], 0, share(&keystore[0], 1));
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InvalidSignature)
+    );
+    let update = submit_share(&keystore[0], 1, share(&keystore[0], 1));
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::EpochHasNotStarted)
+    );
+
+    let mut expected = Hasher::new();
+    expected.update(&seed);
+    expected.update(&0_u64.to_be_bytes());
+    for member in &keystore[0..5] {
+        let signature = share(member, 0);
+        let update = submit_share(member, 0, signature);
+        let res = run_transaction(vec![update], &update_socket).await.unwrap();
+        assert_eq!(
+            res.txn_receipts[0],
+            TransactionResponse::Success(ExecutionData::None)
+        );
+        let index = query_runner
+            .pubkey_to_index(member.node_secret_key.to_pk())
+            .unwrap();
+        expected.update(&index.to_be_bytes());
+        expected.update(&signature.0);
+    }
+    let update = submit_share(&keystore[0], 0, share(&keystore[0], 0));
+    let res = run_transaction(vec![update], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::AlreadySignaled)
+    );
+
+    // The last member withholds its share.
+    for member in &keystore[0..5] {
+        let nonce = query_runner
+            .get_node_info(&member.node_secret_key.to_pk())
+            .unwrap()
+            .nonce
+            + 1;
+        let update = get_update_request_node(
+            UpdateMethod::ChangeEpoch { epoch: 0 },
+            &member.node_secret_key,
+            nonce,
+        );
+        run_transaction(vec![update], &update_socket).await.unwrap();
+    }
+    assert_eq!(query_runner.get_epoch(), 1);
+
+    // The new seed mixes the submitted shares into the previous seed, leaving out the member
+    // that withheld its share, and the new committee is drawn from the nodes using the seed. A
+    // single missed share does not get the member jailed.
+    assert_eq!(
+        query_runner.get_epoch_randomness_seed(),
+        *expected.finalize().as_bytes()
+    );
+    let withholder = query_runner
+        .get_node_info(&keystore[5].node_secret_key.to_pk())
+        .unwrap();
+    assert_eq!(withholder.missed_randomness_shares, 1);
+    assert_eq!(withholder.jailed_since, None);
+    let members = query_runner.get_committee_members();
+    assert_eq!(members.len(), 4);
+    assert_eq!(members.iter().collect::<BTreeSet<_>>().len(), 4);
+    assert!(members.iter().all(|member| {
+        keystore
+            .iter()
+            .any(|node| node.node_secret_key.to_pk() == *member)
+    }));
+}
+
+#[test]
+async fn test_missed_randomness_shares() {
+    let (committee, keystore) = get_genesis_committee(4);
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    genesis.committee_size = 4;
+    genesis.jail_epochs = 2;
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+    let withholder = keystore[3].node_secret_key.to_pk();
+    let nonce = |node: &NodeSecretKey| query_runner.get_node_info(&node.to_pk()).unwrap().nonce + 1;
+
+    // The member is only jailed once it missed its share in two committees in a row.
+    for (epoch, withhold, missed, jailed_since) in [
+        (0, true, 1, None),
+        (1, false, 0, None),
+        (2, true, 1, None),
+        (3, true, 2, Some(3)),
+    ] {
+        let seed = query_runner.get_epoch_randomness_seed();
+        for node in &keystore {
+            if withhold && node.node_secret_key.to_pk() == withholder {
+                continue;
+            }
+            let signature = node
+                .consensus_secret_key
+                .sign(&randomness_share_transcript(epoch, &seed).hash());
+            let update = get_update_request_node(
+                UpdateMethod::SubmitRandomnessShare { epoch, signature },
+                &node.node_secret_key,
+                nonce(&node.node_secret_key),
+            );
+            run_transaction(vec![update], &update_socket).await.unwrap();
+        }
+        for node in &keystore[0..3] {
+            let update = get_update_request_node(
+                UpdateMethod::ChangeEpoch { epoch },
+                &node.node_secret_key,
+                nonce(&node.node_secret_key),
+            );
+            run_transaction(vec![update], &update_socket).await.unwrap();
+        }
+        assert_eq!(query_runner.get_epoch(), epoch + 1);
+
+        let node = query_runner.get_node_info(&withholder).unwrap();
+        assert_eq!(node.missed_randomness_shares, missed);
+        assert_eq!(node.jailed_since, jailed_since);
+        assert_eq!(
+            query_runner.get_committee_members().contains(&withholder),
+            jailed_since.is_none()
+        );
+    }
+}
+
+#[test]
+async fn test_jail_and_unjail() {
+    let (mut committee, keystore) = get_genesis_committee(5);
+    committee[4].reputation = Some(5);
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee.clone();
+    genesis.jail_reputation_threshold = 10;
+    genesis.jail_epochs = 2;
+    genesis.unjail_cooldown = 2;
+    genesis.epoch_start = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    fund_test_client(1_000_000, &update_socket).await;
+
+    let jailed_node = keystore[4].node_secret_key.to_pk();
+    let owner_secret_key = &keystore[4].owner_secret_key;
+    let nonce = || query_runner.get_node_info(&jailed_node).unwrap().nonce + 1;
+
+    // The node is only jailed after staying below the threshold for two epochs.
+    for epoch in 0..2 {
+        assert!(query_runner.is_valid_node(&jailed_node));
+        let res = run_transaction(
+            vec![pod_request(&keystore[4].node_secret_key, 1000, 0, nonce())],
+            &update_socket,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            res.txn_receipts[0],
+            TransactionResponse::Success(ExecutionData::None)
+        );
+        let (_, committee_keystore) = get_new_committee(&query_runner, &committee, &keystore);
+        simple_epoch_change(epoch, &committee_keystore, &update_socket, &query_runner)
+            .await
+            .unwrap();
+    }
+
+    let node_info = query_runner.get_node_info(&jailed_node).unwrap();
+    assert_eq!(node_info.jailed_since, Some(1));
+    assert_eq!(node_info.low_reputation_epochs, 2);
+    assert!(!query_runner.is_valid_node(&jailed_node));
+    assert!(
+        !query_runner
+            .get_node_registry()
+            .iter()
+            .any(|node| node.public_key == jailed_node)
+    );
+    assert!(!query_runner.get_committee_members().contains(&jailed_node));
+    // The node was paid for the first epoch but not for the epoch it was jailed in.
+    let zero = HpUfixed::<18>::zero();
+    assert!(query_runner.get_rewards(0, &jailed_node).unwrap().node.flk > zero);
+    assert_eq!(
+        query_runner.get_rewards(1, &jailed_node).unwrap().node.flk,
+        zero
+    );
+
+    // Jailed nodes can not submit work.
+    let res = run_transaction(
+        vec![pod_request(&keystore[4].node_secret_key, 1000, 0, nonce())],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::NodeJailed)
+    );
+
+    let unjail = |node, secret_key, nonce| {
+        get_update_request_account(UpdateMethod::Unjail { node }, secret_key, nonce)
+    };
+    let res = run_transaction(
+        vec![
+            unjail(jailed_node, &keystore[0].owner_secret_key, 1),
+            unjail(
+                keystore[0].node_secret_key.to_pk(),
+                &keystore[0].owner_secret_key,
+                2,
+            ),
+            unjail(jailed_node, owner_secret_key, 1),
+        ],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::NotNodeOwner)
+    );
+    assert_eq!(
+        res.txn_receipts[1],
+        TransactionResponse::Revert(ExecutionError::NodeNotJailed)
+    );
+    assert_eq!(
+        res.txn_receipts[2],
+        TransactionResponse::Revert(ExecutionError::UnjailCooldownNotPassed)
+    );
+
+    // After the cooldown the node can be unjailed and rejoins the registry.
+    let (_, committee_keystore) = get_new_committee(&query_runner, &committee, &keystore);
+    simple_epoch_change(2, &committee_keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    let res = run_transaction(
+        vec![unjail(jailed_node, owner_secret_key, 2)],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    let node_info = query_runner.get_node_info(&jailed_node).unwrap();
+    assert_eq!(node_info.jailed_since, None);
+    assert_eq!(node_info.low_reputation_epochs, 0);
+    assert!(query_runner.is_valid_node(&jailed_node));
+}
+
+#[test]
+async fn test_service_prices() {
+    let (committee, keystore) = get_genesis_committee(4);
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+    let service_owner_secret_key = AccountOwnerSecretKey::generate();
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    genesis.governance_address = governance_secret_key.to_pk().into();
+    genesis.service[0].owner = service_owner_secret_key.to_pk().into();
+    genesis.epoch_start = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    fund_test_client(1_000_000, &update_socket).await;
+
+    let set_price = |service_id, price: f64, secret_key, nonce| {
+        get_update_request_account(
+            UpdateMethod::SetServicePrice {
+                service_id,
+                commodity: CommodityTypes::Bandwidth,
+                price: price.into(),
+            },
+            secret_key,
+            nonce,
+        )
+    };
+    let res = run_transaction(
+        vec![
+            // Only the service owner or governance can set the price.
+            set_price(0, 0.5, &AccountOwnerSecretKey::generate(), 1),
+            set_price(2, 0.5, &governance_secret_key, 1),
+            set_price(0, 0.5, &service_owner_secret_key, 1),
+            set_price(1, 0.3, &governance_secret_key, 2),
+        ],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::NotServiceOwner)
+    );
+    assert_eq!(
+        res.txn_receipts[1],
+        TransactionResponse::Revert(ExecutionError::NonExistingService)
+    );
+    assert_eq!(
+        res.txn_receipts[2],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(
+        res.txn_receipts[3],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+
+    // Work done in this epoch is still paid with the genesis price.
+    let node_secret_key = &keystore[0].node_secret_key;
+    assert_eq!(
+        query_runner.get_service_price(0, CommodityTypes::Bandwidth),
+        Some(0.1.into())
+    );
+    run_transaction(
+        vec![pod_request(node_secret_key, 1000, 0, 1)],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        query_runner.get_total_served(0).reward_pool,
+        (0.1 * 1000_f64).into()
+    );
+
+    simple_epoch_change(0, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    assert_eq!(
+        query_runner.get_service_price(0, CommodityTypes::Bandwidth),
+        Some(0.5.into())
+    );
+    assert_eq!(
+        query_runner.get_service_price(1, CommodityTypes::Bandwidth),
+        Some(0.3.into())
+    );
+    // Services without their own price keep using the genesis price.
+    assert_eq!(
+        query_runner.get_service_price(1, CommodityTypes::Compute),
+        Some(0.2.into())
+    );
+
+    let nonce = query_runner
+        .get_node_info(&node_secret_key.to_pk())
+        .unwrap()
+        .nonce;
+    run_transaction(
+        vec![pod_request(node_secret_key, 1000, 0, nonce + 1)],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        query_runner.get_total_served(1).reward_pool,
+        (0.5 * 1000_f64).into()
+    );
+
+    // Removing a service drops its prices and the price updates that are still queued.
+    let res = run_transaction(
+        vec![
+            get_update_request_account(
+                UpdateMethod::SetServicePrice {
+                    service_id: 1,
+                    commodity: CommodityTypes::Compute,
+                    price: 0.7.into(),
+                },
+                &governance_secret_key,
+                3,
+            ),
+            get_update_request_account(
+                UpdateMethod::RemoveService { service_id: 1 },
+                &governance_secret_key,
+                4,
+            ),
+        ],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[1],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(
+        query_runner.get_service_price(1, CommodityTypes::Bandwidth),
+        Some(0.1.into())
+    );
+    simple_epoch_change(1, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    assert_eq!(
+        query_runner.get_service_price(1, CommodityTypes::Compute),
+        Some(0.2.into())
+    );
+}
+
+#[test]
+async fn test_vesting() {
+    let (committee, keystore) = get_genesis_committee(4);
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let grantee: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let granter_secret_key = AccountOwnerSecretKey::generate();
+    let mut genesis = Genesis::load().unwrap();
+    genesis.node_info = committee;
+    genesis.lock_time = 0;
+    genesis.epoch_start = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1_900,
+        stables_balance: 0,
+        bandwidth_balance: 0,
+        vesting: Some(GenesisVesting {
+            amount: 1_600,
+            cliff: 2,
+            duration: 4,
+        }),
+    });
+    genesis.account.push(GenesisAccount {
+        public_key: granter_secret_key.to_pk().into(),
+        flk_balance: 100,
+        stables_balance: 0,
+        bandwidth_balance: 0,
+        vesting: None,
+    });
+    let (update_socket, query_runner) = init_app(Some(Config {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        testnet: false,
+        storage: StorageConfig::InMemory,
+        db_path: None,
+        db_options: None,
+        history_retention: 0,
+    }));
+
+    let account = query_runner.get_account_info(&owner).unwrap();
+    assert_eq!(account.vested_flk, HpUfixed::zero());
+    assert_eq!(account.unvested_flk, 1_600_u64.into());
+    assert_eq!(
+        query_runner.get_vesting_schedules(&owner),
+        vec![VestingSchedule {
+            amount: 1_600_u64.into(),
+            start: 0,
+            cliff: 2,
+            duration: 4,
+        }]
+    );
+
+    let grant = |amount: u64, duration, secret_key, nonce| {
+        get_update_request_account(
+            UpdateMethod::GrantVesting {
+                recipient: grantee,
+                amount: amount.into(),
+                cliff: 0,
+                duration,
+            },
+            secret_key,
+            nonce,
+        )
+    };
+    // Every grant gets its own schedule, so a tiny grant that never vests doesn't delay another
+    // one.
+    let res = run_transaction(
+        vec![
+            grant(99, 1, &granter_secret_key, 1),
+            grant(1, u64::MAX, &granter_secret_key, 2),
+        ],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(
+        res.txn_receipts[1],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    let account = query_runner.get_account_info(&grantee).unwrap();
+    assert_eq!(account.unvested_flk, 100_u64.into());
+    assert_eq!(query_runner.get_vesting_schedules(&grantee).len(), 2);
+
+    // Only the vested part of the balance can be transferred.
+    let transfer = |amount: u64, to, nonce| {
+        get_update_request_account(
+            UpdateMethod::Transfer {
+                token: Tokens::FLK,
+                amount: amount.into(),
+                to,
+            },
+            &owner_secret_key,
+            nonce,
+        )
+    };
+    let res = run_transaction(
+        vec![transfer(301, grantee, 1), transfer(300, grantee, 2)],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InsufficientVestedBalance)
+    );
+    assert_eq!(
+        res.txn_receipts[1],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    // Nothing vests before the cliff, then the amount vests linearly.
+    for (epoch, vested) in [(0, 0_u64), (1, 800), (2, 1_200)] {
+        simple_epoch_change(epoch, &keystore, &update_socket, &query_runner)
+            .await
+            .unwrap();
+        let account = query_runner.get_account_info(&owner).unwrap();
+        assert_eq!(account.vested_flk, vested.into());
+        assert_eq!(account.unvested_flk, (1_600 - vested).into());
+    }
+    let account = query_runner.get_account_info(&grantee).unwrap();
+    assert_eq!(account.vested_flk, 99_u64.into());
+    assert_eq!(account.unvested_flk, 1_u64.into());
+    assert_eq!(query_runner.get_vesting_schedules(&grantee).len(), 1);
+    // The unvested part of the balance can still be staked.
+    let node_public_key = NodeSecretKey::generate().to_pk();
+    stake(
+        1_500_u64.into(),
+        node_public_key,
+        ConsensusSecretKey::generate().to_pk(),
+        &owner_secret_key,
+        &update_socket,
+        3,
+    )
+    .await;
+    assert_eq!(query_runner.get_flk_balance(&owner), 100_u64.into());
+    let res = run_transaction(vec![transfer(1, grantee, 4)], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InsufficientVestedBalance)
+    );
+    // Unstaking doesn't let the unvested FLK be withdrawn to another account.
+    let withdraw = |nonce| {
+        get_update_request_account(
+            UpdateMethod::WithdrawUnstaked {
+                node: node_public_key,
+                recipient: Some(grantee),
+            },
+            &owner_secret_key,
+            nonce,
+        )
+    };
+    let unstake = get_update_request_account(
+        UpdateMethod::Unstake {
+            amount: 1_500_u64.into(),
+            node: node_public_key,
+        },
+        &owner_secret_key,
+        5,
+    );
+    let res = run_transaction(vec![unstake, withdraw(6)], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(
+        res.txn_receipts[1],
+        TransactionResponse::Revert(ExecutionError::InsufficientVestedBalance)
+    );
+
+    // Once everything vested the schedule is removed and the whole balance can be spent.
+    simple_epoch_change(3, &keystore, &update_socket, &query_runner)
+        .await
+        .unwrap();
+    let account = query_runner.get_account_info(&owner).unwrap();
+    assert_eq!(account.vested_flk, 1_600_u64.into());
+    assert_eq!(account.unvested_flk, HpUfixed::zero());
+    assert_eq!(query_runner.get_vesting_schedules(&owner), vec![]);
+
+    let res = run_transaction(vec![grant(100, 2, &owner_secret_key, 7)], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(query_runner.get_flk_balance(&owner), HpUfixed::zero());
+    let account = query_runner.get_account_info(&grantee).unwrap();
+    assert_eq!(account.flk_balance, 500_u64.into());
+    assert_eq!(account.unvested_flk, 101_u64.into());
+    let schedules = query_runner.get_vesting_schedules(&grantee);
+    assert_eq!(schedules.len(), 2);
+    assert!(schedules.iter().any(|schedule| schedule.start == 4));
+
+    // The vested stake can be withdrawn to another account.
+    let res = run_transaction(vec![withdraw(8)], &update_socket)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Success(ExecutionData::None)
+    );
+    assert_eq!(query_runner.get_flk_balance(&grantee), 2_000_u64.into());
+}
+
+#[test]
+async fn test_replay_protection() {
+    let (update_socket, query_runner) = init_app(None);
+    assert_eq!(query_runner.get_chain_id(), TEST_CHAIN_ID);
+
+    let secret_key = AccountOwnerSecretKey::generate();
+    let sender: EthAddress = secret_key.to_pk().into();
+    let amount: HpUfixed<18> = 1_000_u64.into();
+    let request = |version, chain_id| {
+        let payload = UpdatePayload {
+            version,
+            chain_id,
+            nonce: 1,
+            method: UpdateMethod::Deposit {
+                proof: get_proof_of_consensus(
+                    sender,
+                    Tokens::FLK,
+                    amount.clone(),
+                    &BRIDGE_VALIDATORS,
+                ),
+                token: Tokens::FLK,
+                amount: amount.clone(),
+            },
+        };
+        let signature = secret_key.sign(&payload.to_digest());
+        UpdateRequest {
+            sender: secret_key.to_pk().into(),
+            signature: signature.into(),
+            payload,
+        }
+    };
+
+    // Transactions signed for another network or in an unknown format are rejected without
+    // using up the nonce.
+    let res = run_transaction(
+        vec![
+            request(UPDATE_PAYLOAD_VERSION, TEST_CHAIN_ID + 1),
+            request(UPDATE_PAYLOAD_VERSION + 1, TEST_CHAIN_ID),
+            request(UPDATE_PAYLOAD_VERSION, TEST_CHAIN_ID),
+        ],
+        &update_socket,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        res.txn_receipts,
+        vec![
+            TransactionResponse::Revert(ExecutionError::InvalidChainId),
+            TransactionResponse::Revert(ExecutionError::UnsupportedPayloadVersion),
+            TransactionResponse::Success(ExecutionData::None),
+        ]
+    );
+    assert_eq!(query_runner.get_flk_balance(&sender), amount);
+
+    // Changing the chain id after signing invalidates the signature.
+    let mut replay = request(UPDATE_PAYLOAD_VERSION, TEST_CHAIN_ID + 1);
+    replay.payload.chain_id = TEST_CHAIN_ID;
+    replay.payload.nonce = 2;
+    let res = run_transaction(vec![replay], &update_socket).await.unwrap();
+    assert_eq!(
+        res.txn_receipts[0],
+        TransactionResponse::Revert(ExecutionError::InvalidSignature)
+    );
+}
+
+#[tokio::test]
+async fn test_state_proofs() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let deposit = |nonce: u64| {
+        get_update_request_account(
+            UpdateMethod::Deposit {
+                proof: get_proof_of_consensus(owner, Tokens::FLK, nonce.into(), &BRIDGE_VALIDATORS),
+                token: Tokens::FLK,
+                amount: nonce.into(),
+            },
+            &owner_secret_key,
+            nonce,
+        )
+    };
+
+    let res = run_transaction(vec![deposit(1)], &update_socket)
+        .await
+        .unwrap();
+    let root = query_runner.get_state_root();
+    assert_eq!(res.state_root, root);
+
+    // The entries are proven under the names of their tables.
+    let (account, proof) = query_runner.get_account_info_with_proof(&owner);
+    let account = account.unwrap();
+    assert_eq!(account.flk_balance, 1_u64.into());
+    let key = DefaultSerdeBackend::serialize_key(&owner);
+    let value = bincode::serialize(&account).unwrap();
+    assert!(proof.verify(&root, "account", &key, Some(&value)));
+    assert!(!proof.verify(&root, "account", &key, None));
+
+    let missing: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let (account, proof) = query_runner.get_account_info_with_proof(&missing);
+    assert_eq!(account, None);
+    assert!(proof.verify(
+        &root,
+        "account",
+        &DefaultSerdeBackend::serialize_key(&missing),
+        None
+    ));
+
+    let (node, proof) = query_runner.get_node_info_with_proof(&0);
+    let node = node.unwrap();
+    assert_eq!(
+        Some(node.clone()),
+        query_runner.get_node_info(&node.public_key)
+    );
+    assert!(proof.verify(
+        &root,
+        "node",
+        &DefaultSerdeBackend::serialize_key(&0_u32),
+        Some(&bincode::serialize(&node).unwrap())
+    ));
+
+    // The proofs of an older block do not verify against the new root.
+    let res = run_transaction(vec![deposit(2)], &update_socket)
+        .await
+        .unwrap();
+    assert_ne!(res.state_root, root);
+    assert_eq!(res.state_root, query_runner.get_state_root());
+    assert!(!proof.verify(
+        &res.state_root,
+        "node",
+        &DefaultSerdeBackend::serialize_key(&0_u32),
+        Some(&bincode::serialize(&node).unwrap())
+    ));
+}
+
+#[test]
+async fn test_last_epoch_delta_hashes() {
+    let config = Config::test();
+    let mut env = Env::new(&config, None, Vec::new()).unwrap();
+    env.genesis(&config);
+    let query_runner = env.query_runner();
+    assert!(query_runner.get_last_epoch_delta_hashes().is_empty());
+
+    // The deltas are kept in order until the next full checkpoint.
+    env.update_last_epoch_hash([1; 32]);
+    env.add_last_epoch_delta_hash([2; 32]);
+    env.add_last_epoch_delta_hash([3; 32]);
+    assert_eq!(query_runner.get_last_epoch_hash(), [1; 32]);
+    assert_eq!(
+        query_runner.get_last_epoch_delta_hashes(),
+        vec![[2; 32], [3; 32]]
+    );
+
+    env.update_last_epoch_hash([4; 32]);
+    assert_eq!(query_runner.get_last_epoch_hash(), [4; 32]);
+    assert!(query_runner.get_last_epoch_delta_hashes().is_empty());
 }
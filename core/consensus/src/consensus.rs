@@ -6,13 +6,14 @@
 use affair::{Executor, TokioSpawn};
 use async_trait::async_trait;
 use derive_more::{From, IsVariant, TryInto};
+use fleek_crypto::{ConsensusSecretKey, SecretKey};
 use lightning_interfaces::application::ExecutionEngineSocket;
 use lightning_interfaces::common::WithStartAndShutdown;
 use lightning_interfaces::config::ConfigConsumer;
 use lightning_interfaces::consensus::{ConsensusInterface, MempoolSocket};
 use lightning_interfaces::infu_collection::{c, Collection};
 use lightning_interfaces::signer::{SignerInterface, SubmitTxSocket};
-use lightning_interfaces::types::{Epoch, EpochInfo, UpdateMethod};
+use lightning_interfaces::types::{randomness_share_transcript, Epoch, EpochInfo, UpdateMethod};
 use lightning_interfaces::{
     ApplicationInterface,
     BroadcastInterface,
@@ -227,8 +228,24 @@ fn get_epoch_info(&self) -> (Committee, WorkerCache, u64, u64) {
     async fn wait_to_signal_epoch_change(&self, time_until_change: Duration, epoch: Epoch) {
         let txn_socket = self.txn_socket.clone();
         let query_runner = self.query_runner.clone();
+        let consensus_secret_key =
+            ConsensusSecretKey::from(self.narwhal_args.primary_keypair.copy().private());
         task::spawn(async move {
             time::sleep(time_until_change).await;
+
+            // Contribute to the randomness of the next epoch before signalling, the signer sends
+            // the transactions in order so the share is included before the epoch changes.
+            let seed = query_runner.get_epoch_randomness_seed();
+            let signature =
+                consensus_secret_key.sign(&randomness_share_transcript(epoch, &seed).hash());
+            if txn_socket
+                .run(UpdateMethod::SubmitRandomnessShare { epoch, signature })
+                .await
+                .is_err()
+            {
+                error!("Error sending randomness share transaction to signer interface");
+            }
+
             info!("Narwhal: Signalling ready to change epoch");
             // We shouldnt panic here lets repeatedly try.
             loop {
@@ -140,6 +140,7 @@ async fn start_node(
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         },
         Default::default(),
         Default::default(),
@@ -376,6 +376,7 @@ fn get_reputation_query() -> lightning_rep_collector::MyReputationQuery {
                 storage: StorageConfig::InMemory,
                 db_path: None,
                 db_options: None,
+                history_retention: 0,
             },
             Default::default(),
             Default::default(),
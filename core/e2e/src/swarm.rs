@@ -253,6 +253,7 @@ pub fn build(self) -> Swarm {
                 storage,
                 db_path: Some(root.join("data/app_db").try_into().unwrap()),
                 db_options: None,
+                history_retention: 0,
             });
 
             let node = ContainerizedNode::new(config, owner_sk, index);
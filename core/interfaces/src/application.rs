@@ -12,17 +12,28 @@
 use crate::types::{
     Block,
     BlockExecutionResponse,
+    ChainId,
+    CommodityTypes,
+    Delegation,
     Epoch,
     EpochInfo,
     NodeInfo,
     NodeServed,
+    PendingWithdrawal,
+    Proposal,
+    ProposalId,
     ProtocolParams,
     ReportedReputationMeasurements,
+    RewardsBreakdown,
     Service,
     ServiceId,
+    StateProof,
     TotalServed,
+    TransactionReceipt,
     TransactionResponse,
+    TransactionSimulation,
     UpdateRequest,
+    VestingSchedule,
 };
 use crate::{BlockStoreInterface, BlockStoreServerInterface, ConfigProviderInterface};
 
@@ -82,8 +93,8 @@ pub trait SyncQueryRunnerInterface: Clone + Send + Sync + 'static {
     /// Returns the latest bandwidth balance associated with the given account public key.
     fn get_account_balance(&self, account: &EthAddress) -> u128;
 
-    /// Returns the latest bandwidth balance associated with the given client public key.
-    fn get_client_balance(&self, client: &ClientPublicKey) -> u128;
+    /// Returns the stables held in escrow for the given client public key.
+    fn get_client_balance(&self, client: &ClientPublicKey) -> HpUfixed<6>;
 
     /// Returns the latest FLK balance of an account
     fn get_flk_balance(&self, account: &EthAddress) -> HpUfixed<18>;
@@ -94,6 +105,9 @@ pub trait SyncQueryRunnerInterface: Clone + Send + Sync + 'static {
     /// Returns the amount of flk a node has staked
     fn get_staked(&self, node: &NodePublicKey) -> HpUfixed<18>;
 
+    /// Returns the stake an account delegated to a node
+    fn get_delegation(&self, node: &NodePublicKey, delegator: &EthAddress) -> Option<Delegation>;
+
     /// Returns the amount of locked tokens a node has
     fn get_locked(&self, node: &NodePublicKey) -> HpUfixed<18>;
 
@@ -124,18 +138,35 @@ pub trait SyncQueryRunnerInterface: Clone + Send + Sync + 'static {
     /// Returns information about an account.
     fn get_account_info(&self, id: &EthAddress) -> Option<AccountInfo>;
 
+    /// Returns the root of the state tree as of the latest executed block.
+    fn get_state_root(&self) -> [u8; 32];
+
+    /// Returns information about an account as of the latest executed block, together with the
+    /// proof of it (or of its absence) against the state root of that block.
+    fn get_account_info_with_proof(&self, id: &EthAddress) -> (Option<AccountInfo>, StateProof);
+
+    /// Returns information about the node with the given index as of the latest executed block,
+    /// together with the proof of it (or of its absence) against the state root of that block.
+    fn get_node_info_with_proof(&self, index: &NodeIndex) -> (Option<NodeInfo>, StateProof);
+
+    /// Returns the vesting schedules of an account that still have FLK left to vest.
+    fn get_vesting_schedules(&self, account: &EthAddress) -> Vec<VestingSchedule>;
+
     /// Returns a full copy of the entire node-registry, but only contains the nodes that
-    /// are still a valid node and have enough stake.
+    /// are still a valid node, have enough stake and are not jailed.
     fn get_node_registry(&self) -> Vec<NodeInfo>;
 
-    /// Returns true if the node is a valid node in the network, with enough stake.
+    /// Returns true if the node is a valid node in the network, with enough stake and not jailed.
     fn is_valid_node(&self, id: &NodePublicKey) -> bool;
 
     /// Returns the amount that is required to be a valid node in the network.
     fn get_staking_amount(&self) -> u128;
 
     /// Returns the randomness that was used to start the current epoch.
-    fn get_epoch_randomness_seed(&self) -> &[u8; 32];
+    fn get_epoch_randomness_seed(&self) -> [u8; 32];
+
+    /// Returns the state root the current epoch started with.
+    fn get_epoch_state_root(&self) -> [u8; 32];
 
     /// Returns the committee members of the current epoch.
     fn get_committee_members(&self) -> Vec<NodePublicKey>;
@@ -146,6 +177,9 @@ pub trait SyncQueryRunnerInterface: Clone + Send + Sync + 'static {
     /// Returns just the current epoch
     fn get_epoch(&self) -> Epoch;
 
+    /// Returns the id of the network that transactions have to be signed for
+    fn get_chain_id(&self) -> ChainId;
+
     /// Returns all the information on the current epoch that Narwhal needs to run
     fn get_epoch_info(&self) -> EpochInfo;
 
@@ -155,6 +189,10 @@ pub trait SyncQueryRunnerInterface: Clone + Send + Sync + 'static {
     /// Return all commodity served for a give node for current epoch
     fn get_node_served(&self, node: &NodePublicKey) -> NodeServed;
 
+    /// Returns the breakdown of the rewards of a node in the given epoch, [`None`] if no rewards
+    /// were distributed at the end of the epoch.
+    fn get_rewards(&self, epoch: Epoch, node: &NodePublicKey) -> Option<RewardsBreakdown>;
+
     /// Return the current total supply of FLK tokens
     fn get_total_supply(&self) -> HpUfixed<18>;
 
@@ -170,12 +208,25 @@ pub trait SyncQueryRunnerInterface: Clone + Send + Sync + 'static {
     /// Validates the passed in transaction
     fn validate_txn(&self, txn: UpdateRequest) -> TransactionResponse;
 
+    /// Runs the transaction against a throwaway copy of the current state and returns its response
+    /// together with the changes it would make to the state. Nothing is committed and the
+    /// signature of the transaction is not checked.
+    fn simulate_txn(&self, txn: UpdateRequest) -> TransactionSimulation;
+
     /// Return all latencies measurements for the current epoch.
     fn get_latencies(&self) -> HashMap<(NodePublicKey, NodePublicKey), Duration>;
 
     /// returns the service information for a given [`ServiceId`]
     fn get_service_info(&self, service_id: ServiceId) -> Service;
 
+    /// Returns the price the service pays for the commodity in the current epoch, which is the
+    /// network wide commodity price unless the service has its own price.
+    fn get_service_price(
+        &self,
+        service_id: ServiceId,
+        commodity: CommodityTypes,
+    ) -> Option<HpUfixed<6>>;
+
     fn pubkey_to_index(&self, node: NodePublicKey) -> Option<u32>;
 
     fn index_to_pubkey(&self, node_index: u32) -> Option<NodePublicKey>;
@@ -183,11 +234,43 @@ pub trait SyncQueryRunnerInterface: Clone + Send + Sync + 'static {
     /// Returns the hash of the last epoch.
     fn get_last_epoch_hash(&self) -> [u8; 32];
 
+    /// Returns the hashes of the state deltas of the epochs after the last epoch checkpoint, in
+    /// order. Applied on top of the checkpoint they lead to the state of the current epoch.
+    fn get_last_epoch_delta_hashes(&self) -> Vec<[u8; 32]>;
+
     /// takes NodeInfo and returns if they are a current committee member or not
     fn is_committee(&self, node_index: u32) -> bool;
 
     /// Returns last executed block hash. [0;32] is genesis
     fn get_last_block(&self) -> [u8; 32];
+
+    /// Returns all the withdrawals that were executed on the network together with their
+    /// withdraw id, ordered by the id. These are picked up by the bridge to release the tokens
+    /// on the L2.
+    fn get_pending_withdrawals(&self) -> Vec<(u64, PendingWithdrawal)>;
+
+    /// Returns the governance proposal with the given id together with its current votes.
+    fn get_proposal(&self, proposal_id: ProposalId) -> Option<Proposal>;
+
+    /// Returns the receipt of the executed transaction with the given hash.
+    fn get_transaction_receipt(&self, hash: &[u8; 32]) -> Option<TransactionReceipt>;
+
+    /// Returns the receipts of all the transactions that were executed in the given block, in
+    /// the order they were executed in.
+    fn get_block_transactions(&self, block_hash: &[u8; 32]) -> Vec<TransactionReceipt>;
+
+    /// Returns the number of the last executed block. 0 is genesis
+    fn get_block_number(&self) -> u64;
+
+    /// Returns a query runner that reads the state as it was after the given block was executed,
+    /// or `None` if the state of that block is no longer retained. The history is kept in memory
+    /// and is not persisted, so the blocks executed before the node started are never retained.
+    fn at_block(&self, block_number: u64) -> Option<Self>;
+
+    /// Returns a query runner that reads the state as it was at the end of the given epoch, or
+    /// `None` if the epoch did not end yet or its state is no longer retained, see
+    /// [`SyncQueryRunnerInterface::at_block`].
+    fn at_epoch(&self, epoch: Epoch) -> Option<Self>;
 }
 
 #[derive(Clone, Debug)]
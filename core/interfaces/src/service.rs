@@ -1,11 +1,12 @@
 use std::future::Future;
 
 use fn_sdk::internal::{OnConnectedArgs, OnDisconnectedArgs, OnMessageArgs};
+use infusion::c;
 
 use crate::blockstore::BlockStoreInterface;
 use crate::infu_collection::Collection;
 use crate::types::ServiceId;
-use crate::{ConfigConsumer, ConfigProviderInterface, WithStartAndShutdown};
+use crate::{ApplicationInterface, ConfigConsumer, ConfigProviderInterface, WithStartAndShutdown};
 
 /// The service executor interface is responsible for loading the services and executing
 /// these services.
@@ -16,15 +17,23 @@
 pub trait ServiceExecutorInterface<C: Collection>:
     WithStartAndShutdown + ConfigConsumer + Sized + Send + Sync
 {
-    fn _init(config: ::ConfigProviderInterface, blockstore: ::BlockStoreInterface) {
-        Self::init(config.get::<Self>(), blockstore)
+    fn _init(
+        config: ::ConfigProviderInterface,
+        blockstore: ::BlockStoreInterface,
+        app: ::ApplicationInterface,
+    ) {
+        Self::init(config.get::<Self>(), blockstore, app.sync_query())
     }
 
     /// The provider which can be used to get a handle on a service during runtime.
     type Provider: ExecutorProviderInterface;
 
     /// Initialize the service executor.
-    fn init(config: Self::Config, blockstore: &C::BlockStoreInterface) -> anyhow::Result<Self>;
+    fn init(
+        config: Self::Config,
+        blockstore: &C::BlockStoreInterface,
+        query_runner: c!(C::ApplicationInterface::SyncExecutor),
+    ) -> anyhow::Result<Self>;
 
     /// Returns the service handle provider which can be used to query and get a handle to a
     /// service that is running.
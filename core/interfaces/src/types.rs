@@ -1,2 +1,4 @@
+/// Re-export the proofs of the application state
+pub use atomo::StateProof;
 /// Re-export all lightning types
 pub use lightning_types::*;
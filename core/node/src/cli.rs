@@ -4,13 +4,22 @@
 use std::fs;
 use std::future::Future;
 use std::io::Read;
-use std::path::PathBuf;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context, Result};
 use clap::{arg, ArgAction, Parser, Subcommand};
-use fleek_crypto::{ConsensusSecretKey, NodeSecretKey, PublicKey, SecretKey};
+use fleek_crypto::{
+    AccountOwnerSecretKey,
+    ConsensusSecretKey,
+    EthAddress,
+    NodeSecretKey,
+    PublicKey,
+    SecretKey,
+};
 use lightning_application::app::Application;
 use lightning_blockstore::blockstore::Blockstore;
 use lightning_blockstore_server::BlockStoreServer;
@@ -21,7 +30,9 @@
     IncrementalPutInterface,
     SignerInterface,
 };
+use lightning_signer::utils::save;
 use lightning_signer::Signer;
+use lightning_types::{Tokens, UpdateMethod};
 use log::{LevelFilter, Record};
 use log4rs::append::console::ConsoleAppender;
 use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
@@ -38,6 +49,7 @@
 use crate::config::TomlConfigProvider;
 use crate::shutdown::ShutdownController;
 use crate::testnet_sync;
+use crate::txn::{get_node_info, send_account_txn};
 
 const DEFAULT_CONFIG_PATH: &str = "~/.lightning/config.toml";
 
@@ -67,6 +79,22 @@ pub enum Command {
     /// Handle keys.
     #[command(subcommand)]
     Keys(Keys),
+    /// Transfer tokens from an account to another account in the network.
+    Transfer {
+        /// Path to the pem file of the secret key of the sending account.
+        #[arg(long)]
+        account_key: PathBuf,
+        /// The address of the receiving account.
+        to: EthAddress,
+        /// The amount of tokens to transfer.
+        amount: u64,
+        /// Which token to transfer, either flk or usdc.
+        #[arg(long, default_value = "flk", value_parser = parse_token)]
+        token: Tokens,
+        /// The address of the RPC server of the node that the transaction is sent to.
+        #[arg(long, default_value = "127.0.0.1:4069")]
+        rpc: SocketAddr,
+    },
     /// Print the loaded configuration.
     ///
     /// By default this command prints the loaded configuration.
@@ -99,6 +127,30 @@ pub enum Keys {
     /// Generate new keys.
     /// This command will fail if the keys already exist.
     Generate,
+    /// Generate new node and consensus keys and rotate the keys of the node to them.
+    /// The old keys are kept next to the new ones with an `.old` suffix.
+    Rotate {
+        /// Path to the pem file of the secret key of the account that owns the node.
+        #[arg(long)]
+        account_key: PathBuf,
+        /// The address of the RPC server of the node that the transaction is sent to.
+        #[arg(long, default_value = "127.0.0.1:4069")]
+        rpc: SocketAddr,
+    },
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+fn parse_token(token: &str) -> Result<Tokens> {
+    match token.to_lowercase().as_str() {
+        "flk" => Ok(Tokens::FLK),
+        "usdc" => Ok(Tokens::USDC),
+        _ => Err(anyhow!("Unknown token: {token}")),
+    }
 }
 
 /// Create a new command line application.
@@ -214,6 +266,16 @@ pub async fn exec(self) -> Result<()> {
             Command::Run {} => self.run(config_path).await,
             Command::Keys(Keys::Generate) => Self::generate_keys(config_path).await,
             Command::Keys(Keys::Show) => Self::show_keys(config_path).await,
+            Command::Keys(Keys::Rotate { account_key, rpc }) => {
+                Self::rotate_keys(config_path, account_key, *rpc).await
+            },
+            Command::Transfer {
+                account_key,
+                to,
+                amount,
+                token,
+                rpc,
+            } => Self::transfer(account_key, *to, *amount, token.clone(), *rpc).await,
             Command::PrintConfig { default } if *default => Self::print_default_config().await,
             Command::PrintConfig { .. } => Self::print_config(config_path).await,
             Command::Dev(cmd) => cmd.exec::<C>(config_path).await,
@@ -327,6 +389,101 @@ async fn generate_keys(config_path: ResolvedPathBuf) -> Result<()> {
         Ok(())
     }
 
+    async fn rotate_keys(
+        config_path: ResolvedPathBuf,
+        account_key: &Path,
+        rpc: SocketAddr,
+    ) -> Result<()> {
+        let config = Arc::new(Self::load_or_write_config(config_path).await?);
+        let signer_config = config.get::<C::SignerInterface>();
+
+        let account_secret_key =
+            fs::read_to_string(account_key).with_context(|| "Failed to read account pem file")?;
+        let account_secret_key = AccountOwnerSecretKey::decode_pem(&account_secret_key)
+            .with_context(|| "Failed to decode account pem file")?;
+        let node_secret_key = fs::read_to_string(&signer_config.node_key_path)
+            .with_context(|| "Failed to read node pem file")?;
+        let node_secret_key = NodeSecretKey::decode_pem(&node_secret_key)
+            .with_context(|| "Failed to decode node pem file")?;
+
+        // The new keys are written to disk before the transaction is sent so they are never lost,
+        // and only replace the current keys once the rotation is visible on chain.
+        let new_node_key_path = with_suffix(&signer_config.node_key_path, ".new");
+        let new_consensus_key_path = with_suffix(&signer_config.consensus_key_path, ".new");
+        if new_node_key_path.exists() || new_consensus_key_path.exists() {
+            return Err(anyhow!(
+                "Keys from a previous rotation exist at {new_node_key_path:?}. Not rotating keys."
+            ));
+        }
+        let new_node_secret_key = NodeSecretKey::generate();
+        let new_consensus_secret_key = ConsensusSecretKey::generate();
+        save(&new_node_key_path, new_node_secret_key.encode_pem())?;
+        save(
+            &new_consensus_key_path,
+            new_consensus_secret_key.encode_pem(),
+        )?;
+
+        let new_public_key = new_node_secret_key.to_pk();
+        let new_consensus_key = new_consensus_secret_key.to_pk();
+        let method = UpdateMethod::RotateKeys {
+            node: node_secret_key.to_pk(),
+            new_public_key: Some(new_public_key),
+            new_consensus_key: Some(new_consensus_key),
+        };
+        send_account_txn(rpc, &account_secret_key, method).await?;
+
+        let mut rotated = false;
+        for _ in 0..30 {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            if let Ok(Some(info)) = get_node_info(rpc, &new_public_key).await {
+                rotated = info.consensus_key == new_consensus_key;
+                break;
+            }
+        }
+        if !rotated {
+            return Err(anyhow!(
+                "The key rotation was not applied. The new keys are kept at {new_node_key_path:?} \
+                 and {new_consensus_key_path:?}."
+            ));
+        }
+
+        for (path, new_path) in [
+            (signer_config.node_key_path.to_path_buf(), new_node_key_path),
+            (
+                signer_config.consensus_key_path.to_path_buf(),
+                new_consensus_key_path,
+            ),
+        ] {
+            fs::rename(&path, with_suffix(&path, ".old"))?;
+            fs::rename(new_path, &path)?;
+        }
+        println!("Node Public Key: {}", new_public_key.to_base64());
+        println!("Consensus Public Key: {}", new_consensus_key.to_base64());
+        println!("Successfully rotated the keys, restart the node to use them.");
+        Ok(())
+    }
+
+    async fn transfer(
+        account_key: &Path,
+        to: EthAddress,
+        amount: u64,
+        token: Tokens,
+        rpc: SocketAddr,
+    ) -> Result<()> {
+        let account_secret_key =
+            fs::read_to_string(account_key).with_context(|| "Failed to read account pem file")?;
+        let account_secret_key = AccountOwnerSecretKey::decode_pem(&account_secret_key)
+            .with_context(|| "Failed to decode account pem file")?;
+        let method = UpdateMethod::Transfer {
+            token,
+            amount: amount.into(),
+            to,
+        };
+        send_account_txn(rpc, &account_secret_key, method).await?;
+        println!("Submitted transfer of {amount} to {to}");
+        Ok(())
+    }
+
     async fn show_keys(config_path: ResolvedPathBuf) -> Result<()> {
         let config = Arc::new(Self::load_or_write_config(config_path).await?);
         let signer_config = config.get::<C::SignerInterface>();
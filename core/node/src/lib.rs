@@ -3,3 +3,4 @@
 pub mod node;
 pub mod shutdown;
 pub mod testnet_sync;
+pub mod txn;
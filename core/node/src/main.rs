@@ -3,6 +3,7 @@
 pub mod node;
 pub mod shutdown;
 pub mod testnet_sync;
+pub mod txn;
 
 use std::process::exit;
 
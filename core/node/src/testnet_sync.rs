@@ -1,5 +1,5 @@
 use std::fs;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 
 use anyhow::{anyhow, Result};
 use atomo_rocks::{Cache as RocksCache, Env as RocksEnv, Options};
@@ -44,7 +44,8 @@ pub async fn sync(
     } else {
         panic!("Node is not whitelisted. Please join the Fleek Discord to get invited.");
     }
-    let mut env = Env::new(&app_config, None).expect("Failed to initialize environment.");
+    let mut env =
+        Env::new(&app_config, None, Vec::new()).expect("Failed to initialize environment.");
     if !env.genesis(&app_config) {
         info!("State already exists. Not loading genesis.");
     }
@@ -161,40 +162,94 @@ async fn attempt_to_sync_state<C: Collection>(
         .await
         {
             let hash = res.result;
+            let delta_hashes = match rpc_request::<Vec<[u8; 32]>>(
+                &client,
+                node.primary_domain,
+                node.ports.rpc,
+                rpc_last_epoch_delta_hashes().to_string(),
+            )
+            .await
+            {
+                Ok(res) => res.result,
+                Err(e) => {
+                    warn!(
+                        "Failed to get the delta hashes from node {}: {e:?}",
+                        node.primary_public_key
+                    );
+                    continue;
+                },
+            };
             let address = format!("{}:{}", node.primary_domain, node.ports.blockstore)
                 .parse()
                 .unwrap();
-            if let Err(e) = blockstore_server.request_download(hash, address).await {
+            let Some(checkpoint) =
+                download::<C>(&blockstore, &blockstore_server, hash, address).await
+            else {
                 warn!(
-                    "Failed to download checkpoint with hash {hash:?} from node {}: {e:?}",
+                    "Failed to download checkpoint with hash {hash:?} from node {}",
                     node.primary_public_key
                 );
                 continue;
+            };
+            let mut deltas = Vec::new();
+            for delta_hash in &delta_hashes {
+                match download::<C>(&blockstore, &blockstore_server, *delta_hash, address).await {
+                    Some(delta) => deltas.push((*delta_hash, delta)),
+                    None => break,
+                }
             }
-            if let Some(checkpoint) = blockstore.read_all_to_vec(&hash).await {
-                // Attempt to build db from checkpoint.
-                match Env::new(config, Some((hash, checkpoint))) {
-                    Ok(mut env) => {
-                        info!("Successfully built database from checkpoint with hash {hash:?}.");
+            if deltas.len() < delta_hashes.len() {
+                warn!(
+                    "Failed to download the deltas of checkpoint with hash {hash:?} from node {}",
+                    node.primary_public_key
+                );
+                continue;
+            }
+            // Attempt to build db from checkpoint.
+            match Env::new(config, Some((hash, checkpoint)), deltas) {
+                Ok(mut env) => {
+                    info!("Successfully built database from checkpoint with hash {hash:?}.");
 
-                        // Update the last epoch has on state
-                        env.update_last_epoch_hash(hash);
+                    // Update the last epoch hash on state, like the node that stored the
+                    // checkpoint or the last delta did.
+                    match delta_hashes.last() {
+                        Some(delta_hash) => env.add_last_epoch_delta_hash(*delta_hash),
+                        None => env.update_last_epoch_hash(hash),
+                    }
 
-                        return Ok(());
-                    },
-                    Err(e) => {
-                        warn!(
-                            "Failed to built database from checkpoint with hash {hash:?} that we received from node {:?}: {e:?}",
-                            node.primary_public_key
-                        );
-                    },
-                }
+                    return Ok(());
+                },
+                Err(e) => {
+                    warn!(
+                        "Failed to built database from checkpoint with hash {hash:?} that we received from node {:?}: {e:?}",
+                        node.primary_public_key
+                    );
+                },
             }
         }
     }
     Err(anyhow!("Failed to sync state."))
 }
 
+/// Reads the content with the given hash from the blockstore, downloading it from the given
+/// address first if it is not there. Our own last checkpoint is usually there already, so only
+/// the deltas after it are downloaded.
+async fn download<C: Collection>(
+    blockstore: &C::BlockStoreInterface,
+    blockstore_server: &C::BlockStoreServerInterface,
+    hash: [u8; 32],
+    address: SocketAddr,
+) -> Option<Vec<u8>> {
+    if let Some(content) = blockstore.read_all_to_vec(&hash).await {
+        return Some(content);
+    }
+    blockstore_server
+        .request_download(hash, address)
+        .await
+        .ok()?;
+    blockstore.read_all_to_vec(&hash).await
+}
+
 pub fn am_i_on_the_committee(genesis: &Genesis, signer_config: &SignerConfig) -> bool {
     let (node_secret_key, _) = load_keys(signer_config);
     let node_pub_key = node_secret_key.to_pk();
@@ -236,7 +291,7 @@ pub async fn am_i_whitelisted(genesis: &Genesis, signer_config: &SignerConfig) -
     false
 }
 
-async fn rpc_request<T: DeserializeOwned>(
+pub(crate) async fn rpc_request<T: DeserializeOwned>(
     client: &reqwest::Client,
     ip: IpAddr,
     port: u16,
@@ -279,6 +334,15 @@ fn rpc_last_epoch_hash() -> serde_json::Value {
     })
 }
 
+fn rpc_last_epoch_delta_hashes() -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_last_epoch_delta_hashes",
+        "params":[],
+        "id":1,
+    })
+}
+
 fn rpc_node_info(node_public_key: &NodePublicKey) -> serde_json::Value {
     serde_json::json!({
         "jsonrpc": "2.0",
@@ -289,8 +353,8 @@ fn rpc_node_info(node_public_key: &NodePublicKey) -> serde_json::Value {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
-struct RpcResponse<T> {
+pub(crate) struct RpcResponse<T> {
     jsonrpc: String,
     id: usize,
-    result: T,
+    pub(crate) result: T,
 }
@@ -0,0 +1,115 @@
+//! Sending transactions signed by an account owner to a node through its RPC server.
+
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use fleek_crypto::{AccountOwnerSecretKey, EthAddress, NodePublicKey, SecretKey};
+use lightning_interfaces::ToDigest;
+use lightning_types::{
+    AccountInfo,
+    ChainId,
+    NodeInfo,
+    UpdateMethod,
+    UpdatePayload,
+    UpdateRequest,
+    UPDATE_PAYLOAD_VERSION,
+};
+
+use crate::testnet_sync::rpc_request;
+
+/// Signs the update method with the given account owner key, using the next nonce of the
+/// account, and submits it to the node at the given RPC address.
+pub async fn send_account_txn(
+    rpc: SocketAddr,
+    secret_key: &AccountOwnerSecretKey,
+    method: UpdateMethod,
+) -> Result<()> {
+    let client = reqwest::Client::new();
+    let account: EthAddress = secret_key.to_pk().into();
+
+    let account_info = rpc_request::<Option<AccountInfo>>(
+        &client,
+        rpc.ip(),
+        rpc.port(),
+        rpc_account_info(&account).to_string(),
+    )
+    .await?
+    .result;
+    let nonce = account_info.map_or(0, |info| info.nonce) + 1;
+    let chain_id =
+        rpc_request::<ChainId>(&client, rpc.ip(), rpc.port(), rpc_chain_id().to_string())
+            .await?
+            .result;
+
+    let payload = UpdatePayload {
+        version: UPDATE_PAYLOAD_VERSION,
+        chain_id,
+        nonce,
+        method,
+    };
+    let signature = secret_key.sign(&payload.to_digest());
+    let request = UpdateRequest {
+        sender: secret_key.to_pk().into(),
+        signature: signature.into(),
+        payload,
+    };
+
+    rpc_request::<()>(
+        &client,
+        rpc.ip(),
+        rpc.port(),
+        rpc_send_txn(&request).to_string(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Fetches the information of the node with the given public key from the node at the given RPC
+/// address.
+pub async fn get_node_info(rpc: SocketAddr, node: &NodePublicKey) -> Result<Option<NodeInfo>> {
+    let client = reqwest::Client::new();
+    Ok(rpc_request::<Option<NodeInfo>>(
+        &client,
+        rpc.ip(),
+        rpc.port(),
+        rpc_node_info(node).to_string(),
+    )
+    .await?
+    .result)
+}
+
+fn rpc_account_info(account: &EthAddress) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_account_info",
+        "params":{"public_key": account},
+        "id":1,
+    })
+}
+
+fn rpc_chain_id() -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_chain_id",
+        "params":[],
+        "id":1,
+    })
+}
+
+fn rpc_node_info(node: &NodePublicKey) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_node_info",
+        "params":{"public_key": node},
+        "id":1,
+    })
+}
+
+fn rpc_send_txn(request: &UpdateRequest) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method":"flk_send_txn",
+        "params": request,
+        "id":1,
+    })
+}
@@ -120,6 +120,7 @@ fn init_app(epoch_time: u64) -> (ExecutionEngineSocket, Application<TestBinding>
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         };
 
         let app = Application::<TestBinding>::init(config, Default::default(), Default::default())
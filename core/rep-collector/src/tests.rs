@@ -21,7 +21,14 @@
     ReputationReporterInterface,
 };
 use lightning_interfaces::signer::SignerInterface;
-use lightning_interfaces::types::{Block, NodePorts, UpdateMethod, UpdatePayload, UpdateRequest};
+use lightning_interfaces::types::{
+    Block,
+    NodePorts,
+    UpdateMethod,
+    UpdatePayload,
+    UpdateRequest,
+    UPDATE_PAYLOAD_VERSION,
+};
 use lightning_interfaces::{
     partial,
     ReputationQueryInteface,
@@ -136,6 +143,7 @@ async fn test_query() {
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         },
         Default::default(),
         Default::default(),
@@ -291,6 +299,7 @@ async fn test_submit_measurements() {
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         },
         Default::default(),
         Default::default(),
@@ -472,6 +481,7 @@ async fn test_reputation_calculation_and_query() {
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         },
         Default::default(),
         Default::default(),
@@ -598,7 +608,12 @@ async fn test_reputation_calculation_and_query() {
         } else {
             1
         };
-        let payload = UpdatePayload { nonce, method };
+        let payload = UpdatePayload {
+            version: UPDATE_PAYLOAD_VERSION,
+            chain_id: query_runner.get_chain_id(),
+            nonce,
+            method,
+        };
         let digest = payload.to_digest();
         let signature = node.node_secret_key.sign(&digest);
         let req = UpdateRequest {
@@ -72,6 +72,7 @@ async fn test_start_shutdown() {
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         },
         Default::default(),
         Default::default(),
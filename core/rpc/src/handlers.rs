@@ -9,10 +9,12 @@
 use hp_fixed::unsigned::HpUfixed;
 use http::StatusCode;
 use jsonrpc_v2::{Data, Error, MapRouter, Params, RequestObject, ResponseObjects, Server};
-use lightning_interfaces::infu_collection::Collection;
+use lightning_interfaces::infu_collection::{c, Collection};
 use lightning_interfaces::types::{
     AccountInfo,
     Blake3Hash,
+    ChainId,
+    Epoch,
     EpochInfo,
     FetcherRequest,
     FetcherResponse,
@@ -20,9 +22,16 @@
     NodeInfo,
     NodeServed,
     OriginProvider,
+    PendingWithdrawal,
+    Proposal,
+    ProposalId,
     ProtocolParams,
     ReportedReputationMeasurements,
+    RewardsBreakdown,
+    StateProof,
     TotalServed,
+    TransactionReceipt,
+    TransactionSimulation,
     UpdateRequest,
 };
 #[cfg(feature = "e2e-test")]
@@ -30,9 +39,17 @@
 use lightning_interfaces::SyncQueryRunnerInterface;
 
 use crate::server::RpcData;
+use crate::types::{
+    AccountProofParam,
+    HashParam,
+    NodeKeyParam,
+    NodeProofParam,
+    PublicKeyParam,
+    RewardsParam,
+    StateParam,
+};
 #[cfg(feature = "e2e-test")]
 use crate::types::{DhtGetParam, DhtPutParam};
-use crate::types::{NodeKeyParam, PublicKeyParam};
 static OPEN_RPC_DOCS: &str = "../../docs/rpc/openrpc.json";
 
 pub type Result<T> = anyhow::Result<T, Error>;
@@ -81,12 +98,22 @@ pub fn new<C>(interface: Arc<RpcData<C>>) -> Self
             .with_method("flk_get_locked_time", get_locked_time_handler::<C>)
             .with_method("flk_get_node_info", get_node_info_handler::<C>)
             .with_method("flk_get_account_info", get_account_info_handler::<C>)
+            .with_method("flk_get_state_root", get_state_root_handler::<C>)
+            .with_method(
+                "flk_get_account_info_with_proof",
+                get_account_info_with_proof_handler::<C>,
+            )
+            .with_method(
+                "flk_get_node_info_with_proof",
+                get_node_info_with_proof_handler::<C>,
+            )
             .with_method("flk_get_staking_amount", get_staking_amount_handler::<C>)
             .with_method(
                 "flk_get_committee_members",
                 get_committee_members_handler::<C>,
             )
             .with_method("flk_get_epoch", get_epoch_handler::<C>)
+            .with_method("flk_get_chain_id", get_chain_id_handler::<C>)
             .with_method("flk_get_epoch_info", get_epoch_info_handler::<C>)
             .with_method("flk_get_total_supply", get_total_supply_handler::<C>)
             .with_method(
@@ -100,6 +127,7 @@ pub fn new<C>(interface: Arc<RpcData<C>>) -> Self
             .with_method("flk_get_protocol_params", get_protocol_params_handler::<C>)
             .with_method("flk_get_total_served", get_total_served_handler::<C>)
             .with_method("flk_get_node_served", get_node_served_handler::<C>)
+            .with_method("flk_get_rewards", get_rewards_handler::<C>)
             .with_method("flk_is_valid_node", is_valid_node_handler::<C>)
             .with_method("flk_get_node_registry", get_node_registry_handler::<C>)
             .with_method("flk_get_reputation", get_reputation_handler::<C>)
@@ -109,6 +137,24 @@ pub fn new<C>(interface: Arc<RpcData<C>>) -> Self
             )
             .with_method("flk_get_latencies", get_latencies_handler::<C>)
             .with_method("flk_get_last_epoch_hash", get_last_epoch_hash_handler::<C>)
+            .with_method(
+                "flk_get_last_epoch_delta_hashes",
+                get_last_epoch_delta_hashes_handler::<C>,
+            )
+            .with_method(
+                "flk_get_pending_withdrawals",
+                get_pending_withdrawals_handler::<C>,
+            )
+            .with_method("flk_get_proposal", get_proposal_handler::<C>)
+            .with_method(
+                "flk_get_transaction_receipt",
+                get_transaction_receipt_handler::<C>,
+            )
+            .with_method(
+                "flk_get_block_transactions",
+                get_block_transactions_handler::<C>,
+            )
+            .with_method("flk_simulate_txn", simulate_txn::<C>)
             .with_method("flk_send_txn", send_txn::<C>)
             .with_method("flk_put", put::<C>);
 
@@ -151,6 +197,30 @@ pub async fn put<C: Collection>(
     }
 }
 
+/// Returns the query runner for the state after the given block or at the end of the given epoch,
+/// or for the latest state if neither is given. The past states are only kept in memory, so the
+/// ones from before the node started are not available.
+fn query_runner_at<C: Collection>(
+    data: &RpcData<C>,
+    block: Option<u64>,
+    epoch: Option<Epoch>,
+) -> Result<c!(C::ApplicationInterface::SyncExecutor)> {
+    let query_runner = match (block, epoch) {
+        (None, None) => Some(data.query_runner.clone()),
+        (Some(block), None) => data.query_runner.at_block(block),
+        (None, Some(epoch)) => data.query_runner.at_epoch(epoch),
+        (Some(_), Some(_)) => {
+            return Err(Error::internal("Only one of block and epoch can be given"));
+        },
+    };
+    query_runner.ok_or_else(|| {
+        Error::internal(
+            "The requested state is not available, only the recent blocks executed since the node \
+             started are retained",
+        )
+    })
+}
+
 #[autometrics]
 pub async fn ping_handler<C: Collection>() -> Result<String> {
     Ok("pong".to_string())
@@ -160,44 +230,44 @@ pub async fn get_flk_balance_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<PublicKeyParam>,
 ) -> Result<HpUfixed<18>> {
-    Ok(data.0.query_runner.get_flk_balance(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.get_flk_balance(&params.public_key))
 }
 
 pub async fn get_stables_balance_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<PublicKeyParam>,
 ) -> Result<HpUfixed<6>> {
-    Ok(data.0.query_runner.get_stables_balance(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?
+        .get_stables_balance(&params.public_key))
 }
 
 pub async fn get_bandwidth_balance_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<PublicKeyParam>,
 ) -> Result<u128> {
-    Ok(data.0.query_runner.get_account_balance(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?
+        .get_account_balance(&params.public_key))
 }
 
 pub async fn get_staked_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<NodeKeyParam>,
 ) -> Result<HpUfixed<18>> {
-    Ok(data.0.query_runner.get_staked(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.get_staked(&params.public_key))
 }
 
 pub async fn get_locked_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<NodeKeyParam>,
 ) -> Result<HpUfixed<18>> {
-    Ok(data.0.query_runner.get_locked(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.get_locked(&params.public_key))
 }
 
 pub async fn get_stake_locked_until_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<NodeKeyParam>,
 ) -> Result<u64> {
-    Ok(data
-        .0
-        .query_runner
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?
         .get_stake_locked_until(&params.public_key))
 }
 
@@ -205,28 +275,51 @@ pub async fn get_locked_time_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<NodeKeyParam>,
 ) -> Result<u64> {
-    Ok(data.0.query_runner.get_locked_time(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.get_locked_time(&params.public_key))
 }
 
 pub async fn get_node_info_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<NodeKeyParam>,
 ) -> Result<Option<NodeInfo>> {
-    Ok(data.0.query_runner.get_node_info(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.get_node_info(&params.public_key))
 }
 
 pub async fn get_account_info_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<PublicKeyParam>,
 ) -> Result<Option<AccountInfo>> {
-    Ok(data.0.query_runner.get_account_info(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.get_account_info(&params.public_key))
+}
+
+pub async fn get_state_root_handler<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+) -> Result<[u8; 32]> {
+    Ok(data.0.query_runner.get_state_root())
+}
+
+pub async fn get_account_info_with_proof_handler<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+    Params(params): Params<AccountProofParam>,
+) -> Result<(Option<AccountInfo>, StateProof)> {
+    Ok(data
+        .0
+        .query_runner
+        .get_account_info_with_proof(&params.public_key))
+}
+
+pub async fn get_node_info_with_proof_handler<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+    Params(params): Params<NodeProofParam>,
+) -> Result<(Option<NodeInfo>, StateProof)> {
+    Ok(data.0.query_runner.get_node_info_with_proof(&params.index))
 }
 
 pub async fn get_reputation_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<NodeKeyParam>,
 ) -> Result<Option<u8>> {
-    Ok(data.0.query_runner.get_reputation(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.get_reputation(&params.public_key))
 }
 
 pub async fn get_latencies_handler<C: Collection>(
@@ -239,7 +332,8 @@ pub async fn get_reputation_measurements_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<NodeKeyParam>,
 ) -> Result<Vec<ReportedReputationMeasurements>> {
-    Ok(data.0.query_runner.get_rep_measurements(params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?
+        .get_rep_measurements(params.public_key))
 }
 
 pub async fn get_staking_amount_handler<C: Collection>(
@@ -258,6 +352,10 @@ pub async fn get_epoch_handler<C: Collection>(data: Data<Arc<RpcData<C>>>) -> Re
     Ok(data.0.query_runner.get_epoch())
 }
 
+pub async fn get_chain_id_handler<C: Collection>(data: Data<Arc<RpcData<C>>>) -> Result<ChainId> {
+    Ok(data.0.query_runner.get_chain_id())
+}
+
 pub async fn get_epoch_info_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
 ) -> Result<EpochInfo> {
@@ -300,20 +398,31 @@ pub async fn get_node_served_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<NodeKeyParam>,
 ) -> Result<NodeServed> {
-    Ok(data.0.query_runner.get_node_served(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.get_node_served(&params.public_key))
+}
+
+pub async fn get_rewards_handler<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+    Params(params): Params<RewardsParam>,
+) -> Result<Option<RewardsBreakdown>> {
+    Ok(data
+        .0
+        .query_runner
+        .get_rewards(params.epoch, &params.public_key))
 }
 
 pub async fn is_valid_node_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(params): Params<NodeKeyParam>,
 ) -> Result<bool> {
-    Ok(data.0.query_runner.is_valid_node(&params.public_key))
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.is_valid_node(&params.public_key))
 }
 
 pub async fn get_node_registry_handler<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
+    Params(params): Params<StateParam>,
 ) -> Result<Vec<NodeInfo>> {
-    Ok(data.0.query_runner.get_node_registry())
+    Ok(query_runner_at(&data.0, params.block, params.epoch)?.get_node_registry())
 }
 
 pub async fn get_last_epoch_hash_handler<C: Collection>(
@@ -322,6 +431,46 @@ pub async fn get_last_epoch_hash_handler<C: Collection>(
     Ok(data.0.query_runner.get_last_epoch_hash())
 }
 
+pub async fn get_last_epoch_delta_hashes_handler<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+) -> Result<Vec<[u8; 32]>> {
+    Ok(data.0.query_runner.get_last_epoch_delta_hashes())
+}
+
+pub async fn get_pending_withdrawals_handler<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+) -> Result<Vec<(u64, PendingWithdrawal)>> {
+    Ok(data.0.query_runner.get_pending_withdrawals())
+}
+
+pub async fn get_proposal_handler<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+    Params(params): Params<ProposalId>,
+) -> Result<Option<Proposal>> {
+    Ok(data.0.query_runner.get_proposal(params))
+}
+
+pub async fn get_transaction_receipt_handler<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+    Params(params): Params<HashParam>,
+) -> Result<Option<TransactionReceipt>> {
+    Ok(data.0.query_runner.get_transaction_receipt(&params.hash))
+}
+
+pub async fn get_block_transactions_handler<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+    Params(params): Params<HashParam>,
+) -> Result<Vec<TransactionReceipt>> {
+    Ok(data.0.query_runner.get_block_transactions(&params.hash))
+}
+
+pub async fn simulate_txn<C: Collection>(
+    data: Data<Arc<RpcData<C>>>,
+    Params(param): Params<UpdateRequest>,
+) -> Result<TransactionSimulation> {
+    Ok(data.0.query_runner.simulate_txn(param))
+}
+
 pub async fn send_txn<C: Collection>(
     data: Data<Arc<RpcData<C>>>,
     Params(param): Params<UpdateRequest>,
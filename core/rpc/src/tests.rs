@@ -22,12 +22,14 @@
 use lightning_fetcher::fetcher::Fetcher;
 use lightning_interfaces::infu_collection::Collection;
 use lightning_interfaces::types::{
+    AccountInfo,
     EpochInfo,
     NodeInfo,
     NodePorts,
     NodeServed,
     ProtocolParams,
     Staking,
+    StateProof,
     TotalServed,
     UpdateRequest,
 };
@@ -117,6 +119,7 @@ async fn init_rpc_without_consensus(
                 storage: StorageConfig::InMemory,
                 db_path: None,
                 db_options: None,
+                history_retention: 0,
             },
             blockstore,
             Default::default(),
@@ -229,6 +232,7 @@ async fn test_rpc_get_flk_balance() -> Result<()> {
         flk_balance: 1000,
         stables_balance: 0,
         bandwidth_balance: 0,
+        vesting: None,
     });
 
     let (mut rpc, _) = init_rpc_without_consensus(Some(genesis)).await.unwrap();
@@ -350,6 +354,7 @@ async fn test_rpc_get_staked() -> Result<()> {
         stake_locked_until: 365,
         locked: 0_u32.into(),
         locked_until: 0,
+        delegated: 0_u32.into(),
     };
 
     let node_info = GenesisNode::new(
@@ -421,6 +426,7 @@ async fn test_rpc_get_stables_balance() -> Result<()> {
         flk_balance: 0,
         stables_balance: 200,
         bandwidth_balance: 0,
+        vesting: None,
     });
 
     let (mut rpc, _) = init_rpc_without_consensus(Some(genesis)).await.unwrap();
@@ -474,6 +480,7 @@ async fn test_rpc_get_stake_locked_until() -> Result<()> {
         stake_locked_until: 365,
         locked: 0_u32.into(),
         locked_until: 0,
+        delegated: 0_u32.into(),
     };
     let node_info = GenesisNode::new(
         eth_address,
@@ -549,6 +556,7 @@ async fn test_rpc_get_locked_time() -> Result<()> {
         stake_locked_until: 365,
         locked: 0_u32.into(),
         locked_until: 2,
+        delegated: 0_u32.into(),
     };
     let node_info = GenesisNode::new(
         eth_address,
@@ -624,6 +632,7 @@ async fn test_rpc_get_locked() -> Result<()> {
         stake_locked_until: 365,
         locked: 500_u32.into(),
         locked_until: 2,
+        delegated: 0_u32.into(),
     };
     let node_info = GenesisNode::new(
         eth_address,
@@ -695,6 +704,7 @@ async fn test_rpc_get_bandwidth_balance() -> Result<()> {
         flk_balance: 0,
         stables_balance: 0,
         bandwidth_balance: 10_000,
+        vesting: None,
     });
 
     let (mut rpc, _) = init_rpc_without_consensus(Some(genesis)).await.unwrap();
@@ -748,6 +758,7 @@ async fn test_rpc_get_node_info() -> Result<()> {
         stake_locked_until: 365,
         locked: 500_u32.into(),
         locked_until: 2,
+        delegated: 0_u32.into(),
     };
     let node_info = GenesisNode::new(
         eth_address,
@@ -806,6 +817,127 @@ async fn test_rpc_get_node_info() -> Result<()> {
     Ok(())
 }
 
+#[test]
+async fn test_rpc_get_state_root() -> Result<()> {
+    let port = 30023;
+    let (mut rpc, query_runner) = init_rpc_app_test().await.unwrap();
+    rpc.config.port = port;
+
+    task::spawn(async move {
+        rpc.start().await;
+    });
+    wait_for_server_start(port).await?;
+
+    let req = json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_state_root",
+        "params":[],
+        "id":1,
+    });
+
+    let response = make_request(port, req.to_string()).await?;
+
+    if response.status().is_success() {
+        let value: Value = response.json().await?;
+        if value.get("result").is_some() {
+            // Parse the response as a successful response
+            let success_response: RpcSuccessResponse<[u8; 32]> = serde_json::from_value(value)?;
+            assert_eq!(query_runner.get_state_root(), success_response.result);
+        } else {
+            panic!("Rpc Error: {value}")
+        }
+    } else {
+        panic!("Request failed with status: {}", response.status());
+    }
+
+    Ok(())
+}
+
+#[test]
+async fn test_rpc_get_account_info_with_proof() -> Result<()> {
+    let port = 30024;
+    let (mut rpc, query_runner) = init_rpc_app_test().await.unwrap();
+    rpc.config.port = port;
+
+    task::spawn(async move {
+        rpc.start().await;
+    });
+    wait_for_server_start(port).await?;
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner_public_key = owner_secret_key.to_pk();
+    let owner_eth_address: EthAddress = owner_public_key.into();
+
+    let req = json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_account_info_with_proof",
+        "params": {"public_key": owner_eth_address},
+        "id":1,
+    });
+
+    let response = make_request(port, req.to_string()).await?;
+
+    if response.status().is_success() {
+        let value: Value = response.json().await?;
+        if value.get("result").is_some() {
+            // Parse the response as a successful response
+            let success_response: RpcSuccessResponse<(Option<AccountInfo>, StateProof)> =
+                serde_json::from_value(value)?;
+            let (account, proof) = success_response.result;
+            assert_eq!(account, None);
+            // The accounts are proven under the name of their table.
+            let key = bincode::serialize(&owner_eth_address)?;
+            assert!(proof.verify(&query_runner.get_state_root(), "account", &key, None));
+        } else {
+            panic!("Rpc Error: {value}")
+        }
+    } else {
+        panic!("Request failed with status: {}", response.status());
+    }
+
+    Ok(())
+}
+
+#[test]
+async fn test_rpc_get_node_info_with_proof() -> Result<()> {
+    let port = 30025;
+    let (mut rpc, query_runner) = init_rpc_app_test().await.unwrap();
+    rpc.config.port = port;
+
+    task::spawn(async move {
+        rpc.start().await;
+    });
+    wait_for_server_start(port).await?;
+
+    let req = json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_node_info_with_proof",
+        "params": {"index": 0},
+        "id":1,
+    });
+
+    let response = make_request(port, req.to_string()).await?;
+
+    if response.status().is_success() {
+        let value: Value = response.json().await?;
+        if value.get("result").is_some() {
+            // Parse the response as a successful response
+            let success_response: RpcSuccessResponse<(Option<NodeInfo>, StateProof)> =
+                serde_json::from_value(value)?;
+            assert_eq!(
+                query_runner.get_node_info_with_proof(&0),
+                success_response.result
+            );
+        } else {
+            panic!("Rpc Error: {value}")
+        }
+    } else {
+        panic!("Request failed with status: {}", response.status());
+    }
+
+    Ok(())
+}
+
 #[test]
 async fn test_rpc_get_staking_amount() -> Result<()> {
     let port = 30010;
@@ -918,6 +1050,42 @@ async fn test_rpc_get_epoch() -> Result<()> {
     Ok(())
 }
 
+#[test]
+async fn test_rpc_get_chain_id() -> Result<()> {
+    let port = 30022;
+    let (mut rpc, query_runner) = init_rpc_app_test().await.unwrap();
+    rpc.config.port = port;
+
+    task::spawn(async move {
+        rpc.start().await;
+    });
+    wait_for_server_start(port).await?;
+
+    let req = json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_chain_id",
+        "params":[],
+        "id":1,
+    });
+
+    let response = make_request(port, req.to_string()).await?;
+
+    if response.status().is_success() {
+        let value: Value = response.json().await?;
+        if value.get("result").is_some() {
+            // Parse the response as a successful response
+            let success_response: RpcSuccessResponse<u32> = serde_json::from_value(value)?;
+            assert_eq!(query_runner.get_chain_id(), success_response.result);
+        } else {
+            panic!("Rpc Error: {value}")
+        }
+    } else {
+        panic!("Request failed with status: {}", response.status());
+    }
+
+    Ok(())
+}
+
 #[test]
 async fn test_rpc_get_epoch_info() -> Result<()> {
     let port = 30013;
@@ -1242,6 +1410,7 @@ async fn test_rpc_is_valid_node() -> Result<()> {
         stake_locked_until: 0,
         locked: 0_u32.into(),
         locked_until: 0,
+        delegated: 0_u32.into(),
     };
     let node_info = GenesisNode::new(
         eth_address,
@@ -1317,6 +1486,7 @@ async fn test_rpc_get_node_registry() -> Result<()> {
         stake_locked_until: 0,
         locked: 0_u32.into(),
         locked_until: 0,
+        delegated: 0_u32.into(),
     };
     let node_info = GenesisNode::new(
         eth_address,
@@ -1,14 +1,55 @@
 use fleek_crypto::{ClientPublicKey, EthAddress, NodePublicKey};
+use lightning_interfaces::types::{Epoch, NodeIndex};
 use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct PublicKeyParam {
     pub public_key: EthAddress,
+    /// Query the state after this block was executed instead of the latest state. Only the
+    /// blocks retained in memory since the node started can be queried.
+    #[serde(default)]
+    pub block: Option<u64>,
+    /// Query the state at the end of this epoch instead of the latest state.
+    #[serde(default)]
+    pub epoch: Option<Epoch>,
 }
 
 #[derive(Deserialize)]
 pub struct NodeKeyParam {
     pub public_key: NodePublicKey,
+    /// Query the state after this block was executed instead of the latest state. Only the
+    /// blocks retained in memory since the node started can be queried.
+    #[serde(default)]
+    pub block: Option<u64>,
+    /// Query the state at the end of this epoch instead of the latest state.
+    #[serde(default)]
+    pub epoch: Option<Epoch>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct StateParam {
+    /// Query the state after this block was executed instead of the latest state. Only the
+    /// blocks retained in memory since the node started can be queried.
+    pub block: Option<u64>,
+    /// Query the state at the end of this epoch instead of the latest state.
+    pub epoch: Option<Epoch>,
+}
+
+#[derive(Deserialize)]
+pub struct AccountProofParam {
+    pub public_key: EthAddress,
+}
+
+#[derive(Deserialize)]
+pub struct NodeProofParam {
+    pub index: NodeIndex,
+}
+
+#[derive(Deserialize)]
+pub struct RewardsParam {
+    pub epoch: Epoch,
+    pub public_key: NodePublicKey,
 }
 
 #[derive(Deserialize)]
@@ -16,6 +57,11 @@ pub struct ClientKeyParam {
     pub public_key: ClientPublicKey,
 }
 
+#[derive(Deserialize)]
+pub struct HashParam {
+    pub hash: [u8; 32],
+}
+
 #[derive(Deserialize)]
 pub struct DhtPutParam {
     pub key: Vec<u8>,
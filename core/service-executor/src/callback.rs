@@ -1,23 +1,26 @@
-use fn_sdk::internal::{IpcRequest, OnEventResponseArgs};
-use lightning_interfaces::ConnectionWork;
+use fn_sdk::internal::{IpcRequest, OnEventResponseArgs, Response};
+use lightning_interfaces::{ConnectionWork, SyncQueryRunnerInterface};
 
 use crate::deque::CommandSender;
 
 /// Create a callback for us to pass to the setup function of the service.
-pub fn make_callback(
+pub fn make_callback<Q: SyncQueryRunnerInterface>(
     scheduler: CommandSender,
+    query_runner: Q,
     cb: fn(OnEventResponseArgs),
 ) -> Box<dyn Fn(IpcRequest) + Send + Sync> {
     Box::new(move |request: IpcRequest| {
         let scheduler = scheduler.clone();
-        tokio::spawn(handle_request(cb, scheduler, request));
+        let query_runner = query_runner.clone();
+        tokio::spawn(handle_request(cb, scheduler, query_runner, request));
     })
 }
 
 #[inline(always)]
-async fn handle_request(
-    _cb: fn(OnEventResponseArgs),
+async fn handle_request<Q: SyncQueryRunnerInterface>(
+    cb: fn(OnEventResponseArgs),
     scheduler: CommandSender,
+    query_runner: Q,
     IpcRequest {
         request_ctx,
         request,
@@ -40,7 +43,15 @@ async fn handle_request(
                 .await;
             assert!(request_ctx.is_none());
         },
-        fn_sdk::internal::Request::QueryClientBalance { pk: _ } => todo!(),
+        fn_sdk::internal::Request::QueryClientBalance { pk } => {
+            // The escrow is reported in the smallest unit of stables (6 decimals).
+            let balance = query_runner.get_client_balance(&pk);
+            let balance = u128::try_from(balance.get_value().clone()).unwrap_or(u128::MAX);
+            cb(OnEventResponseArgs {
+                request_ctx: request_ctx.expect("Query requests should have a context"),
+                response: Response::QueryClientBalance { balance },
+            });
+        },
         fn_sdk::internal::Request::FetchBlake3 { hash: _ } => todo!(),
         _ => todo!(),
     };
@@ -3,9 +3,10 @@
 
 use async_trait::async_trait;
 use fxhash::FxHashSet;
-use lightning_interfaces::infu_collection::Collection;
+use lightning_interfaces::infu_collection::{c, Collection};
 use lightning_interfaces::types::ServiceId;
 use lightning_interfaces::{
+    ApplicationInterface,
     BlockStoreInterface,
     ConfigConsumer,
     ExecutorProviderInterface,
@@ -30,6 +31,7 @@ pub struct ServiceExecutor<C: Collection> {
     sender: CommandSender,
     stealer: CommandStealer,
     blockstore: ResolvedPathBuf,
+    query_runner: c!(C::ApplicationInterface::SyncExecutor),
     p: PhantomData<C>,
 }
 
@@ -55,7 +57,11 @@ pub struct Provider {
 impl<C: Collection> ServiceExecutorInterface<C> for ServiceExecutor<C> {
     type Provider = Provider;
 
-    fn init(config: Self::Config, blockstore: &C::BlockStoreInterface) -> anyhow::Result<Self> {
+    fn init(
+        config: Self::Config,
+        blockstore: &C::BlockStoreInterface,
+        query_runner: c!(C::ApplicationInterface::SyncExecutor),
+    ) -> anyhow::Result<Self> {
         let (sender, stealer) = crate::deque::chan();
         Ok(ServiceExecutor {
             config,
@@ -64,6 +70,7 @@ fn init(config: Self::Config, blockstore: &C::BlockStoreInterface) -> anyhow::Re
             sender,
             stealer,
             blockstore: blockstore.get_root_dir().try_into()?,
+            query_runner,
             p: PhantomData,
         })
     }
@@ -86,7 +93,11 @@ async fn start(&self) {
         self.is_running.store(true, Ordering::Relaxed);
 
         if !self.config.services.is_empty() {
-            let request_sender = make_callback(self.sender.clone(), fn_sdk::api::on_event_response);
+            let request_sender = make_callback(
+                self.sender.clone(),
+                self.query_runner.clone(),
+                fn_sdk::api::on_event_response,
+            );
 
             fn_sdk::api::setup(fn_sdk::internal::OnStartArgs {
                 request_sender,
@@ -31,6 +31,7 @@
     UpdateMethod,
     UpdatePayload,
     UpdateRequest,
+    UPDATE_PAYLOAD_VERSION,
 };
 use lightning_interfaces::{ApplicationInterface, MempoolSocket, SyncQueryRunnerInterface};
 use tokio::sync::{mpsc, Notify};
@@ -267,6 +268,7 @@ async fn handle<Q: SyncQueryRunnerInterface>(
             } else {
                 0
             };
+        let chain_id = query_runner.get_chain_id();
         let mut base_nonce = application_nonce;
         let mut next_nonce = application_nonce + 1;
 
@@ -277,7 +279,12 @@ async fn handle<Q: SyncQueryRunnerInterface>(
                     let task = task.expect("Failed to receive UpdateMethod.");
                     let update_method = task.request.clone();
                     task.respond(next_nonce);
-                    let update_payload = UpdatePayload { method: update_method, nonce: next_nonce };
+                    let update_payload = UpdatePayload {
+                        version: UPDATE_PAYLOAD_VERSION,
+                        chain_id,
+                        nonce: next_nonce,
+                        method: update_method,
+                    };
                     let digest = update_payload.to_digest();
                     let signature = self.node_secret_key.sign(&digest);
                     let update_request = UpdateRequest {
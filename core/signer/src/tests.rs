@@ -71,6 +71,7 @@ async fn test_send_two_txs_in_a_row() {
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         },
         Default::default(),
         Default::default(),
@@ -164,6 +165,7 @@ async fn test_retry_send() {
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         },
         Default::default(),
         Default::default(),
@@ -158,6 +158,7 @@ async fn test_build_latency_matrix() {
             storage: StorageConfig::InMemory,
             db_path: None,
             db_options: None,
+            history_retention: 0,
         },
         Default::default(),
         Default::default(),
@@ -4,7 +4,7 @@
 use hp_fixed::unsigned::HpUfixed;
 use serde::{Deserialize, Serialize};
 
-use super::TransactionResponse;
+use super::{Epoch, TransactionResponse};
 
 /// The response generated from executing an entire batch of transactions (aka a block).
 #[derive(Debug, PartialEq, PartialOrd, Hash, Eq)]
@@ -18,6 +18,8 @@ pub struct BlockExecutionResponse {
     pub node_registry_delta: Vec<(NodePublicKey, NodeRegistryChange)>,
     /// Receipts of all executed transactions
     pub txn_receipts: Vec<TransactionResponse>,
+    /// The root of the state tree after executing the block
+    pub state_root: [u8; 32],
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Hash, Eq)]
@@ -35,7 +37,41 @@ pub struct AccountInfo {
     pub stables_balance: HpUfixed<6>,
     /// The accounts stables/bandwidth balance
     pub bandwidth_balance: u128,
+    /// The FLK of the vesting schedules of the account that has been released so far
+    pub vested_flk: HpUfixed<18>,
+    /// The part of the FLK balance that has not vested yet. It can be staked but not transferred
+    /// or withdrawn.
+    pub unvested_flk: HpUfixed<18>,
     /// The nonce of the account. Added to each transaction before signed to prevent replays and
     /// enforce ordering
     pub nonce: u64,
 }
+
+/// A schedule that releases the FLK of an account over time. Nothing is released before the
+/// cliff, after it the amount vests linearly until the end of the schedule. A schedule whose
+/// cliff is equal to its duration releases everything at once.
+#[derive(Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct VestingSchedule {
+    /// The total amount of FLK that vests over the schedule
+    pub amount: HpUfixed<18>,
+    /// The epoch the schedule starts in
+    pub start: Epoch,
+    /// The number of epochs after the start before anything vests
+    pub cliff: Epoch,
+    /// The number of epochs after the start until everything has vested
+    pub duration: Epoch,
+}
+
+impl VestingSchedule {
+    /// Returns the amount of FLK that has vested by the given epoch.
+    pub fn vested_at(&self, epoch: Epoch) -> HpUfixed<18> {
+        let elapsed = epoch.saturating_sub(self.start);
+        if elapsed < self.cliff {
+            HpUfixed::zero()
+        } else if elapsed >= self.duration {
+            self.amount.clone()
+        } else {
+            &self.amount * &HpUfixed::from(elapsed) / HpUfixed::from(self.duration)
+        }
+    }
+}
@@ -1,8 +1,59 @@
 //! Types that are and will be used for the bridge functionality.
 
+use fleek_crypto::{AccountOwnerSignature, EthAddress};
+use hp_fixed::unsigned::HpUfixed;
+use ink_quill::{ToDigest, TranscriptBuilder};
 use serde::{Deserialize, Serialize};
 
-/// Placeholder
-/// This is the proof used to operate our PoC bridges
+use crate::transaction::HpUfixedWrapper;
+use crate::Tokens;
+
+const FN_BRIDGE_DEPOSIT_DOMAIN: &str = "fleek_network_bridge_deposit";
+
+/// The unique identifier of a deposit on the L2, which is the hash of the L2 transaction
+/// together with the index of the deposit log inside that transaction.
+pub type DepositId = ([u8; 32], u64);
+
+/// The deposit event emitted by the bridge contract on the L2.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub struct DepositEvent {
+    /// The hash of the L2 transaction that emitted the event.
+    pub tx_hash: [u8; 32],
+    /// The index of the event log in the L2 transaction.
+    pub log_index: u64,
+    /// The account on the network that receives the deposited tokens.
+    pub recipient: EthAddress,
+    /// Which token was bridged.
+    pub token: Tokens,
+    /// Amount bridged.
+    pub amount: HpUfixed<18>,
+}
+
+impl DepositEvent {
+    /// Returns the id of this deposit.
+    pub fn id(&self) -> DepositId {
+        (self.tx_hash, self.log_index)
+    }
+}
+
+impl ToDigest for DepositEvent {
+    fn transcript(&self) -> TranscriptBuilder {
+        TranscriptBuilder::empty(FN_BRIDGE_DEPOSIT_DOMAIN)
+            .with("tx_hash", &self.tx_hash)
+            .with("log_index", &self.log_index)
+            .with("recipient", &self.recipient.0)
+            .with("token", &self.token)
+            .with("amount", &HpUfixedWrapper(self.amount.clone()))
+    }
+}
+
+/// This is the proof used to operate our PoC bridges. It contains the deposit event observed
+/// on the L2 along with the signatures of the bridge validators over the digest of the event.
 #[derive(Clone, Debug, Serialize, Deserialize, Hash)]
-pub struct ProofOfConsensus {}
+pub struct ProofOfConsensus {
+    /// The deposit event on the L2.
+    pub event: DepositEvent,
+    /// The signatures of the bridge validators over the event, along with the address of
+    /// the validator that produced each signature.
+    pub signatures: Vec<(EthAddress, AccountOwnerSignature)>,
+}
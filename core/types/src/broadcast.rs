@@ -1,6 +1,9 @@
 use derive_more::IsVariant;
+use ink_quill::TranscriptBuilder;
 use serde::{Deserialize, Serialize};
 
+const FN_SEQUENCED_BROADCAST_DOMAIN: &str = "FLEEK_SEQUENCED_BROADCAST_DOMAIN";
+
 /// Numerical value for different gossip topics used by Fleek Network.
 // New topics can be added as the system grows.
 #[derive(
@@ -26,3 +29,18 @@ fn to_transcript_builder_input(&self) -> Vec<u8> {
         vec![value]
     }
 }
+
+/// Returns the transcript of a sequenced broadcast message, the digest of this transcript is what
+/// the originator signs to commit to a single payload for each sequence number of a topic. This is
+/// what a node can be held accountable for, unlike the digest of a gossiped message which only
+/// covers the topic and the payload.
+pub fn sequenced_broadcast_transcript(
+    topic: Topic,
+    sequence: u64,
+    payload: &[u8],
+) -> TranscriptBuilder {
+    TranscriptBuilder::empty(FN_SEQUENCED_BROADCAST_DOMAIN)
+        .with("topic", &topic)
+        .with("sequence", &sequence)
+        .with("payload", &payload)
+}
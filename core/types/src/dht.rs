@@ -4,7 +4,7 @@
 
 const FN_DHT_ENTRY_DOMAIN: &str = "FLEEK_NETWORK_DHT_ENTRY";
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
 #[repr(u8)]
 pub enum KeyPrefix {
     /// The content registry keys.
@@ -30,7 +30,7 @@ pub enum DhtResponse {
     Get(Option<TableEntry>),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
 pub struct TableEntry {
     /// The name space of this key-value pair.
     pub prefix: KeyPrefix,
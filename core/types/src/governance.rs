@@ -0,0 +1,87 @@
+//! Types related to the on-chain governance.
+
+use fleek_crypto::EthAddress;
+use hp_fixed::unsigned::HpUfixed;
+use ink_quill::{ToDigest, TranscriptBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::transaction::HpUfixedWrapper;
+use crate::{CommodityTypes, Epoch, ProtocolParams, Service, ServiceId};
+
+const FN_PROPOSAL_ACTION_DOMAIN: &str = "FLEEK_NETWORK_PROPOSAL_ACTION";
+
+pub type ProposalId = u64;
+
+/// The change to the network that is executed once a proposal passed.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub enum ProposalAction {
+    ChangeProtocolParam {
+        param: ProtocolParams,
+        value: u128,
+    },
+    AddService {
+        service: Service,
+        service_id: ServiceId,
+    },
+    RemoveService {
+        service_id: ServiceId,
+    },
+    SetServicePrice {
+        service_id: ServiceId,
+        commodity: CommodityTypes,
+        price: HpUfixed<6>,
+    },
+}
+
+/// A governance proposal that node owners vote on with their stake.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Proposal {
+    /// The account that submitted the proposal.
+    pub proposer: EthAddress,
+    /// The action that is executed once the proposal passed.
+    pub action: ProposalAction,
+    /// The epoch in which the proposal was created, votes count with the stake at its start.
+    pub voting_starts: Epoch,
+    /// The epoch in which the voting ends, votes are only accepted before this epoch and the
+    /// proposal can only be executed starting from it.
+    pub voting_ends: Epoch,
+    /// The total stake that voted in favor of the proposal.
+    pub votes_for: HpUfixed<18>,
+    /// The total stake that voted against the proposal.
+    pub votes_against: HpUfixed<18>,
+    /// Whether the action of the proposal has already been executed.
+    pub executed: bool,
+    /// The total stake at the start of the voting, the quorum is measured against it.
+    pub total_voting_power: HpUfixed<18>,
+}
+
+impl ToDigest for ProposalAction {
+    fn transcript(&self) -> TranscriptBuilder {
+        let transcript_builder = TranscriptBuilder::empty(FN_PROPOSAL_ACTION_DOMAIN);
+        match self {
+            ProposalAction::ChangeProtocolParam { param, value } => transcript_builder
+                .with("action", &"change_protocol_param")
+                .with("param", &(param.clone() as u8))
+                .with("value", value),
+            ProposalAction::AddService {
+                service,
+                service_id,
+            } => transcript_builder
+                .with("action", &"add_service")
+                .with("service_id", service_id)
+                .with("service", service),
+            ProposalAction::RemoveService { service_id } => transcript_builder
+                .with("action", &"remove_service")
+                .with("service_id", service_id),
+            ProposalAction::SetServicePrice {
+                service_id,
+                commodity,
+                price,
+            } => transcript_builder
+                .with("action", &"set_service_price")
+                .with("service_id", service_id)
+                .with("commodity", commodity)
+                .with("price", &HpUfixedWrapper(price.clone())),
+        }
+    }
+}
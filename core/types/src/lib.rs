@@ -9,6 +9,7 @@
 mod content;
 mod dht;
 mod fetcher;
+mod governance;
 mod metrics;
 mod misbehavior;
 mod pod;
@@ -27,6 +28,7 @@
 pub use content::*;
 pub use dht::*;
 pub use fetcher::*;
+pub use governance::*;
 pub use metrics::*;
 pub use misbehavior::*;
 pub use pod::*;
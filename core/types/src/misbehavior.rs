@@ -1,9 +1,88 @@
 //! Types related to the proof of misbehavior.
 
+use fleek_crypto::{NodePublicKey, NodeSignature, PublicKey};
+use ink_quill::{ToDigest, TranscriptBuilder};
 use serde::{Deserialize, Serialize};
 
-/// Placeholder
+use crate::{sequenced_broadcast_transcript, TableEntry, Topic};
+
+const FN_MISBEHAVIOR_DOMAIN: &str = "FLEEK_NETWORK_MISBEHAVIOR";
+
 /// This is the proof presented to the slashing function that proves a node misbehaved and should be
 /// slashed
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash)]
-pub enum ProofOfMisbehavior {}
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub enum ProofOfMisbehavior {
+    /// The node signed two different values for the same key in the DHT.
+    DoubleSignedTableEntry {
+        first: TableEntry,
+        second: TableEntry,
+    },
+    /// The node signed two different payloads for the same sequence number of a topic.
+    EquivocatingBroadcast {
+        first: SignedBroadcastMessage,
+        second: SignedBroadcastMessage,
+    },
+}
+
+/// A broadcast message together with the signature of the node that originated it. Each node
+/// numbers the messages it sends on a topic, so it can only ever sign one payload per sequence
+/// number.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub struct SignedBroadcastMessage {
+    pub topic: Topic,
+    pub sequence: u64,
+    pub payload: Vec<u8>,
+    pub signature: NodeSignature,
+}
+
+impl SignedBroadcastMessage {
+    /// Returns true if the message was signed by the given node.
+    pub fn is_signature_valid(&self, origin: &NodePublicKey) -> bool {
+        let digest =
+            sequenced_broadcast_transcript(self.topic, self.sequence, &self.payload).hash();
+        origin.verify(&self.signature, &digest)
+    }
+}
+
+impl ProofOfMisbehavior {
+    /// Returns true if the proof shows that the given node misbehaved.
+    pub fn verify(&self, offender: &NodePublicKey) -> bool {
+        match self {
+            ProofOfMisbehavior::DoubleSignedTableEntry { first, second } => {
+                first.source == *offender
+                    && second.source == *offender
+                    && first.prefix == second.prefix
+                    && first.key == second.key
+                    && first.value != second.value
+                    && first.is_signature_valid()
+                    && second.is_signature_valid()
+            },
+            ProofOfMisbehavior::EquivocatingBroadcast { first, second } => {
+                first.topic == second.topic
+                    && first.sequence == second.sequence
+                    && first.payload != second.payload
+                    && first.is_signature_valid(offender)
+                    && second.is_signature_valid(offender)
+            },
+        }
+    }
+}
+
+impl ToDigest for ProofOfMisbehavior {
+    /// The digest identifies the offense rather than the evidence, two proofs for the same
+    /// offense have the same digest regardless of which of the conflicting messages they contain
+    /// or in which order. This is used to make sure an offense is only slashed once.
+    fn transcript(&self) -> TranscriptBuilder {
+        let transcript_builder = TranscriptBuilder::empty(FN_MISBEHAVIOR_DOMAIN);
+        match self {
+            ProofOfMisbehavior::DoubleSignedTableEntry { first, .. } => transcript_builder
+                .with("misbehavior", &"double_signed_table_entry")
+                .with("prefix", &(first.prefix as u8))
+                .with("key", &first.key),
+            ProofOfMisbehavior::EquivocatingBroadcast { first, .. } => transcript_builder
+                .with("misbehavior", &"equivocating_broadcast")
+                .with("topic", &first.topic)
+                .with("sequence", &first.sequence),
+        }
+    }
+}
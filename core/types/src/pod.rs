@@ -1,8 +1,36 @@
+use fleek_crypto::{ClientSignature, NodePublicKey};
+use ink_quill::{ToDigest, TranscriptBuilder};
 use serde::{Deserialize, Serialize};
 
+const FN_DELIVERY_ACKNOWLEDGMENT_DOMAIN: &str = "FLEEK_NETWORK_DELIVERY_ACKNOWLEDGMENT";
+
 /// A batch of delivery acknowledgments.
 #[derive(Serialize, Deserialize, Debug, Hash)]
 pub struct DeliveryAcknowledgmentBatch;
 
-#[derive(Serialize, Deserialize, Debug, Hash, Clone, Default)]
-pub struct DeliveryAcknowledgment;
+/// A client's acknowledgment that a node delivered an amount of a commodity to it through a
+/// service. The acknowledgments a client gives to a node carry increasing sequence numbers, so
+/// each of them can only be used once to pay the node from the escrow of the client.
+#[derive(Serialize, Deserialize, Debug, Hash, Clone)]
+pub struct DeliveryAcknowledgment {
+    /// The node that delivered the commodity.
+    pub node: NodePublicKey,
+    /// The service the commodity was delivered through.
+    pub service_id: u32,
+    /// How much of the commodity was delivered.
+    pub commodity: u128,
+    /// The sequence number of this acknowledgment among the ones the client gave to the node.
+    pub sequence: u64,
+    /// The signature of the client over the digest of this acknowledgment.
+    pub signature: ClientSignature,
+}
+
+impl ToDigest for DeliveryAcknowledgment {
+    fn transcript(&self) -> TranscriptBuilder {
+        TranscriptBuilder::empty(FN_DELIVERY_ACKNOWLEDGMENT_DOMAIN)
+            .with("node", &self.node.0)
+            .with("service_id", &self.service_id)
+            .with("commodity", &self.commodity)
+            .with("sequence", &self.sequence)
+    }
+}
@@ -1,6 +1,44 @@
+use std::time::Duration;
+
+use fleek_crypto::{
+    ClientPublicKey,
+    ConsensusPublicKey,
+    ConsensusSignature,
+    EthAddress,
+    NodePublicKey,
+    TransactionSender,
+};
+use hp_fixed::unsigned::HpUfixed;
 use serde::{Deserialize, Serialize};
 
-use super::{Epoch, NodeInfo};
+use super::{
+    AccountInfo,
+    Committee,
+    CommodityTypes,
+    Delegation,
+    DepositEvent,
+    DepositId,
+    Epoch,
+    EpochRewards,
+    Metadata,
+    NodeIndex,
+    NodeInfo,
+    NodeInfoUpdate,
+    NodeRewards,
+    NodeServed,
+    PendingWithdrawal,
+    Proposal,
+    ProposalId,
+    ProtocolParams,
+    ReportedReputationMeasurements,
+    Service,
+    ServiceId,
+    ServiceRevenue,
+    Tokens,
+    TotalServed,
+    Value,
+    VestingSchedule,
+};
 
 /// Info on a Narwhal epoch
 #[derive(Clone, Debug, PartialEq, PartialOrd, Hash, Eq, Serialize, Deserialize)]
@@ -26,9 +64,85 @@ pub enum ExecutionData {
     UInt(u128),
     EpochInfo(EpochInfo),
     EpochChange,
+    /// The results of the steps of a batch transaction, in order.
+    Batch(Vec<ExecutionData>),
+}
+
+/// The record of a transaction that was executed on the network.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionReceipt {
+    /// The hash of the block the transaction was included in.
+    pub block_hash: [u8; 32],
+    /// The position of the transaction in the block.
+    pub transaction_index: u32,
+    /// The hash of the transaction, see `UpdateRequest::hash`.
+    pub transaction_hash: [u8; 32],
+    /// The sender of the transaction.
+    pub from: TransactionSender,
+    /// The result of executing the transaction.
+    pub response: TransactionResponse,
+    /// The events that were emitted while executing the transaction, only successful
+    /// transactions emit events.
+    pub events: Vec<TransactionEvent>,
+}
+
+/// Changes to the state that are emitted by transactions so clients can follow them without
+/// polling the state.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionEvent {
+    Transfer {
+        token: Tokens,
+        from: EthAddress,
+        to: EthAddress,
+        amount: HpUfixed<18>,
+    },
+    Deposit {
+        token: Tokens,
+        to: EthAddress,
+        amount: HpUfixed<18>,
+    },
+    Withdraw {
+        withdraw_id: u64,
+        token: Tokens,
+        from: EthAddress,
+        amount: HpUfixed<18>,
+    },
+    Stake {
+        node: NodePublicKey,
+        account: EthAddress,
+        amount: HpUfixed<18>,
+    },
+    Unstake {
+        node: NodePublicKey,
+        account: EthAddress,
+        amount: HpUfixed<18>,
+    },
+    WithdrawUnstaked {
+        node: NodePublicKey,
+        recipient: EthAddress,
+        amount: HpUfixed<18>,
+    },
+    Slash {
+        node: NodePublicKey,
+        amount: HpUfixed<18>,
+    },
+    /// New tokens were minted as rewards at the end of an epoch.
+    Mint {
+        token: Tokens,
+        to: EthAddress,
+        amount: HpUfixed<18>,
+    },
+    /// Stables were moved from the bandwidth balance of an account into the escrow of a client.
+    FundClient {
+        from: EthAddress,
+        client: ClientPublicKey,
+        amount: u128,
+    },
 }
 
 /// Error type for transaction execution on the application layer
+///
+/// The variant index is part of the stored receipts, so new variants are only ever appended.
 #[derive(Clone, Debug, PartialEq, PartialOrd, Hash, Eq, Serialize, Deserialize)]
 pub enum ExecutionError {
     InsufficientBalance,
@@ -54,4 +168,96 @@ pub enum ExecutionError {
     EpochAlreadyChanged,
     EpochHasNotStarted,
     ConsensusKeyAlreadyIndexed,
+    DepositAlreadyProcessed,
+    ServiceAlreadyExists,
+    ServiceHasPendingRevenue,
+    AlreadySlashed,
+    InvalidCommission,
+    NoVotingPower,
+    ProposalDoesNotExist,
+    VotingEnded,
+    VotingNotEnded,
+    AlreadyVoted,
+    ProposalNotPassed,
+    ProposalAlreadyExecuted,
+    /// The batch is empty, nested or contains a method that is not allowed in a batch.
+    InvalidBatch,
+    /// A step of a batch transaction failed, none of the changes of the batch were kept.
+    BatchStepFailed {
+        step: u32,
+        error: Box<ExecutionError>,
+    },
+    InvalidPorts,
+    NodeKeyAlreadyIndexed,
+    InsufficientClientBalance,
+    NodeJailed,
+    NodeNotJailed,
+    UnjailCooldownNotPassed,
+    NotServiceOwner,
+    InsufficientVestedBalance,
+    VestingScheduleExists,
+    InvalidChainId,
+    UnsupportedPayloadVersion,
+    InvalidProtocolParamValue,
+    ZeroAmount,
+}
+
+/// The result of simulating a transaction against the current state without committing it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TransactionSimulation {
+    /// The response the transaction would have if it was executed now.
+    pub response: TransactionResponse,
+    /// The changes the transaction would make to the state.
+    pub diff: StateDiff,
+}
+
+/// A change to a single entry of a state table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateChange<K, V> {
+    pub key: K,
+    /// The value before the change, [`None`] if the entry did not exist.
+    pub before: Option<V>,
+    /// The value after the change, [`None`] if the entry was removed.
+    pub after: Option<V>,
+}
+
+/// The changes to the application state grouped by table, only tables that can be modified by
+/// transactions are included.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub metadata: Vec<StateChange<Metadata, Value>>,
+    pub account: Vec<StateChange<EthAddress, AccountInfo>>,
+    pub client_keys: Vec<StateChange<ClientPublicKey, EthAddress>>,
+    pub client_balances: Vec<StateChange<ClientPublicKey, HpUfixed<6>>>,
+    pub delivery_ack_sequences: Vec<StateChange<(ClientPublicKey, NodeIndex), u64>>,
+    pub vesting_schedules: Vec<StateChange<(EthAddress, u64), VestingSchedule>>,
+    pub node: Vec<StateChange<NodeIndex, NodeInfo>>,
+    pub consensus_key_to_index: Vec<StateChange<ConsensusPublicKey, NodeIndex>>,
+    pub pub_key_to_index: Vec<StateChange<NodePublicKey, NodeIndex>>,
+    pub latencies: Vec<StateChange<(NodeIndex, NodeIndex), Duration>>,
+    pub committee: Vec<StateChange<Epoch, Committee>>,
+    pub service: Vec<StateChange<ServiceId, Service>>,
+    pub parameter: Vec<StateChange<ProtocolParams, u128>>,
+    pub rep_measurements: Vec<StateChange<NodeIndex, Vec<ReportedReputationMeasurements>>>,
+    pub rep_scores: Vec<StateChange<NodeIndex, u8>>,
+    pub current_epoch_served: Vec<StateChange<NodeIndex, NodeServed>>,
+    pub last_epoch_served: Vec<StateChange<NodeIndex, NodeServed>>,
+    pub total_served: Vec<StateChange<Epoch, TotalServed>>,
+    pub commodity_prices: Vec<StateChange<CommodityTypes, HpUfixed<6>>>,
+    pub service_prices: Vec<StateChange<(ServiceId, CommodityTypes), HpUfixed<6>>>,
+    pub service_price_updates: Vec<StateChange<(ServiceId, CommodityTypes), HpUfixed<6>>>,
+    pub service_revenue: Vec<StateChange<ServiceId, ServiceRevenue>>,
+    pub pending_withdrawals: Vec<StateChange<u64, PendingWithdrawal>>,
+    pub processed_deposits: Vec<StateChange<DepositId, DepositEvent>>,
+    pub slashed_misbehaviors: Vec<StateChange<(NodeIndex, [u8; 32]), HpUfixed<18>>>,
+    pub delegations: Vec<StateChange<(NodeIndex, EthAddress), Delegation>>,
+    pub commissions: Vec<StateChange<NodeIndex, u8>>,
+    pub node_info_updates: Vec<StateChange<NodeIndex, NodeInfoUpdate>>,
+    pub proposals: Vec<StateChange<ProposalId, Proposal>>,
+    pub proposal_votes: Vec<StateChange<(ProposalId, EthAddress), bool>>,
+    pub node_stake_snapshots: Vec<StateChange<(NodeIndex, Epoch), HpUfixed<18>>>,
+    pub randomness_shares: Vec<StateChange<NodeIndex, ConsensusSignature>>,
+    pub node_service_revenue: Vec<StateChange<(NodeIndex, ServiceId), ServiceRevenue>>,
+    pub epoch_rewards: Vec<StateChange<Epoch, EpochRewards>>,
+    pub node_rewards: Vec<StateChange<(Epoch, NodeIndex), NodeRewards>>,
 }
@@ -1,15 +1,16 @@
 //! The data types used in the application state.
 
+use std::collections::BTreeMap;
 use std::net::IpAddr;
 
 use fleek_crypto::{ConsensusPublicKey, EthAddress, NodePublicKey};
 use hp_fixed::unsigned::HpUfixed;
-use ink_quill::TranscriptBuilderInput;
+use ink_quill::{TranscriptBuilder, TranscriptBuilderInput};
 use multiaddr::Multiaddr;
 use num_derive::FromPrimitive;
 use serde::{Deserialize, Serialize};
 
-use super::ReputationMeasurements;
+use super::{ChainId, ReputationMeasurements};
 
 /// The Id of a Service
 pub type ServiceId = u32;
@@ -20,7 +21,9 @@
 /// A nodes index
 pub type NodeIndex = u32;
 
-#[derive(Serialize, Deserialize, Hash, Debug, Clone)]
+const FN_EPOCH_RANDOMNESS_DOMAIN: &str = "FLEEK_NETWORK_EPOCH_RANDOMNESS";
+
+#[derive(Serialize, Deserialize, Hash, Debug, Clone, PartialEq, Eq)]
 pub enum Tokens {
     USDC,
     FLK,
@@ -40,6 +43,45 @@ pub struct TotalServed {
 
 pub type ServiceRevenue = HpUfixed<6>;
 
+/// The rewards a node earned in an epoch.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct NodeRewards {
+    /// The FLK emitted for the node, before it is split between the owner and the delegators.
+    pub flk: HpUfixed<18>,
+    /// The boost of the node for locking its stake, see `ProtocolParams::MaxBoost`.
+    pub boost: HpUfixed<3>,
+    /// The stables paid to the node owner for the commodities served to each service.
+    pub stables: BTreeMap<ServiceId, HpUfixed<6>>,
+}
+
+/// The rewards of a service builder in an epoch.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct ServiceBuilderRewards {
+    pub flk: HpUfixed<18>,
+    pub stables: HpUfixed<6>,
+}
+
+/// The rewards distributed to everyone at the end of an epoch.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct EpochRewards {
+    /// The FLK emitted in the epoch, split between nodes, service builders and the protocol.
+    pub emissions: HpUfixed<18>,
+    /// The stables revenue of all the commodities served in the epoch.
+    pub reward_pool: HpUfixed<6>,
+    pub service_builders: BTreeMap<ServiceId, ServiceBuilderRewards>,
+    pub protocol_flk: HpUfixed<18>,
+    pub protocol_stables: HpUfixed<6>,
+}
+
+/// The rewards of a node in an epoch, along with the rewards of everyone else so the share of the
+/// node can be reconciled.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct RewardsBreakdown {
+    /// The rewards of the node, all zero if the node did not serve anything in the epoch.
+    pub node: NodeRewards,
+    pub epoch: EpochRewards,
+}
+
 /// This is commodity served by each of the commodity types
 type CommodityServed = Vec<u128>;
 
@@ -70,6 +112,23 @@ pub enum Metadata {
     GovernanceAddress,
     LastEpochHash,
     LastBlockHash,
+    NextWithdrawId,
+    BridgeValidators,
+    NextProposalId,
+    BlockNumber,
+    /// The randomness seed of the current epoch, see [`randomness_share_transcript`].
+    EpochRandomness,
+    /// The id of the network that transactions have to be signed for.
+    ChainId,
+    /// The id of the next vesting grant.
+    NextVestingId,
+    /// The state root the current epoch started with. Committing it to the state lets a light
+    /// client verify proofs against the root of an epoch it learned from the chain.
+    EpochStateRoot,
+    /// The hashes of the state deltas stored since the checkpoint of [`Metadata::LastEpochHash`],
+    /// one for each epoch in order. Like the checkpoint hash it is local to the node, so both are
+    /// kept out of the state tree.
+    LastEpochDeltaHashes,
 }
 
 /// The Value enum is a data type used to represent values in a key-value pair for a metadata table
@@ -81,6 +140,13 @@ pub enum Value {
     AccountPublicKey(EthAddress),
     NextNodeIndex(u32),
     Hash([u8; 32]),
+    NextWithdrawId(u64),
+    BridgeValidators(Vec<EthAddress>),
+    NextProposalId(u64),
+    BlockNumber(u64),
+    ChainId(ChainId),
+    NextVestingId(u64),
+    Hashes(Vec<[u8; 32]>),
 }
 
 /// Adjustable parameters that are stored in the blockchain
@@ -109,6 +175,23 @@ pub enum ProtocolParams {
     MaxBoost = 9,
     /// The max amount of time tokens can be locked
     MaxStakeLockTime = 10,
+    /// The percentage of a node's stake that is slashed for a proven misbehavior
+    SlashShare = 11,
+    /// The percentage of the total stake that has to vote on a proposal for it to be valid
+    GovernanceQuorum = 12,
+    /// The percentage of the votes that have to be in favor of a proposal for it to pass
+    GovernanceThreshold = 13,
+    /// The time in epochs that node owners can vote on a proposal
+    VotingPeriod = 14,
+    /// The reputation score below which a node is considered to be performing badly
+    JailReputationThreshold = 15,
+    /// The number of consecutive epochs a node can stay below the reputation threshold before
+    /// it is jailed
+    JailEpochs = 16,
+    /// The time in epochs a node has to stay jailed before it can be unjailed
+    UnjailCooldown = 17,
+    /// The number of blocks the receipts of the transactions are kept for, 0 keeps them forever
+    ReceiptRetention = 18,
 }
 
 #[derive(Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize, Clone)]
@@ -135,6 +218,14 @@ pub struct NodeInfo {
     /// The nonce of the node. Added to each transaction before signed to prevent replays and
     /// enforce ordering
     pub nonce: u64,
+    /// The epoch the node was jailed in, jailed nodes are not part of the node registry and
+    /// can not be chosen for the committee or earn rewards.
+    pub jailed_since: Option<Epoch>,
+    /// The number of consecutive epochs the reputation of the node was below the jail threshold
+    pub low_reputation_epochs: u64,
+    /// The number of consecutive committees the node was part of without revealing its
+    /// randomness share
+    pub missed_randomness_shares: u64,
 }
 
 #[derive(Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize, Clone, Default)]
@@ -161,6 +252,48 @@ pub struct Staking {
     pub locked: HpUfixed<18>,
     /// The epoch the locked FLK is eligible to be withdrawn
     pub locked_until: u64,
+    /// How much of the staked FLK was delegated to the node by accounts other than the owner
+    #[serde(default)]
+    pub delegated: HpUfixed<18>,
+}
+
+/// Struct that stores the stake an account delegated to a node it does not own.
+#[derive(Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct Delegation {
+    /// How much FLK is currently delegated to the node
+    pub staked: HpUfixed<18>,
+    /// How much FLK is undelegated and locked pending withdraw
+    pub locked: HpUfixed<18>,
+    /// The epoch the locked FLK is eligible to be withdrawn
+    pub locked_until: u64,
+}
+
+/// Changes to the details of a node that are applied at the start of the next epoch. Fields that
+/// are [`None`] are left unchanged.
+#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct NodeInfoUpdate {
+    /// The new primary domain of the node
+    pub domain: Option<IpAddr>,
+    /// The new domain of the node workers
+    pub worker_domain: Option<IpAddr>,
+    /// The new public key of the nodes narwhal worker
+    pub worker_public_key: Option<NodePublicKey>,
+    /// The new open ports of the node
+    pub ports: Option<NodePorts>,
+    /// The new consensus key of the node
+    pub consensus_key: Option<ConsensusPublicKey>,
+}
+
+/// A withdrawal that was executed on the network and is waiting to be released on the L2 by the
+/// bridge. Pending withdrawals are keyed by a sequence id that is assigned in order of execution.
+#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct PendingWithdrawal {
+    /// The address to receive these tokens on the L2
+    pub receiver: EthAddress,
+    /// Which token was withdrawn
+    pub token: Tokens,
+    /// The amount withdrawn
+    pub amount: HpUfixed<18>,
 }
 
 #[derive(Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize, Clone)]
@@ -175,7 +308,7 @@ pub struct Worker {
 
 /// Placeholder
 /// Information about the services
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct Service {
     /// the owner address that deploys the service and also recieves reward share
     pub owner: EthAddress,
@@ -193,6 +326,17 @@ pub struct Committee {
     pub epoch_end_timestamp: u64,
 }
 
+/// Returns the transcript that committee members sign with their consensus key to contribute to
+/// the randomness of the next epoch. BLS signatures are unique, so a member can not choose its
+/// share, it can only decide whether to submit it, and members that don't submit it are jailed.
+/// The seed of the next epoch is the hash of the current seed and the shares that were submitted
+/// before the epoch changed.
+pub fn randomness_share_transcript(epoch: Epoch, seed: &[u8; 32]) -> TranscriptBuilder {
+    TranscriptBuilder::empty(FN_EPOCH_RANDOMNESS_DOMAIN)
+        .with("epoch", &epoch)
+        .with("seed", seed)
+}
+
 impl TranscriptBuilderInput for Service {
     const TYPE: &'static str = "service";
 
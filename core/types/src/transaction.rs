@@ -2,7 +2,9 @@
 use std::net::IpAddr;
 
 use fleek_crypto::{
+    ClientPublicKey,
     ConsensusPublicKey,
+    ConsensusSignature,
     EthAddress,
     NodePublicKey,
     TransactionSender,
@@ -14,10 +16,13 @@
 use serde::{Deserialize, Serialize};
 
 use super::{
+    CommodityTypes,
     DeliveryAcknowledgment,
     Epoch,
     ProofOfConsensus,
     ProofOfMisbehavior,
+    ProposalAction,
+    ProposalId,
     ProtocolParams,
     ReputationMeasurements,
     Service,
@@ -28,6 +33,14 @@
 
 // TODO: Change this to capital and non-abrv version.
 const FN_TXN_PAYLOAD_DOMAIN: &str = "fleek_network_txn_payload";
+const FN_TXN_HASH_DOMAIN: &str = "fleek_network_txn_hash";
+const FN_TXN_BATCH_STEP_DOMAIN: &str = "fleek_network_txn_batch_step";
+
+/// The version of [`UpdatePayload`] that is currently accepted by the application.
+pub const UPDATE_PAYLOAD_VERSION: u8 = 1;
+
+/// The id of the network a transaction is meant for
+pub type ChainId = u32;
 
 /// A block of transactions, which is a list of update requests each signed by a user,
 /// the block is the atomic view into the network, meaning that queries do not view
@@ -53,9 +66,29 @@ pub struct UpdateRequest {
     pub payload: UpdatePayload,
 }
 
+impl UpdateRequest {
+    /// Returns the hash that identifies this transaction. It covers the sender together with the
+    /// signed payload, so two transactions that passed the nonce check never share a hash.
+    pub fn hash(&self) -> [u8; 32] {
+        let sender = match &self.sender {
+            TransactionSender::NodeConsensus(public_key) => public_key.0.to_vec(),
+            TransactionSender::NodeMain(public_key) => public_key.0.to_vec(),
+            TransactionSender::AccountOwner(address) => address.0.to_vec(),
+        };
+        TranscriptBuilder::empty(FN_TXN_HASH_DOMAIN)
+            .with("sender", &sender)
+            .with("payload", &self.payload.to_digest())
+            .hash()
+    }
+}
+
 /// The payload data of an update request.
 #[derive(Debug, Hash, Clone, Serialize, Deserialize)]
 pub struct UpdatePayload {
+    /// The version of the payload format, see [`UPDATE_PAYLOAD_VERSION`].
+    pub version: u8,
+    /// The id of the network this request is meant for, so it can not be replayed on another one.
+    pub chain_id: ChainId,
     /// The counter or nonce of this request.
     pub nonce: u64,
     /// The transition function (and parameters) for this update request.
@@ -63,12 +96,16 @@ pub struct UpdatePayload {
 }
 
 /// All of the update functions in our logic, along their parameters.
+///
+/// The variant index is part of the signed payload, so new variants are only ever appended.
 #[derive(Debug, Hash, Clone, Serialize, Deserialize)]
 pub enum UpdateMethod {
     /// The main function of the application layer. After aggregating ProofOfAcknowledgements a
     /// node will submit this transaction to get paid.
     /// Revisit the naming of this transaction.
     SubmitDeliveryAcknowledgmentAggregation {
+        /// The client whose escrow pays for this batch
+        client: ClientPublicKey,
         /// How much of the commodity was served
         commodity: u128,
         /// The service id of the service this was provided through(CDN, compute, ect.)
@@ -96,7 +133,8 @@ pub enum UpdateMethod {
         /// Amount bridged
         amount: HpUfixed<18>,
     },
-    /// Stake FLK in network
+    /// Stake FLK in network. Staking on an existing node that the sender does not own delegates
+    /// the stake to that node
     Stake {
         /// Amount to stake
         amount: HpUfixed<18>,
@@ -120,14 +158,15 @@ pub enum UpdateMethod {
         locked_for: u64,
     },
     /// Unstake FLK, the tokens will be locked for a set amount of
-    /// time(ProtocolParameter::LockTime) before they can be withdrawn
+    /// time(ProtocolParameter::LockTime) before they can be withdrawn. If the sender is not the
+    /// owner of the node this undelegates the senders stake
     Unstake {
         amount: HpUfixed<18>,
         node: NodePublicKey,
     },
     /// Withdraw tokens from a node after lock period has passed
-    /// must be submitted by node owner but optionally they can provide a different public key to
-    /// receive the tokens
+    /// must be submitted by node owner or a delegator of the node but optionally they can provide
+    /// a different public key to receive the tokens, as long as the tokens sent there have vested
     WithdrawUnstaked {
         node: NodePublicKey,
         recipient: Option<EthAddress>,
@@ -152,7 +191,7 @@ pub enum UpdateMethod {
         service_id: ServiceId,
         /// The public key of the node that misbehaved
         node: NodePublicKey,
-        /// Zk proof to be provided to the slash circuit
+        /// Proof that the node misbehaved
         proof_of_misbehavior: ProofOfMisbehavior,
     },
     SubmitReputationMeasurements {
@@ -164,6 +203,95 @@ pub enum UpdateMethod {
     },
     // only for testnet
     TestnetAdmin(TestnetAdmin),
+    /// Transfer tokens to another account in the network
+    Transfer {
+        /// Which token to transfer.
+        token: Tokens,
+        /// The amount to transfer.
+        amount: HpUfixed<18>,
+        /// The account that receives the tokens.
+        to: EthAddress,
+    },
+    /// Sets the percentage of the FLK rewards of a node that the node owner keeps before the rest
+    /// is shared with the delegators of the node
+    SetCommission {
+        node: NodePublicKey,
+        commission: u8,
+    },
+    /// Submit a governance proposal, must be submitted by the owner of a staked node
+    Propose {
+        action: ProposalAction,
+    },
+    /// Vote on an open governance proposal with the stake of the nodes the sender owns
+    Vote {
+        proposal_id: ProposalId,
+        approve: bool,
+    },
+    /// Execute the action of a proposal that passed once its voting period is over
+    ExecuteProposal {
+        proposal_id: ProposalId,
+    },
+    /// Execute a list of methods in order as a single transaction. Either all of them succeed or
+    /// none of their changes are kept. Batches can not be nested and can not change the epoch.
+    Batch(Vec<UpdateMethod>),
+    /// Updates the domains, ports or worker key of a node, only callable by the node owner. The
+    /// changes take effect at the start of the next epoch.
+    UpdateNodeInfo {
+        node: NodePublicKey,
+        domain: Option<IpAddr>,
+        worker_domain: Option<IpAddr>,
+        worker_public_key: Option<NodePublicKey>,
+        ports: Option<NodePorts>,
+    },
+    /// Replaces the public key and/or the consensus key of a node, only callable by the node
+    /// owner. The node keeps its index, stake, reputation and served history. The public key is
+    /// replaced right away, the consensus key at the start of the next epoch.
+    RotateKeys {
+        node: NodePublicKey,
+        new_public_key: Option<NodePublicKey>,
+        new_consensus_key: Option<ConsensusPublicKey>,
+    },
+    /// Sent by committee members to contribute to the randomness of the next epoch, the
+    /// signature is over [`randomness_share_transcript`](crate::randomness_share_transcript) of
+    /// the current epoch and seed. Members that haven't sent it when the epoch changes are
+    /// jailed.
+    SubmitRandomnessShare {
+        epoch: Epoch,
+        signature: ConsensusSignature,
+    },
+    /// Move stables from the bandwidth balance of the sender into the escrow of a client, which
+    /// is used to pay for the services delivered to that client
+    FundClient {
+        /// The client whose escrow is credited.
+        client: ClientPublicKey,
+        /// The amount of stables to move.
+        amount: u128,
+    },
+    /// Releases a jailed node once the unjail cooldown has passed, only callable by the node
+    /// owner.
+    Unjail {
+        node: NodePublicKey,
+    },
+    /// Set the price a service pays for a commodity, starting from the next epoch. Only callable
+    /// by the service owner or the governance address.
+    SetServicePrice {
+        service_id: ServiceId,
+        commodity: CommodityTypes,
+        price: HpUfixed<6>,
+    },
+    /// Transfer FLK to an account that vests according to a schedule starting in the current
+    /// epoch. Every grant gets its own schedule, so grants to the same account vest
+    /// independently.
+    GrantVesting {
+        /// The account that receives the tokens.
+        recipient: EthAddress,
+        /// The amount of FLK to grant.
+        amount: HpUfixed<18>,
+        /// The number of epochs before anything vests.
+        cliff: Epoch,
+        /// The number of epochs until everything has vested.
+        duration: Epoch,
+    },
 }
 
 // only for testnet
@@ -179,179 +307,334 @@ impl ToDigest for UpdatePayload {
     /// # Safety
     ///
     /// This function must take all of the data into account, including the
-    /// nonce, the name of all of the update method names along with the value
-    /// for all of the parameters.
+    /// version, the chain id, the nonce, the name of all of the update method
+    /// names along with the value for all of the parameters.
     fn transcript(&self) -> TranscriptBuilder {
-        let mut transcript_builder =
-            TranscriptBuilder::empty(FN_TXN_PAYLOAD_DOMAIN).with("nonce", &self.nonce);
+        let transcript_builder = TranscriptBuilder::empty(FN_TXN_PAYLOAD_DOMAIN)
+            .with("version", &self.version)
+            .with("chain_id", &self.chain_id)
+            .with("nonce", &self.nonce);
 
-        // insert method fields
-        match &self.method {
-            UpdateMethod::Deposit {
-                proof: _,
-                token,
-                amount,
-            } => {
-                transcript_builder = transcript_builder
-                    .with("transaction_name", &"deposit")
-                    .with_prefix("input".to_owned())
-                    .with("token", token)
-                    .with("amount", &HpUfixedWrapper(amount.clone()));
-                //.with("method.proof", proof);
-            },
+        method_transcript(transcript_builder, &self.method)
+    }
+}
 
-            UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
-                commodity,
-                service_id,
-                proofs: _,
-                metadata,
-            } => {
-                transcript_builder = transcript_builder
-                    .with(
-                        "transaction_name",
-                        &"submit_delivery_acknowledgment_aggregation",
-                    )
-                    .with_prefix("input".to_owned())
-                    .with("commodity", commodity)
-                    .with("service_id", service_id)
-                    .with("metadata", metadata);
-                //.with("method.proof", proof);
-            },
-            UpdateMethod::Withdraw {
-                amount,
-                token,
-                receiving_address,
-            } => {
-                transcript_builder = transcript_builder
-                    .with("transaction_name", &"withdraw")
-                    .with_prefix("input".to_owned())
-                    .with("amount", &HpUfixedWrapper(amount.clone()))
-                    .with("token", token)
-                    .with("receiving_address", &receiving_address.0);
-            },
-            UpdateMethod::Stake {
-                amount,
-                node_public_key,
-                consensus_key,
-                node_domain,
-                worker_public_key,
-                worker_domain,
-                ports,
-            } => {
-                transcript_builder = transcript_builder
-                    .with("transaction_name", &"stake")
-                    .with_prefix("input".to_owned())
-                    .with("amount", &HpUfixedWrapper(amount.clone()))
-                    .with("node_public_key", &node_public_key.0)
-                    .with(
-                        "node_network_key",
-                        &consensus_key.map_or([0u8; 96], |key| key.0),
-                    )
-                    .with("node_domain", &node_domain.map(|d| d.to_string()))
-                    .with(
-                        "worker_public_key",
-                        &worker_public_key.map_or([0u8; 32], |key| key.0),
-                    )
-                    .with("worker_domain", &worker_domain.map(|d| d.to_string()))
-                    .with("primary_port", &ports.as_ref().map(|p| p.primary))
-                    .with("worker_port", &ports.as_ref().map(|p| p.worker))
-                    .with("mempool_port", &ports.as_ref().map(|p| p.mempool))
-                    .with("rpc_port", &ports.as_ref().map(|p| p.rpc))
-                    .with("pool_port", &ports.as_ref().map(|p| p.pool))
-                    .with("dht_port", &ports.as_ref().map(|p| p.dht))
-                    .with("handshake_port", &ports.as_ref().map(|p| p.handshake))
-            },
-            UpdateMethod::StakeLock { node, locked_for } => {
-                transcript_builder = transcript_builder
-                    .with("transaction_name", &"stake_lock")
-                    .with_prefix("input".to_owned())
-                    .with("node", &node.0)
-                    .with("locked_for", locked_for);
-            },
-            UpdateMethod::Unstake { amount, node } => {
-                transcript_builder = transcript_builder
-                    .with("transaction_name", &"unstake")
-                    .with_prefix("input".to_owned())
-                    .with("node", &node.0)
-                    .with("amount", &HpUfixedWrapper(amount.clone()));
-            },
-            UpdateMethod::WithdrawUnstaked { node, recipient } => {
-                transcript_builder = transcript_builder
-                    .with("transaction_name", &"withdraw_unstaked")
-                    .with_prefix("input".to_owned())
-                    .with("node", &node.0)
-                    .with("recipient", &recipient.map_or([0u8; 20], |key| key.0));
-            },
-            UpdateMethod::ChangeEpoch { epoch } => {
-                transcript_builder = transcript_builder
-                    .with("transaction_name", &"change_epoch")
-                    .with_prefix("input".to_owned())
-                    .with("epoch", epoch);
-            },
-            UpdateMethod::AddService {
-                service,
-                service_id,
-            } => {
-                transcript_builder = transcript_builder
-                    .with("transaction_name", &"add_service")
-                    .with_prefix("input".to_owned())
-                    .with("service_id", service_id)
-                    .with("service", service);
-            },
-            UpdateMethod::RemoveService { service_id } => {
+/// Inserts the name of the update method and the values for all of its parameters into the
+/// transcript.
+fn method_transcript(
+    mut transcript_builder: TranscriptBuilder,
+    method: &UpdateMethod,
+) -> TranscriptBuilder {
+    // insert method fields
+    match method {
+        UpdateMethod::Deposit {
+            proof,
+            token,
+            amount,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"deposit")
+                .with_prefix("input".to_owned())
+                .with("token", token)
+                .with("amount", &HpUfixedWrapper(amount.clone()))
+                .with("deposit_event", &proof.event.to_digest());
+        },
+
+        UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+            client,
+            commodity,
+            service_id,
+            proofs: _,
+            metadata,
+        } => {
+            transcript_builder = transcript_builder
+                .with(
+                    "transaction_name",
+                    &"submit_delivery_acknowledgment_aggregation",
+                )
+                .with_prefix("input".to_owned())
+                .with("client", &client.0)
+                .with("commodity", commodity)
+                .with("service_id", service_id)
+                .with("metadata", metadata);
+            //.with("method.proof", proof);
+        },
+        UpdateMethod::Withdraw {
+            amount,
+            token,
+            receiving_address,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"withdraw")
+                .with_prefix("input".to_owned())
+                .with("amount", &HpUfixedWrapper(amount.clone()))
+                .with("token", token)
+                .with("receiving_address", &receiving_address.0);
+        },
+        UpdateMethod::Transfer { token, amount, to } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"transfer")
+                .with_prefix("input".to_owned())
+                .with("token", token)
+                .with("amount", &HpUfixedWrapper(amount.clone()))
+                .with("to", &to.0);
+        },
+        UpdateMethod::GrantVesting {
+            recipient,
+            amount,
+            cliff,
+            duration,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"grant_vesting")
+                .with_prefix("input".to_owned())
+                .with("recipient", &recipient.0)
+                .with("amount", &HpUfixedWrapper(amount.clone()))
+                .with("cliff", cliff)
+                .with("duration", duration);
+        },
+        UpdateMethod::FundClient { client, amount } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"fund_client")
+                .with_prefix("input".to_owned())
+                .with("client", &client.0)
+                .with("amount", amount);
+        },
+        UpdateMethod::Stake {
+            amount,
+            node_public_key,
+            consensus_key,
+            node_domain,
+            worker_public_key,
+            worker_domain,
+            ports,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"stake")
+                .with_prefix("input".to_owned())
+                .with("amount", &HpUfixedWrapper(amount.clone()))
+                .with("node_public_key", &node_public_key.0)
+                .with(
+                    "node_network_key",
+                    &consensus_key.map_or([0u8; 96], |key| key.0),
+                )
+                .with("node_domain", &node_domain.map(|d| d.to_string()))
+                .with(
+                    "worker_public_key",
+                    &worker_public_key.map_or([0u8; 32], |key| key.0),
+                )
+                .with("worker_domain", &worker_domain.map(|d| d.to_string()))
+                .with("primary_port", &ports.as_ref().map(|p| p.primary))
+                .with("worker_port", &ports.as_ref().map(|p| p.worker))
+                .with("mempool_port", &ports.as_ref().map(|p| p.mempool))
+                .with("rpc_port", &ports.as_ref().map(|p| p.rpc))
+                .with("pool_port", &ports.as_ref().map(|p| p.pool))
+                .with("dht_port", &ports.as_ref().map(|p| p.dht))
+                .with("handshake_port", &ports.as_ref().map(|p| p.handshake))
+        },
+        UpdateMethod::StakeLock { node, locked_for } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"stake_lock")
+                .with_prefix("input".to_owned())
+                .with("node", &node.0)
+                .with("locked_for", locked_for);
+        },
+        UpdateMethod::Unstake { amount, node } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"unstake")
+                .with_prefix("input".to_owned())
+                .with("node", &node.0)
+                .with("amount", &HpUfixedWrapper(amount.clone()));
+        },
+        UpdateMethod::WithdrawUnstaked { node, recipient } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"withdraw_unstaked")
+                .with_prefix("input".to_owned())
+                .with("node", &node.0)
+                .with("recipient", &recipient.map_or([0u8; 20], |key| key.0));
+        },
+        UpdateMethod::SetCommission { node, commission } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"set_commission")
+                .with_prefix("input".to_owned())
+                .with("node", &node.0)
+                .with("commission", commission);
+        },
+        UpdateMethod::UpdateNodeInfo {
+            node,
+            domain,
+            worker_domain,
+            worker_public_key,
+            ports,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"update_node_info")
+                .with_prefix("input".to_owned())
+                .with("node", &node.0)
+                .with("domain", &domain.map(|d| d.to_string()))
+                .with("worker_domain", &worker_domain.map(|d| d.to_string()))
+                .with(
+                    "worker_public_key",
+                    &worker_public_key.map_or([0u8; 32], |key| key.0),
+                )
+                .with("primary_port", &ports.as_ref().map(|p| p.primary))
+                .with("worker_port", &ports.as_ref().map(|p| p.worker))
+                .with("mempool_port", &ports.as_ref().map(|p| p.mempool))
+                .with("rpc_port", &ports.as_ref().map(|p| p.rpc))
+                .with("pool_port", &ports.as_ref().map(|p| p.pool))
+                .with("dht_port", &ports.as_ref().map(|p| p.dht))
+                .with("handshake_port", &ports.as_ref().map(|p| p.handshake))
+                .with("blockstore_port", &ports.as_ref().map(|p| p.blockstore));
+        },
+        UpdateMethod::RotateKeys {
+            node,
+            new_public_key,
+            new_consensus_key,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"rotate_keys")
+                .with_prefix("input".to_owned())
+                .with("node", &node.0)
+                .with(
+                    "new_public_key",
+                    &new_public_key.map_or([0u8; 32], |key| key.0),
+                )
+                .with(
+                    "new_consensus_key",
+                    &new_consensus_key.map_or([0u8; 96], |key| key.0),
+                );
+        },
+        UpdateMethod::Unjail { node } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"unjail")
+                .with_prefix("input".to_owned())
+                .with("node", &node.0);
+        },
+        UpdateMethod::ChangeEpoch { epoch } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"change_epoch")
+                .with_prefix("input".to_owned())
+                .with("epoch", epoch);
+        },
+        UpdateMethod::SubmitRandomnessShare { epoch, signature } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"submit_randomness_share")
+                .with_prefix("input".to_owned())
+                .with("epoch", epoch)
+                .with("signature", &signature.0);
+        },
+        UpdateMethod::AddService {
+            service,
+            service_id,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"add_service")
+                .with_prefix("input".to_owned())
+                .with("service_id", service_id)
+                .with("service", service);
+        },
+        UpdateMethod::RemoveService { service_id } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"remove_service")
+                .with_prefix("input".to_owned())
+                .with("service_id", service_id);
+        },
+        UpdateMethod::SetServicePrice {
+            service_id,
+            commodity,
+            price,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"set_service_price")
+                .with_prefix("input".to_owned())
+                .with("service_id", service_id)
+                .with("commodity", commodity)
+                .with("price", &HpUfixedWrapper(price.clone()));
+        },
+        UpdateMethod::Slash {
+            service_id,
+            node,
+            proof_of_misbehavior,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"slash")
+                .with_prefix("input".to_owned())
+                .with("service_id", service_id)
+                .with("node", &node.0)
+                .with("proof_of_misbehavior", &proof_of_misbehavior.to_digest());
+        },
+        UpdateMethod::SubmitReputationMeasurements { measurements } => {
+            transcript_builder =
+                transcript_builder.with("transaction_name", &"submit_reputation_measurements");
+            for (key, value) in measurements {
                 transcript_builder = transcript_builder
-                    .with("transaction_name", &"remove_service")
-                    .with_prefix("input".to_owned())
-                    .with("service_id", service_id);
-            },
-            UpdateMethod::Slash {
-                service_id,
-                node,
-                proof_of_misbehavior: _,
-            } => {
+                    .with_prefix(key.to_string())
+                    .with("latency", &value.latency.map_or(0, |l| l.as_nanos()))
+                    .with("interactions", &value.interactions)
+                    .with("inbound_bandwidth", &value.inbound_bandwidth)
+                    .with("outbound_bandwidth", &value.outbound_bandwidth)
+                    .with("bytes_received", &value.bytes_received)
+                    .with("bytes_sent", &value.bytes_sent)
+                    .with("hops", &value.hops);
+            }
+        },
+        UpdateMethod::ChangeProtocolParam { param, value } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"change_protocol_param")
+                .with_prefix("input".to_owned())
+                .with("param", &(param.clone() as u8))
+                .with("value", value);
+        },
+        UpdateMethod::Propose { action } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"propose")
+                .with_prefix("input".to_owned())
+                .with("action", &action.to_digest());
+        },
+        UpdateMethod::Vote {
+            proposal_id,
+            approve,
+        } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"vote")
+                .with_prefix("input".to_owned())
+                .with("proposal_id", proposal_id)
+                .with("approve", &(*approve as u8));
+        },
+        UpdateMethod::ExecuteProposal { proposal_id } => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"execute_proposal")
+                .with_prefix("input".to_owned())
+                .with("proposal_id", proposal_id);
+        },
+        UpdateMethod::Batch(methods) => {
+            transcript_builder = transcript_builder
+                .with("transaction_name", &"batch")
+                .with_prefix("input".to_owned())
+                .with("steps", &(methods.len() as u64));
+            // Every step is hashed on its own so the labels of different steps can not
+            // collide.
+            for (index, method) in methods.iter().enumerate() {
+                let step_digest =
+                    method_transcript(TranscriptBuilder::empty(FN_TXN_BATCH_STEP_DOMAIN), method)
+                        .hash();
                 transcript_builder = transcript_builder
-                    .with("transaction_name", &"slash")
-                    .with_prefix("input".to_owned())
-                    .with("service_id", service_id)
-                .with("node", &node.0)
-                // .with("proof_of_misbehavior", proof_of_misbehavior)
-                ;
-            },
-            UpdateMethod::SubmitReputationMeasurements { measurements } => {
+                    .with_prefix(format!("step_{index}"))
+                    .with("method", &step_digest);
+            }
+        },
+        UpdateMethod::TestnetAdmin(inner) => match inner {
+            TestnetAdmin::Kill => {
                 transcript_builder =
-                    transcript_builder.with("transaction_name", &"submit_reputation_measurements");
-                for (key, value) in measurements {
-                    transcript_builder = transcript_builder
-                        .with_prefix(key.to_string())
-                        .with("latency", &value.latency.map_or(0, |l| l.as_nanos()))
-                        .with("interactions", &value.interactions)
-                        .with("inbound_bandwidth", &value.inbound_bandwidth)
-                        .with("outbound_bandwidth", &value.outbound_bandwidth)
-                        .with("bytes_received", &value.bytes_received)
-                        .with("bytes_sent", &value.bytes_sent)
-                        .with("hops", &value.hops);
-                }
-            },
-            UpdateMethod::ChangeProtocolParam { param, value } => {
-                transcript_builder = transcript_builder
-                    .with("transaction_name", &"change_protocol_param")
-                    .with_prefix("input".to_owned())
-                    .with("param", &(param.clone() as u8))
-                    .with("value", value);
-            },
-            UpdateMethod::TestnetAdmin(inner) => match inner {
-                TestnetAdmin::Kill => {
-                    transcript_builder =
-                        transcript_builder.with("transaction_name", &"testnet_admin_kill")
-                },
+                    transcript_builder.with("transaction_name", &"testnet_admin_kill")
             },
-        }
-
-        transcript_builder
+        },
     }
+
+    transcript_builder
 }
 
-struct HpUfixedWrapper<const T: usize>(HpUfixed<T>);
+pub(crate) struct HpUfixedWrapper<const T: usize>(pub(crate) HpUfixed<T>);
 
 impl<const T: usize> HpUfixedWrapper<T> {
     #[inline]
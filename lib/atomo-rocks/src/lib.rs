@@ -1,18 +1,39 @@
 //! A [`rocksdb`] storage backend implementation for [`atomo`].
 
 mod serialization;
+use std::collections::BTreeMap;
+use std::ops::Bound;
 use std::path::PathBuf;
+use std::sync::Mutex;
 use std::{env, fs};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use atomo::batch::Operation;
-use atomo::{AtomoBuilder, DefaultSerdeBackend, StorageBackend, StorageBackendConstructor};
+use atomo::{
+    AtomoBuilder,
+    DefaultSerdeBackend,
+    StorageBackend,
+    StorageBackendConstructor,
+    StorageIterator,
+};
+use fleek_blake3 as blake3;
 use fxhash::FxHashMap;
+use rocksdb::checkpoint::Checkpoint;
 /// Re-export of [`rocksdb::Options`].
 pub use rocksdb::Options;
 pub use rocksdb::{Cache, Env, DB};
-use rocksdb::{ColumnFamilyDescriptor, WriteBatch};
-pub use serialization::{build_db_from_checkpoint, serialize_db};
+use rocksdb::{ColumnFamilyDescriptor, Direction, IteratorMode, ReadOptions, Snapshot, WriteBatch};
+use serialization::serialize_snapshot;
+pub use serialization::{
+    apply_deltas,
+    build_db_from_checkpoint,
+    deserialize_delta,
+    hash_db,
+    serialize_db,
+    serialize_delta,
+    Delta,
+    TableChanges,
+};
 
 /// Helper alias for an [`atomo::AtomoBuilder`] using a [`RocksBackendBuilder`].
 pub type AtomoBuilderWithRocks<S = DefaultSerdeBackend> = AtomoBuilder<RocksBackendBuilder, S>;
@@ -47,6 +68,8 @@ pub struct RocksBackendBuilder {
     columns: Vec<String>,
     column_options: FxHashMap<String, Options>,
     checkpoint: Option<([u8; 32], Vec<u8>)>,
+    deltas: Vec<([u8; 32], Vec<u8>)>,
+    track_deltas: bool,
 }
 
 impl RocksBackendBuilder {
@@ -59,6 +82,8 @@ pub fn new<P: Into<PathBuf>>(path: P) -> Self {
             columns: Default::default(),
             column_options: Default::default(),
             checkpoint: Default::default(),
+            deltas: Default::default(),
+            track_deltas: Default::default(),
         }
     }
 
@@ -85,6 +110,25 @@ pub fn with_table_option(mut self, name: &str, opts: Options) -> Self {
         self.checkpoint = Some((hash, checkpoint));
         self
     }
+
+    /// Provide a chain of deltas, each with the hash of its bytes, that will be applied in order
+    /// on top of the checkpoint (or of the existing database if there is no checkpoint). Building
+    /// fails if any delta does not match its hash or does not apply to the state left by the
+    /// previous one.
+    #[inline(always)]
+    pub fn with_checkpoint_deltas(mut self, deltas: Vec<([u8; 32], Vec<u8>)>) -> Self {
+        self.deltas = deltas;
+        self
+    }
+
+    /// Record the committed changes, so they can be serialized as a delta against the previous
+    /// checkpoint with [`RocksBackend::serialize_delta`]. A database that was opened with delta
+    /// tracking has to keep it enabled.
+    #[inline(always)]
+    pub fn with_delta_tracking(mut self) -> Self {
+        self.track_deltas = true;
+        self
+    }
 }
 
 impl StorageBackendConstructor for RocksBackendBuilder {
@@ -97,85 +141,313 @@ fn open_table(&mut self, name: String) {
     }
 
     fn build(mut self) -> Result<Self::Storage, Self::Error> {
-        let cf_iter: Vec<_> = self
-            .columns
-            .iter()
-            .map(|name| {
-                ColumnFamilyDescriptor::new(
-                    name,
-                    self.column_options.remove(name).unwrap_or_default(),
-                )
-            })
-            .collect();
-        let db = match self.checkpoint {
-            Some((hash, checkpoint)) => {
-                // We try to build the db from a checkpoint in a temporary dir.
-                let tmp_path = env::temp_dir().join("lightning_checkpoint_tmp");
-                if tmp_path.exists() {
-                    fs::remove_dir_all(&tmp_path)?;
-                }
-                let (_db, column_names) =
-                    build_db_from_checkpoint(&tmp_path, hash, &checkpoint, self.options.clone())?;
-                // If the build was successful, we move the db over to the actual directory.
-                if self.path.exists() {
-                    fs::remove_dir_all(&self.path)?;
-                }
-                fs::rename(&tmp_path, &self.path)?;
-                if tmp_path.exists() {
-                    fs::remove_dir_all(&tmp_path)?;
-                }
-                let cf_iter: Vec<_> = column_names
-                    .iter()
-                    .map(|name| {
-                        ColumnFamilyDescriptor::new(
-                            name,
-                            self.column_options.remove(name).unwrap_or_default(),
-                        )
-                    })
-                    .collect();
-                let mut options = self.options;
-                // The database should exist at this point.
-                options.create_if_missing(false);
-                DB::open_cf_descriptors(&options, &self.path, cf_iter)?
-            },
-            None => DB::open_cf_descriptors(&self.options, self.path, cf_iter)?,
+        // The changes are recorded in a column of their own, which is not part of the
+        // checkpoints.
+        let delta_column = self.track_deltas.then(|| DELTA_COLUMN.to_owned());
+        let (db, base) = if self.checkpoint.is_none() && self.deltas.is_empty() {
+            let cf_iter: Vec<_> = self
+                .columns
+                .iter()
+                .chain(&delta_column)
+                .map(|name| {
+                    ColumnFamilyDescriptor::new(
+                        name,
+                        self.column_options.remove(name).unwrap_or_default(),
+                    )
+                })
+                .collect();
+            let db = DB::open_cf_descriptors(&self.options, &self.path, cf_iter)?;
+            (db, None)
+        } else {
+            // We try to build the db in a temporary dir, so a bad checkpoint or delta leaves the
+            // existing db untouched.
+            let tmp_path = env::temp_dir().join("lightning_checkpoint_tmp");
+            if tmp_path.exists() {
+                fs::remove_dir_all(&tmp_path)?;
+            }
+            let (db, column_names, hash) = match self.checkpoint {
+                Some((hash, checkpoint)) => {
+                    let (db, column_names) = build_db_from_checkpoint(
+                        &tmp_path,
+                        hash,
+                        &checkpoint,
+                        self.options.clone(),
+                    )?;
+                    (db, column_names, Some(hash))
+                },
+                None => {
+                    // The deltas are applied to a copy of the existing db.
+                    let column_names: Vec<_> =
+                        self.columns.iter().chain(&delta_column).cloned().collect();
+                    let db = DB::open_cf(&self.options, &self.path, &column_names)?;
+                    Checkpoint::new(&db)?.create_checkpoint(&tmp_path)?;
+                    drop(db);
+                    let db = DB::open_cf(&self.options, &tmp_path, &column_names)?;
+                    (db, self.columns.clone(), None)
+                },
+            };
+            let hash = if self.deltas.is_empty() {
+                hash
+            } else {
+                apply_deltas(&db, &column_names, hash, &self.deltas)?
+            };
+            drop(db);
+            // If the build was successful, we move the db over to the actual directory.
+            if self.path.exists() {
+                fs::remove_dir_all(&self.path)?;
+            }
+            fs::rename(&tmp_path, &self.path)?;
+            if tmp_path.exists() {
+                fs::remove_dir_all(&tmp_path)?;
+            }
+            let cf_iter: Vec<_> = column_names
+                .iter()
+                .chain(&delta_column)
+                .map(|name| {
+                    ColumnFamilyDescriptor::new(
+                        name,
+                        self.column_options.remove(name).unwrap_or_default(),
+                    )
+                })
+                .collect();
+            let mut options = self.options;
+            // The database should exist at this point.
+            options.create_if_missing(false);
+            (
+                DB::open_cf_descriptors(&options, &self.path, cf_iter)?,
+                hash,
+            )
+        };
+
+        let deltas = if self.track_deltas {
+            Some(DeltaTracker::open(&db, &self.columns, base)?)
+        } else {
+            None
         };
 
         Ok(RocksBackend {
             columns: self.columns,
             db,
+            deltas,
         })
     }
 }
 
+/// The name of the column that holds the changes committed since the last checkpoint.
+const DELTA_COLUMN: &str = "__checkpoint_delta";
+
+/// The key of the hash of the last checkpoint in the [`DELTA_COLUMN`].
+const BASE_KEY: [u8; 1] = [0];
+
+/// The prefix of the keys of the changes in the [`DELTA_COLUMN`].
+const CHANGE_PREFIX: u8 = 1;
+
+/// Returns the prefix of the keys of the changes recorded under the given generation. The full
+/// key is followed by the table name with its length and the key in the table.
+fn change_key_prefix(generation: u64) -> Vec<u8> {
+    let mut key = vec![CHANGE_PREFIX];
+    key.extend(generation.to_be_bytes());
+    key
+}
+
+/// Records the changes committed since the last checkpoint in the [`DELTA_COLUMN`], next to
+/// the hash of the last checkpoint. The changes are written in the same batch as the tables, so
+/// they survive restarts and every node that commits the same batches between two checkpoints
+/// takes the same delta.
+struct DeltaTracker {
+    /// The generation the committed changes are recorded under. It is bumped when a checkpoint
+    /// is taken, so the changes committed while the checkpoint is being hashed are left for the
+    /// next one.
+    generation: Mutex<u64>,
+    /// Makes sure only one checkpoint is taken at a time.
+    checkpoint_lock: Mutex<()>,
+}
+
+impl DeltaTracker {
+    /// Opens the tracker of a database. The given hash is the one of the database state if it
+    /// was just built from a checkpoint or from deltas, which replaces the recorded changes.
+    fn open(db: &DB, table_names: &[String], hash: Option<[u8; 32]>) -> Result<Self> {
+        let cf = db.cf_handle(DELTA_COLUMN).context("Unknown table name")?;
+        let base = db.get_cf(&cf, BASE_KEY)?;
+        let generation = if hash.is_none() && base.is_some() {
+            // Keep recording under the last generation.
+            db.iterator_cf(&cf, IteratorMode::End)
+                .next()
+                .transpose()?
+                .filter(|(key, _)| key[0] == CHANGE_PREFIX)
+                .map(|(key, _)| u64::from_be_bytes(key[1..9].try_into().unwrap()))
+                .unwrap_or_default()
+        } else {
+            let hash = match hash {
+                Some(hash) => hash,
+                None => hash_db(db, table_names)?,
+            };
+            let mut batch = WriteBatch::default();
+            batch.delete_range_cf(&cf, change_key_prefix(0), vec![CHANGE_PREFIX + 1]);
+            batch.put_cf(&cf, BASE_KEY, hash);
+            db.write(batch)?;
+            0
+        };
+        Ok(Self {
+            generation: Mutex::new(generation),
+            checkpoint_lock: Mutex::new(()),
+        })
+    }
+
+    /// Takes a snapshot of the database and returns it with the generation of the changes it
+    /// holds. The changes committed after the snapshot are recorded under the next generation.
+    fn snapshot<'a>(&self, db: &'a DB) -> (Snapshot<'a>, u64) {
+        let mut generation = self
+            .generation
+            .lock()
+            .expect("Could not acquire the delta lock");
+        let snapshot = db.snapshot();
+        *generation += 1;
+        (snapshot, *generation - 1)
+    }
+
+    /// Reads the hash of the last checkpoint and the changes recorded since then, up to the given
+    /// generation, from a snapshot.
+    fn read(
+        db: &DB,
+        snapshot: &Snapshot,
+        generation: u64,
+    ) -> Result<([u8; 32], BTreeMap<String, TableChanges>)> {
+        let cf = db.cf_handle(DELTA_COLUMN).context("Unknown table name")?;
+        let base = snapshot
+            .get_cf(&cf, BASE_KEY)?
+            .context("Missing checkpoint hash")?
+            .try_into()
+            .map_err(|_| anyhow!("Invalid checkpoint hash"))?;
+        let mut tables: BTreeMap<String, TableChanges> = BTreeMap::new();
+        let end = change_key_prefix(generation + 1);
+        let from = change_key_prefix(0);
+        let iterator = snapshot.iterator_cf(&cf, IteratorMode::From(&from[..], Direction::Forward));
+        // The changes are ordered by their generation, so the later ones win.
+        for entry in iterator {
+            let (key, value) = entry?;
+            if key[..] >= end[..] {
+                break;
+            }
+            let table_name_len = u64::from_le_bytes(key[9..17].try_into()?) as usize;
+            let table_name = String::from_utf8(key[17..17 + table_name_len].to_vec())?;
+            let value = match value[0] {
+                0 => None,
+                _ => Some(value[1..].into()),
+            };
+            tables
+                .entry(table_name)
+                .or_default()
+                .insert(key[17 + table_name_len..].into(), value);
+        }
+        Ok((base, tables))
+    }
+
+    /// Removes the changes up to the given generation, which are covered by the checkpoint with
+    /// the given hash.
+    fn reset(db: &DB, generation: u64, hash: [u8; 32]) -> Result<()> {
+        let cf = db.cf_handle(DELTA_COLUMN).context("Unknown table name")?;
+        let mut batch = WriteBatch::default();
+        batch.delete_range_cf(&cf, change_key_prefix(0), change_key_prefix(generation + 1));
+        batch.put_cf(&cf, BASE_KEY, hash);
+        db.write(batch)?;
+        Ok(())
+    }
+}
+
 /// RocksDB persistence backend for [`atomo`].
 pub struct RocksBackend {
     db: rocksdb::DB,
     columns: Vec<String>,
+    deltas: Option<DeltaTracker>,
 }
 
 impl RocksBackend {
     pub fn serialize(&self) -> Vec<u8> {
-        // We can safely unwrap here.
-        // This will only panic if the table names in `columns` are not consistent with the
-        // database.
-        serialize_db(&self.db, &self.columns).unwrap()
+        let Some(deltas) = &self.deltas else {
+            // We can safely unwrap here.
+            // This will only panic if the table names in `columns` are not consistent with the
+            // database.
+            return serialize_db(&self.db, &self.columns).unwrap();
+        };
+        let _guard = deltas
+            .checkpoint_lock
+            .lock()
+            .expect("Could not acquire the checkpoint lock");
+        let (snapshot, generation) = deltas.snapshot(&self.db);
+        let checkpoint = serialize_snapshot(&self.db, &snapshot, &self.columns).unwrap();
+        // The next delta is taken against this checkpoint.
+        DeltaTracker::reset(&self.db, generation, *blake3::hash(&checkpoint).as_bytes())
+            .expect("failed to reset the delta tracker");
+        checkpoint
+    }
+
+    /// Serializes the changes committed since the last checkpoint as a delta. The last
+    /// checkpoint is the one created by the previous call to this method or to
+    /// [`RocksBackend::serialize`], or the state of the database when it was first opened with
+    /// delta tracking or built from a checkpoint.
+    ///
+    /// Hashing the whole database for every delta would be linear in the size of the state, so
+    /// the hash of the resulting state is provided by the caller instead, which has to maintain
+    /// it incrementally (such as the root of the state tree of [`atomo`]) and check it when the
+    /// deltas are applied. It has to be the hash of the state as of the call, so no commits may
+    /// happen concurrently.
+    ///
+    /// Returns an error if the backend was not built
+    /// [`with_delta_tracking`](RocksBackendBuilder::with_delta_tracking).
+    pub fn serialize_delta(&self, result: [u8; 32]) -> Result<Vec<u8>> {
+        let deltas = self
+            .deltas
+            .as_ref()
+            .context("Delta tracking is not enabled")?;
+        let _guard = deltas
+            .checkpoint_lock
+            .lock()
+            .expect("Could not acquire the checkpoint lock");
+        let (snapshot, generation) = deltas.snapshot(&self.db);
+        let (base, tables) = DeltaTracker::read(&self.db, &snapshot, generation)?;
+        DeltaTracker::reset(&self.db, generation, result)?;
+        Ok(serialize_delta(&Delta {
+            base,
+            result,
+            tables,
+        }))
     }
 }
 
 impl StorageBackend for RocksBackend {
     fn commit(&self, batch: atomo::batch::VerticalBatch) {
+        // The generation is locked until the batch is written, so a checkpoint snapshot either
+        // has all of the changes of the batch under its generation or none of them.
+        let delta = self.deltas.as_ref().map(|deltas| {
+            let generation = deltas
+                .generation
+                .lock()
+                .expect("Could not acquire the delta lock");
+            (generation, self.db.cf_handle(DELTA_COLUMN).unwrap())
+        });
         let mut inner_batch = WriteBatch::default();
         for (table, batch) in self.columns.iter().zip(batch.into_raw().into_iter()) {
             let cf = self.db.cf_handle(table).unwrap();
             for (key, operation) in batch {
-                match operation {
+                let change = match operation {
                     Operation::Insert(value) => {
-                        inner_batch.put_cf(&cf, key, value);
+                        inner_batch.put_cf(&cf, &key, &value);
+                        let mut change = vec![1];
+                        change.extend(value.iter());
+                        change
                     },
                     Operation::Remove => {
-                        inner_batch.delete_cf(&cf, key);
+                        inner_batch.delete_cf(&cf, &key);
+                        vec![0]
                     },
+                };
+                if let Some((generation, delta_cf)) = &delta {
+                    let mut change_key = change_key_prefix(**generation);
+                    change_key.extend((table.len() as u64).to_le_bytes());
+                    change_key.extend(table.as_bytes());
+                    change_key.extend(key.iter());
+                    inner_batch.put_cf(delta_cf, change_key, change);
                 }
             }
         }
@@ -195,6 +467,36 @@ fn keys(&self, tid: u8) -> Vec<atomo::batch::BoxedVec> {
             .collect()
     }
 
+    fn range<'a>(&'a self, tid: u8, from: Bound<&[u8]>, to: Bound<&[u8]>) -> StorageIterator<'a> {
+        let cf = self.db.cf_handle(&self.columns[tid as usize]).unwrap();
+        // RocksDB only supports an inclusive lower and an exclusive upper bound, the other kinds
+        // of bounds are checked on the returned entries.
+        let mut options = ReadOptions::default();
+        let mut skip = None;
+        let mut last = None;
+        match from {
+            Bound::Included(key) => options.set_iterate_lower_bound(key),
+            Bound::Excluded(key) => {
+                options.set_iterate_lower_bound(key);
+                skip = Some(key.to_vec());
+            },
+            Bound::Unbounded => {},
+        }
+        match to {
+            Bound::Included(key) => last = Some(key.to_vec()),
+            Bound::Excluded(key) => options.set_iterate_upper_bound(key),
+            Bound::Unbounded => {},
+        }
+        // The iterator reads from an implicit snapshot of the database taken when it is created.
+        let iterator = self
+            .db
+            .iterator_cf_opt(&cf, options, IteratorMode::Start)
+            .map(|res| res.expect("failed to get entry from column family iterator"))
+            .skip_while(move |(key, _)| skip.as_deref() == Some(&key[..]))
+            .take_while(move |(key, _)| last.as_deref().map_or(true, |last| key[..] <= *last));
+        Box::new(iterator)
+    }
+
     fn get(&self, tid: u8, key: &[u8]) -> Option<Vec<u8>> {
         let cf = self.db.cf_handle(&self.columns[tid as usize]).unwrap();
         self.db
@@ -220,9 +522,10 @@ mod tests {
     use std::path::PathBuf;
     use std::sync::Arc;
 
-    use rocksdb::Options;
+    use atomo::{DefaultSerdeBackend, SerdeBackend};
+    use rocksdb::{IteratorMode, Options};
 
-    use crate::{AtomoBuilderWithRocks, RocksBackend, RocksBackendBuilder};
+    use crate::{deserialize_delta, AtomoBuilderWithRocks, RocksBackend, RocksBackendBuilder};
 
     const TEST_PATH: &str = "test-rocksdb";
 
@@ -296,4 +599,293 @@ fn create_insert_and_query() {
         // cleanup
         std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
     }
+
+    #[test]
+    fn range_iteration() {
+        let path: PathBuf = "test-rocksdb-range".parse().unwrap();
+        if path.exists() {
+            std::fs::remove_dir_all(path.clone()).expect("failed to remove old rocksdb");
+        }
+
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let rocksdb = RocksBackendBuilder::new(path.clone()).with_options(options);
+        let mut db = AtomoBuilderWithRocks::<atomo::DefaultSerdeBackend>::new(rocksdb)
+            .with_table::<(u8, u8), u64>("test")
+            .build()
+            .unwrap();
+        let table_res = db.resolve::<(u8, u8), u64>("test");
+
+        db.run(|ctx| {
+            let mut table_ref = table_res.get(ctx);
+            for key in [(0, 1), (1, 0), (1, 1), (1, 255), (2, 0)] {
+                table_ref.insert(key, key.1 as u64);
+            }
+        });
+
+        db.query().run(|ctx| {
+            let mut table_ref = table_res.get(ctx);
+            table_ref.remove((1, 0));
+            table_ref.insert((1, 2), 2);
+
+            let keys: Vec<_> = table_ref.prefix(&1u8).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![(1, 1), (1, 2), (1, 255)]);
+            let keys: Vec<_> = table_ref
+                .range((
+                    std::ops::Bound::Excluded((0, 1)),
+                    std::ops::Bound::Included((1, 2)),
+                ))
+                .map(|(key, _)| key)
+                .collect();
+            assert_eq!(keys, vec![(1, 1), (1, 2)]);
+            assert_eq!(table_ref.range(..).count(), 5);
+        });
+
+        // cleanup
+        drop(db);
+        std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
+    }
+
+    #[test]
+    fn index_is_filled_on_open() {
+        let path: PathBuf = "test-rocksdb-index".parse().unwrap();
+        if path.exists() {
+            std::fs::remove_dir_all(path.clone()).expect("failed to remove old rocksdb");
+        }
+
+        let open = |with_index: bool| {
+            let mut options = Options::default();
+            options.create_if_missing(true);
+            options.create_missing_column_families(true);
+            let rocksdb = RocksBackendBuilder::new(path.clone()).with_options(options);
+            let builder = AtomoBuilderWithRocks::<atomo::DefaultSerdeBackend>::new(rocksdb)
+                .with_table::<u64, u64>("test");
+            let builder = if with_index {
+                builder.with_index("test", "parity", |value: &u64| value % 2)
+            } else {
+                builder
+            };
+            builder.build().unwrap()
+        };
+
+        let mut db = open(false);
+        db.run(|ctx| {
+            let mut table_ref = ctx.get_table::<u64, u64>("test");
+            for key in 0..5 {
+                table_ref.insert(key, key * 3);
+            }
+        });
+        drop(db);
+
+        let mut db = open(true);
+        db.query().run(|ctx| {
+            let table_ref = ctx.get_table::<u64, u64>("test");
+            assert_eq!(table_ref.get_by_index::<u64>("parity", 1), vec![1, 3]);
+        });
+        db.run(|ctx| {
+            let mut table_ref = ctx.get_table::<u64, u64>("test");
+            table_ref.insert(1, 2);
+        });
+        drop(db);
+
+        // Changes made while the database was opened without the index are picked up too.
+        let mut db = open(false);
+        db.run(|ctx| {
+            let mut table_ref = ctx.get_table::<u64, u64>("test");
+            table_ref.insert(3, 4);
+            table_ref.insert(5, 5);
+            table_ref.remove(4);
+        });
+        drop(db);
+
+        let db = open(true);
+        db.query().run(|ctx| {
+            let table_ref = ctx.get_table::<u64, u64>("test");
+            assert_eq!(table_ref.get_by_index::<u64>("parity", 1), vec![5]);
+            assert_eq!(table_ref.get_by_index::<u64>("parity", 0), vec![0, 1, 2, 3]);
+        });
+
+        // cleanup
+        drop(db);
+        std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
+    }
+
+    #[test]
+    fn state_tree_is_persisted() {
+        let path: PathBuf = "test-rocksdb-state-tree".parse().unwrap();
+        if path.exists() {
+            std::fs::remove_dir_all(path.clone()).expect("failed to remove old rocksdb");
+        }
+
+        let open = || {
+            let mut options = Options::default();
+            options.create_if_missing(true);
+            options.create_missing_column_families(true);
+            let rocksdb = RocksBackendBuilder::new(path.clone()).with_options(options);
+            AtomoBuilderWithRocks::<DefaultSerdeBackend>::new(rocksdb)
+                .with_table::<u64, u64>("test")
+                .with_state_tree()
+                .build()
+                .unwrap()
+        };
+
+        let mut db = open();
+        db.run(|ctx| {
+            let mut table_ref = ctx.get_table::<u64, u64>("test");
+            for key in 0..10 {
+                table_ref.insert(key, key * 3);
+            }
+        });
+        let root = db.state_root();
+        // The nodes of the tree are stored next to the data.
+        let storage = db.get_storage_backend_unsafe();
+        let cf = storage.db.cf_handle("#state_tree").unwrap();
+        assert!(storage.db.iterator_cf(&cf, IteratorMode::Start).next().is_some());
+        drop(db);
+
+        let db = open();
+        assert_eq!(db.state_root(), root);
+        let (value, proof) = db.get_with_proof::<u64, u64>("test", &4);
+        assert_eq!(value, Some(12));
+        assert!(proof.verify(
+            &root,
+            "test",
+            &DefaultSerdeBackend::serialize_key(&4u64),
+            Some(&DefaultSerdeBackend::serialize(&12u64))
+        ));
+
+        // cleanup
+        drop(db);
+        std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
+    }
+
+    #[test]
+    fn delta_checkpoints() {
+        let path: PathBuf = "test-rocksdb-deltas-1".parse().unwrap();
+        let new_path: PathBuf = "test-rocksdb-deltas-2".parse().unwrap();
+        for path in [&path, &new_path] {
+            if path.exists() {
+                std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
+            }
+        }
+
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let rocksdb = RocksBackendBuilder::new(path.clone())
+            .with_options(options.clone())
+            .with_delta_tracking();
+        let mut db = AtomoBuilderWithRocks::<DefaultSerdeBackend>::new(rocksdb)
+            .with_table::<u64, u64>("test")
+            .with_state_tree()
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table_ref = ctx.get_table::<u64, u64>("test");
+            for key in 0..10 {
+                table_ref.insert(key, key);
+            }
+        });
+        let checkpoint = db.get_storage_backend_unsafe().serialize();
+        let hash = *fleek_blake3::hash(&checkpoint).as_bytes();
+
+        // Take a delta for each of a few epochs, the deltas promise the root of the state tree.
+        let mut deltas = Vec::new();
+        for epoch in 1..4 {
+            db.run(|ctx| {
+                let mut table_ref = ctx.get_table::<u64, u64>("test");
+                table_ref.remove(epoch);
+                table_ref.insert(epoch * 10, epoch);
+                table_ref.insert(0, epoch);
+            });
+            let root = db.state_root();
+            let delta = db
+                .get_storage_backend_unsafe()
+                .serialize_delta(root)
+                .unwrap();
+            deltas.push((*fleek_blake3::hash(&delta).as_bytes(), delta));
+        }
+        let last_checkpoint = db.get_storage_backend_unsafe().serialize();
+
+        let open = |deltas: Vec<([u8; 32], Vec<u8>)>| {
+            let rocksdb = RocksBackendBuilder::new(new_path.clone())
+                .with_options(options.clone())
+                .from_checkpoint(hash, checkpoint.clone())
+                .with_checkpoint_deltas(deltas);
+            AtomoBuilderWithRocks::<DefaultSerdeBackend>::new(rocksdb)
+                .with_table::<u64, u64>("test")
+                .with_state_tree()
+                .build()
+        };
+
+        // The deltas only apply in order.
+        let mut swapped = deltas.clone();
+        swapped.swap(0, 1);
+        assert!(open(swapped).is_err());
+
+        let mut new_db = open(deltas.clone()).unwrap();
+        assert_eq!(
+            new_db.get_storage_backend_unsafe().serialize(),
+            last_checkpoint
+        );
+        let promised = deserialize_delta(&deltas[2].1).unwrap().result;
+        assert_eq!(new_db.state_root(), promised);
+
+        // Tracking is opt-in.
+        assert!(
+            new_db
+                .get_storage_backend_unsafe()
+                .serialize_delta([0; 32])
+                .is_err()
+        );
+        drop(new_db);
+
+        // The changes are kept across restarts.
+        db.run(|ctx| {
+            let mut table_ref = ctx.get_table::<u64, u64>("test");
+            table_ref.insert(100, 100);
+        });
+        drop(db);
+        let rocksdb = RocksBackendBuilder::new(path.clone())
+            .with_options(options.clone())
+            .with_delta_tracking();
+        let mut db = AtomoBuilderWithRocks::<DefaultSerdeBackend>::new(rocksdb)
+            .with_table::<u64, u64>("test")
+            .with_state_tree()
+            .build()
+            .unwrap();
+        let root = db.state_root();
+        let delta = db
+            .get_storage_backend_unsafe()
+            .serialize_delta(root)
+            .unwrap();
+        let delta = (*fleek_blake3::hash(&delta).as_bytes(), delta);
+
+        // The deltas apply to an existing database too, which is left untouched by a bad one.
+        let open_existing = |deltas: Vec<([u8; 32], Vec<u8>)>| {
+            let rocksdb = RocksBackendBuilder::new(new_path.clone())
+                .with_options(options.clone())
+                .with_checkpoint_deltas(deltas);
+            AtomoBuilderWithRocks::<DefaultSerdeBackend>::new(rocksdb)
+                .with_table::<u64, u64>("test")
+                .with_state_tree()
+                .build()
+        };
+        let mut bad_delta = delta.clone();
+        bad_delta.0[0] ^= 1;
+        assert!(open_existing(vec![bad_delta]).is_err());
+        let mut new_db = open_existing(vec![delta]).unwrap();
+        assert_eq!(
+            new_db.get_storage_backend_unsafe().serialize(),
+            db.get_storage_backend_unsafe().serialize()
+        );
+
+        // cleanup
+        drop(db);
+        drop(new_db);
+        std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
+        std::fs::remove_dir_all(new_path).expect("failed to remove old rocksdb");
+    }
 }
@@ -3,10 +3,28 @@
 
 use anyhow::{anyhow, Context, Result};
 use fleek_blake3 as blake3;
-use rocksdb::{ColumnFamilyDescriptor, IteratorMode, Options, DB};
+use rocksdb::{ColumnFamilyDescriptor, IteratorMode, Options, Snapshot, WriteBatch, DB};
 
 type Entry = (Box<[u8]>, Box<[u8]>);
 
+/// The changes to the entries of a table, [`None`] means the key was removed.
+pub type TableChanges = BTreeMap<Box<[u8]>, Option<Box<[u8]>>>;
+
+/// The changes to a database between two checkpoints. A full checkpoint is identified by the hash
+/// of the serialization of the database (see [`serialize_db`]) and the state after a delta by the
+/// hash promised by the delta, so a delta can be applied to a database built from any checkpoint
+/// or delta whose resulting state has its base hash.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Delta {
+    /// The hash of the state the delta applies to.
+    pub base: [u8; 32],
+    /// The hash of the state after the delta is applied, as maintained by the owner of the
+    /// database (see [`crate::RocksBackend::serialize_delta`]).
+    pub result: [u8; 32],
+    /// The changes to each table by its name, tables without changes are left out.
+    pub tables: BTreeMap<String, TableChanges>,
+}
+
 pub fn build_db_from_checkpoint(
     path: &Path,
     hash: [u8; 32],
@@ -46,28 +64,206 @@ pub fn build_db_from_checkpoint(
 /// [num_tables][table1 name length][table1 name bytes][table1 bytes][table2 name length][table2
 /// name bytes][table2 bytes]...
 pub fn serialize_db(db: &DB, table_names: &[String]) -> Result<Vec<u8>> {
-    let mut table_names_sort = table_names.to_vec();
-    table_names_sort.sort();
+    serialize_snapshot(db, &db.snapshot(), table_names)
+}
 
-    let snapshot = db.snapshot();
+/// Returns the hash of the serialization of a RocksDb database, which is the hash of its
+/// checkpoint, without keeping all of the serialized bytes in memory.
+pub fn hash_db(db: &DB, table_names: &[String]) -> Result<[u8; 32]> {
+    hash_snapshot(db, &db.snapshot(), table_names)
+}
 
+/// Serializes a snapshot of a RocksDb database, see [`serialize_db`].
+pub(crate) fn serialize_snapshot(
+    db: &DB,
+    snapshot: &Snapshot,
+    table_names: &[String],
+) -> Result<Vec<u8>> {
     let mut bytes = Vec::new();
+    write_snapshot(db, snapshot, table_names, |chunk| bytes.extend(chunk))?;
+    Ok(bytes)
+}
+
+/// Returns the hash of the serialization of a snapshot of a RocksDb database, see [`hash_db`].
+pub(crate) fn hash_snapshot(
+    db: &DB,
+    snapshot: &Snapshot,
+    table_names: &[String],
+) -> Result<[u8; 32]> {
+    let mut hasher = blake3::Hasher::new();
+    write_snapshot(db, snapshot, table_names, |chunk| {
+        hasher.update(chunk);
+    })?;
+    Ok(*hasher.finalize().as_bytes())
+}
+
+/// Writes the serialization of a snapshot of a RocksDb database to the given sink, one table at
+/// a time.
+fn write_snapshot(
+    db: &DB,
+    snapshot: &Snapshot,
+    table_names: &[String],
+    mut write: impl FnMut(&[u8]),
+) -> Result<()> {
+    let mut table_names_sort = table_names.to_vec();
+    table_names_sort.sort();
+
     let num_tables = (table_names_sort.len() as u64).to_le_bytes();
-    bytes.extend(&num_tables);
+    write(&num_tables);
 
     for table_name in table_names_sort {
         let table_name_len = (table_name.len() as u64).to_le_bytes();
-        bytes.extend(&table_name_len);
-        bytes.extend(table_name.as_bytes());
+        write(&table_name_len);
+        write(table_name.as_bytes());
 
         let cf = db
             .cf_handle(&table_name)
             .ok_or(anyhow!("Unknown table name"))?;
         let table_iter = snapshot.iterator_cf(&cf, IteratorMode::Start);
         let table_bytes = serialize_table(table_iter.flatten());
-        bytes.extend(&table_bytes);
+        write(&table_bytes);
+    }
+    Ok(())
+}
+
+/// Applies a chain of serialized deltas, each with the hash of its bytes, to a RocksDb database
+/// and returns the hash of the new state promised by the last delta. The chain has to start at
+/// the given hash if there is one, and every delta has to start at the state promised by the
+/// previous one.
+///
+/// The promised hash is not derived from the database alone, so it is up to the owner of the
+/// database to check that the new state has it. If applying the deltas fails the changes are not
+/// reverted, so the deltas should be applied to a copy of the database.
+pub fn apply_deltas(
+    db: &DB,
+    table_names: &[String],
+    base: Option<[u8; 32]>,
+    deltas: &[([u8; 32], Vec<u8>)],
+) -> Result<Option<[u8; 32]>> {
+    let mut hash = base;
+    let mut batch = WriteBatch::default();
+    for (delta_hash, delta) in deltas {
+        if blake3::hash(delta).as_bytes() != delta_hash {
+            return Err(anyhow!("Failed to verify delta hash"));
+        }
+        let delta = deserialize_delta(delta)?;
+        if hash.map_or(false, |hash| hash != delta.base) {
+            return Err(anyhow!("Delta does not apply to the current state"));
+        }
+        hash = Some(delta.result);
+
+        for (table_name, changes) in &delta.tables {
+            if !table_names.contains(table_name) {
+                return Err(anyhow!("Unknown table name"));
+            }
+            let cf = db.cf_handle(table_name).context("Unknown table name")?;
+            for (key, value) in changes {
+                match value {
+                    Some(value) => batch.put_cf(&cf, key, value),
+                    None => batch.delete_cf(&cf, key),
+                }
+            }
+        }
+    }
+    db.write(batch)?;
+    Ok(hash)
+}
+
+/// Serializes a delta into a stream of bytes.
+/// The serialization format is:
+/// [base hash][result hash][num tables][table1 name length][table1 name bytes][table1 num
+/// changes][key1 length][key1 bytes][0 if key1 is removed, 1 otherwise][value1 length][value1
+/// bytes]...
+///
+/// The value length and bytes are only present for the keys that are not removed.
+pub fn serialize_delta(delta: &Delta) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(&delta.base);
+    bytes.extend(&delta.result);
+    bytes.extend((delta.tables.len() as u64).to_le_bytes());
+    for (table_name, changes) in &delta.tables {
+        bytes.extend((table_name.len() as u64).to_le_bytes());
+        bytes.extend(table_name.as_bytes());
+        bytes.extend((changes.len() as u64).to_le_bytes());
+        for (key, value) in changes {
+            bytes.extend((key.len() as u64).to_le_bytes());
+            bytes.extend(key.as_ref());
+            match value {
+                Some(value) => {
+                    bytes.push(1);
+                    bytes.extend((value.len() as u64).to_le_bytes());
+                    bytes.extend(value.as_ref());
+                },
+                None => bytes.push(0),
+            }
+        }
+    }
+    bytes
+}
+
+/// Deserializes a delta from a stream of bytes. Unlike the checkpoints every length is checked,
+/// since the deltas are meant to be received from other nodes.
+pub fn deserialize_delta(bytes: &[u8]) -> Result<Delta> {
+    let mut reader = Reader { bytes, pointer: 0 };
+    let mut delta = Delta {
+        base: reader.take(32)?.try_into()?,
+        result: reader.take(32)?.try_into()?,
+        tables: BTreeMap::new(),
+    };
+    for _ in 0..reader.read_u64()? {
+        let table_name_len = reader.read_len()?;
+        let table_name = String::from_utf8(reader.take(table_name_len)?.to_owned())?;
+        let mut changes = TableChanges::new();
+        for _ in 0..reader.read_u64()? {
+            let key_length = reader.read_len()?;
+            let key = reader.take(key_length)?.into();
+            let value = match reader.take(1)?[0] {
+                0 => None,
+                1 => {
+                    let value_length = reader.read_len()?;
+                    Some(reader.take(value_length)?.into())
+                },
+                _ => return Err(anyhow!("Invalid change in delta")),
+            };
+            if changes.insert(key, value).is_some() {
+                return Err(anyhow!("Duplicate key in delta"));
+            }
+        }
+        if delta.tables.insert(table_name, changes).is_some() {
+            return Err(anyhow!("Duplicate table name in delta"));
+        }
+    }
+    if reader.pointer != bytes.len() {
+        return Err(anyhow!("Unexpected bytes at the end of delta"));
+    }
+    Ok(delta)
+}
+
+/// A cursor over a stream of bytes that fails instead of reading out of bounds.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pointer: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pointer
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .context("Unexpected end of delta")?;
+        let bytes = &self.bytes[self.pointer..end];
+        self.pointer = end;
+        Ok(bytes)
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        Ok(usize::try_from(self.read_u64()?)?)
     }
-    Ok(bytes)
 }
 
 /// Deserializes a RocksDb database from a stream of bytes.
@@ -137,12 +333,18 @@ mod tests {
     use rocksdb::{ColumnFamilyDescriptor, Options, DB};
 
     use super::{
+        apply_deltas,
         build_db_from_checkpoint,
         deserialize_db,
+        deserialize_delta,
         deserialize_table,
+        hash_db,
         serialize_db,
+        serialize_delta,
         serialize_table,
+        Delta,
         Entry,
+        TableChanges,
     };
 
     fn generate_random_bytes(length: usize) -> Box<[u8]> {
@@ -281,4 +483,130 @@ fn test_build_db_from_checkpoint() {
         std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
         std::fs::remove_dir_all(new_path).expect("failed to remove old rocksdb");
     }
+
+    #[test]
+    fn test_serialize_deserialize_delta() {
+        let mut delta = Delta {
+            base: [1; 32],
+            result: [2; 32],
+            tables: BTreeMap::new(),
+        };
+        for table_name in ["table1", "table2"] {
+            let changes: TableChanges = build_random_table(100, 4..32, 4..32)
+                .into_iter()
+                .enumerate()
+                .map(|(i, (key, value))| (key, (i % 3 != 0).then_some(value)))
+                .collect();
+            delta.tables.insert(table_name.to_owned(), changes);
+        }
+
+        let bytes = serialize_delta(&delta);
+        assert_eq!(deserialize_delta(&bytes).unwrap(), delta);
+
+        // Truncated or padded deltas are rejected.
+        assert!(deserialize_delta(&bytes[..bytes.len() - 1]).is_err());
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert!(deserialize_delta(&padded).is_err());
+    }
+
+    #[test]
+    fn test_apply_deltas() {
+        let path = std::env::temp_dir().join("lightning_test_rocksdb_delta_1");
+        let new_path = std::env::temp_dir().join("lightning_test_rocksdb_delta_2");
+        for path in [&path, &new_path] {
+            if path.exists() {
+                std::fs::remove_dir_all(path).expect("failed to remove old rocksdb for test");
+            }
+        }
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let columns = vec!["table2".to_owned(), "table1".to_owned()];
+        let cf_iter: Vec<_> = columns
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(name.to_owned(), options.clone()))
+            .collect();
+
+        // Build a database and a copy of it from a checkpoint
+        let db = DB::open_cf_descriptors(&options, &path, cf_iter).unwrap();
+        let mut keys = Vec::new();
+        for col in &columns {
+            let cf = db.cf_handle(col).expect("Unknown table name");
+            for (key, value) in build_random_table(100, 4..16, 4..32) {
+                keys.push((col.clone(), key.clone()));
+                db.put_cf(&cf, key, value).unwrap();
+            }
+        }
+        let checkpoint = serialize_db(&db, &columns).expect("Failed to serialize db");
+        let base = *blake3::hash(&checkpoint).as_bytes();
+        assert_eq!(hash_db(&db, &columns).unwrap(), base);
+        let (new_db, _) = build_db_from_checkpoint(&new_path, base, &checkpoint, options)
+            .expect("Failed to build db from checkpoint");
+
+        // Change the first database in two steps and record the changes of each
+        let mut removals: BTreeMap<String, TableChanges> = BTreeMap::new();
+        for (col, key) in keys.iter().step_by(3) {
+            let cf = db.cf_handle(col).expect("Unknown table name");
+            db.delete_cf(&cf, key).unwrap();
+            removals
+                .entry(col.clone())
+                .or_default()
+                .insert(key.clone(), None);
+        }
+        let middle = hash_db(&db, &columns).unwrap();
+        let mut inserts: BTreeMap<String, TableChanges> = BTreeMap::new();
+        for (key, value) in build_random_table(50, 4..16, 4..32) {
+            let cf = db.cf_handle("table1").expect("Unknown table name");
+            db.put_cf(&cf, &key, &value).unwrap();
+            inserts
+                .entry("table1".to_owned())
+                .or_default()
+                .insert(key, Some(value));
+        }
+        let result = hash_db(&db, &columns).unwrap();
+        let first = serialize_delta(&Delta {
+            base,
+            result: middle,
+            tables: removals,
+        });
+        let first = (*blake3::hash(&first).as_bytes(), first);
+        let second = serialize_delta(&Delta {
+            base: middle,
+            result,
+            tables: inserts,
+        });
+        let second = (*blake3::hash(&second).as_bytes(), second);
+
+        // A delta that does not match its hash is rejected
+        let mut bad_hash = first.clone();
+        bad_hash.0[0] ^= 1;
+        assert!(apply_deltas(&new_db, &columns, Some(base), &[bad_hash]).is_err());
+
+        // The deltas only apply to their base state and in order
+        assert!(apply_deltas(&new_db, &columns, Some([0; 32]), &[first.clone()]).is_err());
+        assert!(
+            apply_deltas(
+                &new_db,
+                &columns,
+                Some(base),
+                &[second.clone(), first.clone()]
+            )
+            .is_err()
+        );
+        assert_eq!(hash_db(&new_db, &columns).unwrap(), base);
+
+        assert_eq!(
+            apply_deltas(&new_db, &columns, Some(base), &[first, second]).unwrap(),
+            Some(result)
+        );
+        assert_eq!(
+            serialize_db(&db, &columns).unwrap(),
+            serialize_db(&new_db, &columns).unwrap()
+        );
+
+        std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
+        std::fs::remove_dir_all(new_path).expect("failed to remove old rocksdb");
+    }
 }
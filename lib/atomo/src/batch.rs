@@ -17,6 +17,17 @@ pub enum Operation {
     Insert(BoxedVec),
 }
 
+impl Operation {
+    /// Returns the value after the change, [`None`] if the key was removed.
+    #[inline(always)]
+    pub fn value(&self) -> Option<BoxedVec> {
+        match self {
+            Operation::Remove => None,
+            Operation::Insert(value) => Some(value.clone()),
+        }
+    }
+}
+
 impl VerticalBatch {
     /// Returns a new empty vertical batch with the given size. The
     /// size can be used for the number of tables.
@@ -1,16 +1,20 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
 use std::hash::Hash;
 use std::iter::Extend;
+use std::ops::Bound;
 use std::sync::Arc;
 
+use fxhash::FxHashSet;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::batch::{BoxedVec, Operation, VerticalBatch};
 use crate::db::{Atomo, TableId, UpdatePerm};
 use crate::inner::AtomoInner;
 use crate::serder::SerdeBackend;
+use crate::state_tree::{state_key, StateTree, STATE_TREE_TABLE};
 use crate::storage::{InMemoryStorage, StorageBackendConstructor};
-use crate::table::TableMeta;
+use crate::table::{index_entry, IndexMeta, TableMeta};
 use crate::{DefaultSerdeBackend, StorageBackend};
 
 /// The builder API to use for opening an [`Atomo`] database.
@@ -95,6 +99,116 @@ pub fn enable_iter(mut self, name: &str) -> Self {
         panic!("Table {name} is not defined.");
     }
 
+    /// Add a secondary index with the given name to the provided table. The index maps the key
+    /// returned by `extractor` for every value of the table to the keys holding that value, and
+    /// can be read using [`crate::TableRef::get_by_index`].
+    ///
+    /// The index is kept consistent with every write to the table and is persisted in a table of
+    /// its own, when the index is added to a table that already has data it is filled upon
+    /// opening the database.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the provided table name is not already defined using a prior call to
+    /// `with_table` with the value type `V`, or if the table already has an index with the
+    /// given name.
+    #[must_use = "Builder is incomplete."]
+    pub fn with_index<V, I>(
+        mut self,
+        table: &str,
+        name: impl ToString,
+        extractor: impl Fn(&V) -> I + Send + Sync + 'static,
+    ) -> Self
+    where
+        V: Serialize + DeserializeOwned + Any,
+        I: Serialize + Any,
+    {
+        let Some(table_id) = self.atomo.table_name_to_id.get(table).copied() else {
+            panic!("Table {table} is not defined.");
+        };
+
+        let meta = &self.atomo.tables[table_id as usize];
+        let v_str = std::any::type_name::<V>();
+        assert_eq!(
+            meta.v_id,
+            TypeId::of::<V>(),
+            "Could not resolve table '{table}' with value type '{v_str}'."
+        );
+
+        let name = name.to_string();
+        if meta.indexes.iter().any(|index| index.name == name) {
+            panic!("Index {name} of table {table} is already defined.");
+        }
+
+        let index_table = format!("{table}#{name}");
+        let tid = self.atomo.tables.len() as TableId;
+        self.with_table_internal_non_generic_part(index_table.clone());
+        self.atomo.tables.push(TableMeta::internal(index_table));
+        self.atomo.tables[table_id as usize]
+            .indexes
+            .push(IndexMeta {
+                name,
+                tid,
+                i_id: TypeId::of::<I>(),
+                extractor: Box::new(move |value| {
+                    S::serialize_key(&extractor(&S::deserialize(value)))
+                }),
+            });
+        self
+    }
+
+    /// Keep the state of the last `size` versions saved with [`Atomo::save_version`] around, so
+    /// they can be queried using [`Atomo::at_version`]. The history is kept in memory as the
+    /// inverse of every change since the oldest saved version, and is not persisted.
+    ///
+    /// By default no history is kept.
+    #[must_use = "Builder is incomplete."]
+    pub fn with_history(mut self, size: usize) -> Self {
+        self.atomo.history_size = size;
+        self
+    }
+
+    /// Maintain a sparse Merkle tree over the entries of every table, which commits to the
+    /// state using a single root and can prove the value of any key or its absence. See
+    /// [`Atomo::state_root`] and [`Atomo::get_with_proof`].
+    ///
+    /// The nodes of the tree are persisted in a table of their own and are committed together
+    /// with the data. When the tree is added to a database that already has data it is filled
+    /// upon opening the database, after that the tree is only kept up to date by the writes made
+    /// while it is enabled. The entries of the indexes and of the tables left out using
+    /// [`AtomoBuilder::without_state_tree`] are not part of the tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the state tree is already enabled.
+    #[must_use = "Builder is incomplete."]
+    pub fn with_state_tree(mut self) -> Self {
+        let tid = self.atomo.tables.len() as TableId;
+        self.with_table_internal_non_generic_part(STATE_TREE_TABLE.to_owned());
+        self.atomo
+            .tables
+            .push(TableMeta::internal(STATE_TREE_TABLE.to_owned()));
+        self.atomo.state_tree = Some(tid);
+        self
+    }
+
+    /// Leave the provided table out of the state tree. Use this for the data that is local to
+    /// the node and can differ between nodes that agree on the state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the provided table name is not already defined using a prior call
+    /// to `with_table`.
+    #[must_use = "Builder is incomplete."]
+    pub fn without_state_tree(mut self, name: &str) -> Self {
+        if let Some(index) = self.atomo.table_name_to_id.get(name) {
+            self.atomo.tables[*index as usize].in_state_tree = false;
+            return self;
+        }
+
+        panic!("Table {name} is not defined.");
+    }
+
     /// Finish the construction and returns an [`Atomo`] with [`UpdatePerm`] permission.
     #[must_use = "Creating a Atomo without using it is probably a mistake."]
     pub fn build(self) -> Result<Atomo<UpdatePerm, B::Storage, S>, B::Error> {
@@ -127,6 +241,52 @@ pub(crate) fn build_inner(mut self) -> Result<AtomoInner<B::Storage, S>, B::Erro
             });
         }
 
+        // Reconcile the indexes with their tables. An index is out of sync if the database was
+        // opened without it while its table changed, so the missing entries of every index are
+        // added and the stale ones are removed.
+        let mut batch = VerticalBatch::new(self.atomo.tables.len());
+        for (tid, meta) in self.atomo.tables.iter().enumerate() {
+            for index in &meta.indexes {
+                let mut expected: FxHashSet<BoxedVec> = storage
+                    .range(tid as TableId, Bound::Unbounded, Bound::Unbounded)
+                    .map(|(key, value)| index_entry(&(index.extractor)(&value), &key))
+                    .collect();
+                let entries = batch.get_mut(index.tid as usize);
+                for (entry, _) in storage.range(index.tid, Bound::Unbounded, Bound::Unbounded) {
+                    if !expected.remove(&entry) {
+                        entries.insert(entry, Operation::Remove);
+                    }
+                }
+                for entry in expected {
+                    entries.insert(entry, Operation::Insert(BoxedVec::default()));
+                }
+            }
+        }
+        storage.commit(batch);
+
+        // Fill the state tree if it was just added to the database.
+        if let Some(tree_tid) = self.atomo.state_tree {
+            if storage
+                .range(tree_tid, Bound::Unbounded, Bound::Unbounded)
+                .next()
+                .is_none()
+            {
+                let mut batch = VerticalBatch::new(self.atomo.tables.len());
+                let mut state_tree = StateTree::new(&storage, tree_tid);
+                for (tid, meta) in self.atomo.tables.iter().enumerate() {
+                    if !meta.in_state_tree {
+                        continue;
+                    }
+                    let tid = tid as TableId;
+                    for (key, value) in storage.range(tid, Bound::Unbounded, Bound::Unbounded) {
+                        state_tree.insert(state_key(&meta.name, &key), &value);
+                    }
+                }
+                state_tree.write(batch.get_mut(tree_tid as usize));
+                storage.commit(batch);
+            }
+        }
+
         Ok(self.atomo.swap_persistance(storage))
     }
 }
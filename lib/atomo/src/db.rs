@@ -6,8 +6,12 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::batch::VerticalBatch;
 use crate::inner::AtomoInner;
+use crate::keys::VerticalKeys;
 use crate::serder::SerdeBackend;
+use crate::snapshot::Snapshot;
+use crate::state_tree::StateProof;
 use crate::storage::{InMemoryStorage, StorageBackend};
 use crate::table::{ResolvedTableReference, TableSelector};
 use crate::DefaultSerdeBackend;
@@ -38,13 +42,19 @@
 /// you can clone it anytime (and the clone implementation is rather cheap.)
 pub struct Atomo<O, B: StorageBackend = InMemoryStorage, S: SerdeBackend = DefaultSerdeBackend> {
     inner: Arc<AtomoInner<B, S>>,
+    /// The snapshot of a past version this instance is pinned to, see [`Atomo::at_version`].
+    version: Option<Snapshot<VerticalBatch, VerticalKeys>>,
     ownership: PhantomData<O>,
 }
 
 // only implement the clone for the query permission.
 impl<B: StorageBackend, S: SerdeBackend> Clone for Atomo<QueryPerm, B, S> {
     fn clone(&self) -> Self {
-        Self::new(self.inner.clone())
+        Self {
+            inner: self.inner.clone(),
+            version: self.version.clone(),
+            ownership: PhantomData,
+        }
     }
 }
 
@@ -53,6 +63,7 @@ impl<O, B: StorageBackend, S: SerdeBackend> Atomo<O, B, S> {
     pub(crate) fn new(inner: Arc<AtomoInner<B, S>>) -> Self {
         Self {
             inner,
+            version: None,
             ownership: PhantomData,
         }
     }
@@ -76,6 +87,33 @@ pub fn resolve<K, V>(&self, name: impl AsRef<str>) -> ResolvedTableReference<K,
     {
         self.inner.resolve::<K, V>(name)
     }
+
+    /// Returns the root of the state tree as of the latest committed version.
+    ///
+    /// # Panics
+    ///
+    /// If the state tree is not enabled. See the documentation for
+    /// [`crate::AtomoBuilder::with_state_tree`] for more information.
+    pub fn state_root(&self) -> [u8; 32] {
+        self.inner.state_root()
+    }
+
+    /// Returns the value of the key in the table with the given name as of the latest committed
+    /// version, together with the proof of the value (or its absence) against the state root of
+    /// that version.
+    ///
+    /// # Panics
+    ///
+    /// 1. If the state tree is not enabled.
+    /// 2. If the table with the provided name does not exists or has other key-value types.
+    /// 3. If the table is not part of the state tree.
+    pub fn get_with_proof<K, V>(&self, name: impl AsRef<str>, key: &K) -> (Option<V>, StateProof)
+    where
+        K: Hash + Eq + Serialize + DeserializeOwned + Any,
+        V: Serialize + DeserializeOwned + Any,
+    {
+        self.inner.get_with_proof(name, key)
+    }
 }
 
 impl<B: StorageBackend, S: SerdeBackend> Atomo<QueryPerm, B, S> {
@@ -84,9 +122,25 @@ pub fn run<F, R>(&self, query: F) -> R
     where
         F: FnOnce(&mut TableSelector<B, S>) -> R,
     {
-        let mut selector = TableSelector::new(self.inner.clone());
+        let mut selector = match &self.version {
+            Some(snapshot) => TableSelector::with_snapshot(self.inner.clone(), snapshot.clone()),
+            None => TableSelector::new(self.inner.clone()),
+        };
         query(&mut selector)
     }
+
+    /// Returns a query end that views the data as it was when the given version was saved using
+    /// [`Atomo::save_version`]. Returns [`None`] if the version is not part of the history (see
+    /// [`crate::AtomoBuilder::with_history`]).
+    ///
+    /// The returned instance keeps the version alive until it is dropped.
+    pub fn at_version(&self, version: u64) -> Option<Self> {
+        self.inner.get_version(version).map(|snapshot| Self {
+            inner: self.inner.clone(),
+            version: Some(snapshot),
+            ownership: PhantomData,
+        })
+    }
 }
 
 impl<B: StorageBackend, S: SerdeBackend> Atomo<UpdatePerm, B, S> {
@@ -107,6 +161,12 @@ pub fn run<F, R>(&mut self, mutation: F) -> R
         response
     }
 
+    /// Save the current state in the history under the given version so it can be queried later
+    /// using [`Atomo::at_version`]. Does nothing if the history is not enabled.
+    pub fn save_version(&mut self, version: u64) {
+        self.inner.save_version(version);
+    }
+
     /// Return a reference to the storage backend. Modifying the state directly and going behind
     /// Atomo will break Atomo.
     ///
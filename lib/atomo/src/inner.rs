@@ -1,7 +1,9 @@
 use std::any::{Any, TypeId};
+use std::collections::VecDeque;
 use std::hash::Hash;
 use std::marker::PhantomData;
 use std::sync::atomic::AtomicUsize;
+use std::sync::{Mutex, RwLock};
 
 use fxhash::FxHashMap;
 use serde::de::DeserializeOwned;
@@ -11,7 +13,8 @@
 use crate::db::TableId;
 use crate::keys::VerticalKeys;
 use crate::serder::SerdeBackend;
-use crate::snapshot::SnapshotList;
+use crate::snapshot::{Snapshot, SnapshotList};
+use crate::state_tree::{state_key, StateProof, StateTree};
 use crate::storage::StorageBackend;
 use crate::table::{ResolvedTableReference, TableMeta};
 
@@ -28,6 +31,16 @@ pub struct AtomoInner<B, S: SerdeBackend> {
     pub table_name_to_id: FxHashMap<String, TableId>,
     /// The linked list of the old-snapshots.
     pub snapshot_list: SnapshotList<VerticalBatch, VerticalKeys>,
+    /// The number of saved versions that are kept in the history.
+    pub history_size: usize,
+    /// The snapshots of the saved versions, ordered from the oldest to the newest.
+    pub history: Mutex<VecDeque<(u64, Snapshot<VerticalBatch, VerticalKeys>)>>,
+    /// The table holding the nodes of the Merkle tree over the entries of the tables, if it is
+    /// enabled.
+    pub state_tree: Option<TableId>,
+    /// Held while the state tree is changed, so the readers of the tree see the nodes of a
+    /// single version.
+    pub state_tree_lock: RwLock<()>,
     serde: PhantomData<S>,
 }
 
@@ -40,6 +53,10 @@ pub fn empty() -> Self {
             tables: Vec::new(),
             table_name_to_id: FxHashMap::default(),
             snapshot_list: SnapshotList::default(),
+            history_size: 0,
+            history: Mutex::new(VecDeque::new()),
+            state_tree: None,
+            state_tree_lock: RwLock::new(()),
             serde: PhantomData,
         }
     }
@@ -51,6 +68,10 @@ pub fn swap_persistance<P: StorageBackend>(self, persistence: P) -> AtomoInner<P
             tables: self.tables,
             table_name_to_id: self.table_name_to_id,
             snapshot_list: self.snapshot_list,
+            history_size: self.history_size,
+            history: self.history,
+            state_tree: self.state_tree,
+            state_tree_lock: self.state_tree_lock,
             serde: PhantomData,
         }
     }
@@ -58,10 +79,84 @@ pub fn swap_persistance<P: StorageBackend>(self, persistence: P) -> AtomoInner<P
 
 impl<B: StorageBackend, S: SerdeBackend> AtomoInner<B, S> {
     /// Performs a batch of operations on the persistence layer.
-    pub fn perform_batch(&self, batch: VerticalBatch) {
+    pub fn perform_batch(&self, mut batch: VerticalBatch) {
+        let Some(tree_tid) = self.state_tree else {
+            self.persistence.commit(batch);
+            return;
+        };
+
+        // The lock is held until the batch is committed so the tree always matches the persisted
+        // data for the readers of the proofs.
+        let _guard = self
+            .state_tree_lock
+            .write()
+            .expect("Could not acquire the lock");
+        let mut state_tree = StateTree::new(&self.persistence, tree_tid);
+        for (tid, meta) in self.tables.iter().enumerate() {
+            if !meta.in_state_tree {
+                continue;
+            }
+            for (key, operation) in batch.get(tid).iter() {
+                let path = state_key(&meta.name, key);
+                match operation {
+                    Operation::Insert(value) => state_tree.insert(path, value),
+                    Operation::Remove => state_tree.remove(path),
+                }
+            }
+        }
+        // The nodes are committed in the same batch as the data.
+        state_tree.write(batch.get_mut(tree_tid as usize));
         self.persistence.commit(batch);
     }
 
+    /// Returns the root of the state tree.
+    ///
+    /// # Panics
+    ///
+    /// If the state tree is not enabled.
+    pub fn state_root(&self) -> [u8; 32] {
+        let _guard = self
+            .state_tree_lock
+            .read()
+            .expect("Could not acquire the lock");
+        StateTree::new(&self.persistence, self.state_tree_tid()).root()
+    }
+
+    /// Returns the value of the key in the table with the given name as of the latest committed
+    /// version, together with the proof of it in the state tree.
+    ///
+    /// # Panics
+    ///
+    /// If the state tree is not enabled, the table can not be resolved or it is not part of the
+    /// state tree.
+    pub fn get_with_proof<K, V>(&self, name: impl AsRef<str>, key: &K) -> (Option<V>, StateProof)
+    where
+        K: Hash + Eq + Serialize + DeserializeOwned + Any,
+        V: Serialize + DeserializeOwned + Any,
+    {
+        let name = name.as_ref();
+        let tid = self.resolve::<K, V>(name).tid();
+        assert!(
+            self.tables[tid as usize].in_state_tree,
+            "Table {name} is not part of the state tree."
+        );
+        let key = S::serialize_key(key);
+        let _guard = self
+            .state_tree_lock
+            .read()
+            .expect("Could not acquire the lock");
+        let state_tree = StateTree::new(&self.persistence, self.state_tree_tid());
+        let value = self
+            .persistence
+            .get(tid, &key)
+            .map(|value| S::deserialize(&value));
+        (value, state_tree.prove(state_key(name, &key)))
+    }
+
+    fn state_tree_tid(&self) -> TableId {
+        self.state_tree.expect("State tree is not enabled.")
+    }
+
     /// Given the name of a table as an input string returns a [`ResolvedTableReference`].
     ///
     /// # Panics
@@ -102,6 +197,31 @@ pub fn resolve<K, V>(&self, name: impl AsRef<str>) -> ResolvedTableReference<K,
         ResolvedTableReference::<K, V>::new(self.id, index)
     }
 
+    /// Keep the current state in the history under the given version, dropping the oldest saved
+    /// version once there are more than `history_size` of them.
+    pub fn save_version(&self, version: u64) {
+        if self.history_size == 0 {
+            return;
+        }
+
+        let mut history = self.history.lock().expect("Could not acquire the lock");
+        history.push_back((version, self.snapshot_list.current()));
+        while history.len() > self.history_size {
+            history.pop_front();
+        }
+    }
+
+    /// Returns the snapshot of the state that was saved under the given version, if it is still
+    /// part of the history.
+    pub fn get_version(&self, version: u64) -> Option<Snapshot<VerticalBatch, VerticalKeys>> {
+        let history = self.history.lock().expect("Could not acquire the lock");
+        history
+            .iter()
+            .rev()
+            .find(|(v, _)| *v == version)
+            .map(|(_, snapshot)| snapshot.clone())
+    }
+
     /// Given a vertical batch (which we intend to commit) compute the inverse of the batch. The
     /// inverse of a batch is another batch that when executed reverts the changes.
     #[inline]
@@ -166,9 +286,11 @@ pub fn contains_key(&self, tid: TableId, key: &[u8]) -> bool {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use crate::batch::{Operation, VerticalBatch};
     use crate::storage::InMemoryStorage;
-    use crate::{AtomoBuilder, BincodeSerde};
+    use crate::{Atomo, AtomoBuilder, BincodeSerde, QueryPerm, SerdeBackend};
 
     #[test]
     fn resolve_valid_should_work() {
@@ -355,4 +477,458 @@ fn compute_inverse_on_empty_db() {
         assert_eq!(inner.get_raw(0, &[2]), None);
         assert_eq!(inner.get_raw(0, &[3]), None);
     }
+
+    #[test]
+    fn query_saved_versions() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<u8, usize>("TABLE")
+            .with_history(2)
+            .build()
+            .unwrap();
+        let query = db.query();
+
+        for version in 1..=3 {
+            db.run(|ctx| {
+                let mut table = ctx.get_table::<u8, usize>("TABLE");
+                table.insert(0, version as usize);
+                if version == 2 {
+                    table.insert(1, 2);
+                }
+                if version == 3 {
+                    table.remove(1);
+                }
+            });
+            db.save_version(version);
+        }
+
+        // The oldest version is evicted from the history.
+        assert!(query.at_version(1).is_none());
+        assert!(query.at_version(4).is_none());
+
+        let get = |query: &Atomo<QueryPerm, InMemoryStorage, BincodeSerde>, key: u8| {
+            query.run(|ctx| ctx.get_table::<u8, usize>("TABLE").get(key))
+        };
+
+        let v2 = query.at_version(2).unwrap();
+        assert_eq!(get(&v2, 0), Some(2));
+        assert_eq!(get(&v2, 1), Some(2));
+
+        let v3 = query.at_version(3).unwrap();
+        assert_eq!(get(&v3, 0), Some(3));
+        assert_eq!(get(&v3, 1), None);
+
+        // Changes after the version was saved are not visible.
+        db.run(|ctx| ctx.get_table::<u8, usize>("TABLE").insert(1, 4));
+        assert_eq!(get(&v2, 1), Some(2));
+        assert_eq!(get(&v3, 1), None);
+        assert_eq!(get(&query, 1), Some(4));
+    }
+
+    #[test]
+    fn table_changes() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<u8, usize>("TABLE")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, usize>("TABLE");
+            table.insert(0, 0);
+            table.insert(1, 1);
+        });
+
+        db.query().run(|ctx| {
+            let mut table = ctx.get_table::<u8, usize>("TABLE");
+            table.insert(0, 0);
+            table.insert(1, 2);
+            table.insert(2, 2);
+            table.remove(3);
+            table.remove(0);
+            table.insert(0, 0);
+
+            let mut changes = table.changes();
+            changes.sort();
+            assert_eq!(changes, vec![(1, Some(2)), (2, Some(2))]);
+            assert_eq!(table.get(1), Some(2));
+            assert_eq!(table.get_committed(1), Some(1));
+            assert_eq!(table.get_committed(2), None);
+
+            table.remove(1);
+            let mut changes = table.changes();
+            changes.sort();
+            assert_eq!(changes, vec![(1, None), (2, Some(2))]);
+        });
+    }
+
+    #[test]
+    fn range_and_prefix_iteration() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<(u8, u8), usize>("TABLE")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<(u8, u8), usize>("TABLE");
+            for key in [(2, 0), (1, 1), (0, 2), (1, 255), (255, 255)] {
+                table.insert(key, key.0 as usize + key.1 as usize);
+            }
+        });
+        let query = db.query();
+
+        query.run(|ctx| {
+            let mut table = ctx.get_table::<(u8, u8), usize>("TABLE");
+            // Uncommitted changes are merged into the persisted entries.
+            table.insert((1, 0), 1);
+            table.remove((1, 1));
+            table.insert((2, 0), 0);
+
+            let entries: Vec<_> = table.range(..).collect();
+            assert_eq!(
+                entries,
+                vec![
+                    ((0, 2), 2),
+                    ((1, 0), 1),
+                    ((1, 255), 256),
+                    ((2, 0), 0),
+                    ((255, 255), 510)
+                ]
+            );
+            let keys: Vec<_> = table.range((1, 0)..(2, 0)).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![(1, 0), (1, 255)]);
+            let keys: Vec<_> = table.range((1, 1)..=(2, 0)).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![(1, 255), (2, 0)]);
+
+            let keys: Vec<_> = table.prefix(&1u8).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![(1, 0), (1, 255)]);
+            let keys: Vec<_> = table.prefix(&255u8).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![(255, 255)]);
+            assert_eq!(table.prefix(&3u8).count(), 0);
+        });
+
+        // A running query keeps seeing its version while the data is updated.
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let (updated_tx, updated_rx) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            query.run(|ctx| {
+                let table = ctx.get_table::<(u8, u8), usize>("TABLE");
+                started_tx.send(()).unwrap();
+                updated_rx.recv().unwrap();
+                let keys: Vec<_> = table.prefix(&1u8).map(|(key, _)| key).collect();
+                assert_eq!(keys, vec![(1, 1), (1, 255)]);
+            });
+            query.run(|ctx| {
+                let table = ctx.get_table::<(u8, u8), usize>("TABLE");
+                let keys: Vec<_> = table.prefix(&1u8).map(|(key, _)| key).collect();
+                assert_eq!(keys, vec![(1, 0), (1, 255)]);
+            });
+        });
+        started_rx.recv().unwrap();
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<(u8, u8), usize>("TABLE");
+            table.insert((1, 0), 1);
+            table.remove((1, 1));
+        });
+        updated_tx.send(()).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn range_of_integer_keys() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<u32, ()>("TABLE")
+            .with_table::<(u32, u64), ()>("PAIRS")
+            .enable_iter("TABLE")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u32, ()>("TABLE");
+            for key in [0, 1, 255, 256, 257, 511, 65536] {
+                table.insert(key, ());
+            }
+            let mut pairs = ctx.get_table::<(u32, u64), ()>("PAIRS");
+            for key in [(1, 256), (1, 1), (256, 0), (0, 257)] {
+                pairs.insert(key, ());
+            }
+        });
+
+        // The ranges cross byte boundaries of the keys, which only works if the order of the
+        // serialized keys is the order of the integers.
+        db.query().run(|ctx| {
+            let table = ctx.get_table::<u32, ()>("TABLE");
+            let keys: Vec<_> = table.range(255..257).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![255, 256]);
+            let keys: Vec<_> = table.range(256..).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![256, 257, 511, 65536]);
+            let keys: Vec<_> = table.range(..=256).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![0, 1, 255, 256]);
+            assert_eq!(
+                table.keys().collect::<Vec<_>>(),
+                vec![0, 1, 255, 256, 257, 511, 65536]
+            );
+
+            let pairs = ctx.get_table::<(u32, u64), ()>("PAIRS");
+            let keys: Vec<_> = pairs.range(..).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![(0, 257), (1, 1), (1, 256), (256, 0)]);
+            let keys: Vec<_> = pairs.prefix(&1u32).map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![(1, 1), (1, 256)]);
+        });
+    }
+
+    #[test]
+    fn nested_savepoints() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<u8, usize>("TABLE")
+            .enable_iter("TABLE")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, usize>("TABLE");
+            table.insert(0, 0);
+            table.insert(1, 1);
+        });
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, usize>("TABLE");
+            table.insert(2, 2);
+
+            let outer = ctx.savepoint();
+            table.insert(0, 10);
+            table.remove(2);
+
+            let inner = ctx.savepoint();
+            table.insert(3, 3);
+            table.remove(1);
+            ctx.rollback_to(inner);
+            assert_eq!(table.get(1), Some(1));
+            assert_eq!(table.get(3), None);
+
+            // The savepoint stays active after a rollback.
+            table.insert(4, 4);
+            ctx.release(inner);
+            assert_eq!(table.get(4), Some(4));
+
+            ctx.rollback_to(outer);
+            assert_eq!(table.get(0), Some(0));
+            assert_eq!(table.get(2), Some(2));
+            assert_eq!(table.get(4), None);
+            let mut keys: Vec<_> = table.keys().collect();
+            keys.sort();
+            assert_eq!(keys, vec![0, 1, 2]);
+
+            table.insert(5, 5);
+            ctx.release(outer);
+            let mut changes = table.changes();
+            changes.sort();
+            assert_eq!(changes, vec![(2, Some(2)), (5, Some(5))]);
+        });
+
+        db.query().run(|ctx| {
+            let table = ctx.get_table::<u8, usize>("TABLE");
+            let entries: Vec<_> = table.range(..).collect();
+            assert_eq!(entries, vec![(0, 0), (1, 1), (2, 2), (5, 5)]);
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn released_savepoint_should_panic() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<u8, usize>("TABLE")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let outer = ctx.savepoint();
+            let inner = ctx.savepoint();
+            ctx.release(outer);
+            ctx.rollback_to(inner);
+        });
+    }
+
+    #[test]
+    fn secondary_index() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<u8, (String, u64)>("NODES")
+            .with_index("NODES", "OWNER", |(owner, _): &(String, u64)| owner.clone())
+            .with_index("NODES", "LOCKED", |(_, locked): &(String, u64)| *locked)
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, (String, u64)>("NODES");
+            table.insert(0, ("alice".to_string(), 5));
+            table.insert(1, ("bob".to_string(), 5));
+            table.insert(2, ("alice".to_string(), 7));
+            assert_eq!(
+                table.get_by_index::<String>("OWNER", "alice".to_string()),
+                vec![0, 2]
+            );
+        });
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, (String, u64)>("NODES");
+            table.insert(0, ("bob".to_string(), 7));
+            table.remove(2);
+            assert_eq!(
+                table.get_by_index::<String>("OWNER", "alice".to_string()),
+                vec![]
+            );
+            assert_eq!(
+                table.get_by_index::<String>("OWNER", "bob".to_string()),
+                vec![0, 1]
+            );
+            assert_eq!(table.get_by_index::<u64>("LOCKED", 7), vec![0]);
+
+            // The indexes follow the rollback of a savepoint.
+            let savepoint = ctx.savepoint();
+            table.insert(3, ("alice".to_string(), 5));
+            table.remove(1);
+            ctx.rollback_to(savepoint);
+            ctx.release(savepoint);
+            assert_eq!(table.get_by_index::<u64>("LOCKED", 5), vec![1]);
+        });
+
+        db.query().run(|ctx| {
+            let table = ctx.get_table::<u8, (String, u64)>("NODES");
+            assert_eq!(
+                table.get_by_index::<String>("OWNER", "bob".to_string()),
+                vec![0, 1]
+            );
+            assert_eq!(
+                table.get_by_index::<String>("OWNER", "alice".to_string()),
+                vec![]
+            );
+            assert_eq!(table.get_by_index::<u64>("LOCKED", 5), vec![1]);
+            assert_eq!(table.get_by_index::<u64>("LOCKED", 7), vec![0]);
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_with_wrong_key_type_should_panic() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<u8, u64>("TABLE")
+            .with_index("TABLE", "INDEX", |value: &u64| *value)
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let table = ctx.get_table::<u8, u64>("TABLE");
+            table.get_by_index::<u32>("INDEX", 0);
+        });
+    }
+
+    #[test]
+    fn state_tree() {
+        let open = || {
+            AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+                .with_table::<u8, u64>("A")
+                .with_table::<u64, String>("B")
+                .with_index("B", "LEN", |value: &String| value.len())
+                .with_table::<u8, u64>("C")
+                .with_state_tree()
+                .without_state_tree("C")
+                .build()
+                .unwrap()
+        };
+        let mut db = open();
+        assert_eq!(db.state_root(), [0; 32]);
+
+        db.run(|ctx| {
+            let mut a = ctx.get_table::<u8, u64>("A");
+            for key in 0..100 {
+                a.insert(key, key as u64 * 7);
+            }
+            let mut b = ctx.get_table::<u64, String>("B");
+            b.insert(0, "zero".to_string());
+        });
+        let root = db.state_root();
+        assert_ne!(root, [0; 32]);
+
+        // The tables left out of the tree don't change the root.
+        db.run(|ctx| {
+            ctx.get_table::<u8, u64>("C").insert(0, 1);
+        });
+        assert_eq!(db.state_root(), root);
+
+        // The proofs show the values and the absence of the missing keys.
+        let key = BincodeSerde::serialize_key(&42u8);
+        let (value, proof) = db.get_with_proof::<u8, u64>("A", &42);
+        assert_eq!(value, Some(294));
+        assert!(proof.verify(&root, "A", &key, Some(&BincodeSerde::serialize(&294u64))));
+        assert!(!proof.verify(&root, "A", &key, Some(&BincodeSerde::serialize(&295u64))));
+        assert!(!proof.verify(&root, "A", &key, None));
+        assert!(!proof.verify(&root, "B", &key, Some(&BincodeSerde::serialize(&294u64))));
+
+        let key = BincodeSerde::serialize_key(&200u8);
+        let (value, proof) = db.get_with_proof::<u8, u64>("A", &200);
+        assert_eq!(value, None);
+        assert!(proof.verify(&root, "A", &key, None));
+        assert!(!proof.verify(&root, "A", &key, Some(&BincodeSerde::serialize(&0u64))));
+
+        // The root only depends on the entries.
+        db.run(|ctx| {
+            let mut a = ctx.get_table::<u8, u64>("A");
+            a.insert(200, 1);
+            a.remove(200);
+            a.insert(42, 0);
+        });
+        assert_ne!(db.state_root(), root);
+        assert!(!db.get_with_proof::<u8, u64>("A", &42).1.verify(
+            &root,
+            "A",
+            &BincodeSerde::serialize_key(&42u8),
+            Some(&BincodeSerde::serialize(&294u64))
+        ));
+
+        let mut other = open();
+        other.run(|ctx| {
+            let mut b = ctx.get_table::<u64, String>("B");
+            b.insert(0, "zero".to_string());
+            let mut a = ctx.get_table::<u8, u64>("A");
+            for key in (0..100).rev() {
+                a.insert(key, if key == 42 { 0 } else { key as u64 * 7 });
+            }
+        });
+        assert_eq!(other.state_root(), db.state_root());
+
+        db.run(|ctx| {
+            let mut a = ctx.get_table::<u8, u64>("A");
+            for key in 0..100 {
+                a.remove(key);
+            }
+            let mut b = ctx.get_table::<u64, String>("B");
+            b.remove(0);
+        });
+        assert_eq!(db.state_root(), [0; 32]);
+
+        // The nodes left behind by any sequence of changes give the same root as writing the
+        // remaining entries at once.
+        let mut entries = BTreeMap::new();
+        for round in 0..20u64 {
+            db.run(|ctx| {
+                let mut a = ctx.get_table::<u8, u64>("A");
+                for i in 0..50 {
+                    let key = ((round * 50 + i) * 37 % 256) as u8;
+                    if (round + i) % 3 == 0 {
+                        a.remove(key);
+                        entries.remove(&key);
+                    } else {
+                        a.insert(key, round);
+                        entries.insert(key, round);
+                    }
+                }
+            });
+        }
+        let mut other = open();
+        other.run(|ctx| {
+            let mut a = ctx.get_table::<u8, u64>("A");
+            for (key, value) in &entries {
+                a.insert(*key, *value);
+            }
+        });
+        assert_eq!(other.state_root(), db.state_root());
+    }
 }
@@ -29,7 +29,7 @@ impl<K, S: SerdeBackend> Iterator for KeyIterator<K, S>
     type Item = K;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|x| S::deserialize(&x))
+        self.inner.next().map(|x| S::deserialize_key(&x))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
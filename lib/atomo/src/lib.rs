@@ -8,8 +8,10 @@
 mod inner;
 mod key_iterator;
 mod keys;
+mod range_iterator;
 mod serder;
 mod snapshot;
+mod state_tree;
 pub mod storage;
 mod table;
 
@@ -18,6 +20,8 @@
 pub use builder::AtomoBuilder;
 pub use db::{Atomo, QueryPerm, UpdatePerm};
 pub use key_iterator::KeyIterator;
+pub use range_iterator::RangeIterator;
 pub use serder::{BincodeSerde, SerdeBackend};
-pub use storage::{InMemoryStorage, StorageBackend, StorageBackendConstructor};
-pub use table::{ResolvedTableReference, TableRef, TableSelector};
+pub use state_tree::{state_key, StateProof};
+pub use storage::{InMemoryStorage, StorageBackend, StorageBackendConstructor, StorageIterator};
+pub use table::{ResolvedTableReference, Savepoint, TableRef, TableSelector};
@@ -0,0 +1,70 @@
+use std::cmp::Ordering;
+use std::collections::btree_map;
+use std::iter::Peekable;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+
+use crate::batch::BoxedVec;
+use crate::storage::StorageIterator;
+use crate::{DefaultSerdeBackend, SerdeBackend};
+
+/// The changes that are not part of the persisted data yet, [`None`] means the key was removed.
+pub(crate) type PendingChanges = btree_map::IntoIter<BoxedVec, Option<BoxedVec>>;
+
+/// An iterator over the key-value pairs in a range of the table, ordered by the serialized keys.
+///
+/// The entries are read lazily from the persistence layer and merged with the changes that
+/// happened since the version of the data the iterator was created from.
+pub struct RangeIterator<'a, K, V, S: SerdeBackend = DefaultSerdeBackend> {
+    persisted: Peekable<StorageIterator<'a>>,
+    changes: Peekable<PendingChanges>,
+    kv: PhantomData<(K, V, S)>,
+}
+
+impl<'a, K, V, S: SerdeBackend> RangeIterator<'a, K, V, S> {
+    pub(crate) fn new(persisted: StorageIterator<'a>, changes: PendingChanges) -> Self {
+        Self {
+            persisted: persisted.peekable(),
+            changes: changes.peekable(),
+            kv: PhantomData,
+        }
+    }
+
+    pub(crate) fn next_raw(&mut self) -> Option<(BoxedVec, BoxedVec)> {
+        loop {
+            let ordering = match (self.persisted.peek(), self.changes.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((persisted, _)), Some((changed, _))) => persisted.cmp(changed),
+            };
+
+            match ordering {
+                Ordering::Less => return self.persisted.next(),
+                // The change shadows the persisted value.
+                Ordering::Equal => {
+                    self.persisted.next();
+                },
+                Ordering::Greater => {},
+            }
+
+            if let Some((key, Some(value))) = self.changes.next() {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+impl<'a, K, V, S: SerdeBackend> Iterator for RangeIterator<'a, K, V, S>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_raw()
+            .map(|(key, value)| (S::deserialize_key(&key), S::deserialize(&value)))
+    }
+}
@@ -1,3 +1,4 @@
+use bincode::Options;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
@@ -11,11 +12,38 @@ fn serialize<T>(value: &T) -> Vec<u8>
     fn deserialize<T>(slice: &[u8]) -> T
     where
         T: DeserializeOwned;
+
+    /// Serializes the key of a table. The entries of a table are ordered by their serialized
+    /// keys, so backends should use an encoding that preserves the order of the keys here.
+    fn serialize_key<T>(key: &T) -> Vec<u8>
+    where
+        T: Serialize,
+    {
+        Self::serialize(key)
+    }
+
+    fn deserialize_key<T>(slice: &[u8]) -> T
+    where
+        T: DeserializeOwned,
+    {
+        Self::deserialize(slice)
+    }
 }
 
 /// The bincode serializer from the [`bincode`] crate.
+///
+/// Keys are encoded with fixed size big-endian integers, so unsigned integers as well as arrays,
+/// tuples and structs of them are ordered the same way as their serialized form. Signed integers
+/// and variable length keys such as strings (which are prefixed with their length) are not.
 pub struct BincodeSerde;
 
+fn key_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_big_endian()
+        .allow_trailing_bytes()
+}
+
 impl SerdeBackend for BincodeSerde {
     fn serialize<T>(value: &T) -> Vec<u8>
     where
@@ -30,4 +58,18 @@ fn deserialize<T>(slice: &[u8]) -> T
     {
         bincode::deserialize(slice).unwrap()
     }
+
+    fn serialize_key<T>(key: &T) -> Vec<u8>
+    where
+        T: Serialize,
+    {
+        key_options().serialize(key).unwrap()
+    }
+
+    fn deserialize_key<T>(slice: &[u8]) -> T
+    where
+        T: DeserializeOwned,
+    {
+        key_options().deserialize(slice).unwrap()
+    }
 }
@@ -224,6 +224,18 @@ pub fn find<'a, F, R>(&'a self, predicate: F) -> Option<R>
     }
 }
 
+impl<T, U> Clone for Snapshot<T, U> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner)
+    }
+}
+
+// Safety: A snapshot only points to a node that is not retired as long as its counter is not zero,
+// and the shared state of the node is only accessed through atomics. So it can be moved to and
+// used from other threads the same way the [`SnapshotList`] is.
+unsafe impl<T: Send + Sync, U: Send + Sync> Send for Snapshot<T, U> {}
+unsafe impl<T: Send + Sync, U: Send + Sync> Sync for Snapshot<T, U> {}
+
 impl<T, U> Drop for Snapshot<T, U> {
     fn drop(&mut self) {
         debug_assert!(!self.inner.is_null());
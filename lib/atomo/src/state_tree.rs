@@ -0,0 +1,353 @@
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::batch::{BatchHashMap, Operation};
+use crate::db::TableId;
+use crate::storage::StorageBackend;
+
+/// The root of an empty tree and the hash of an empty subtree.
+const EMPTY_HASH: [u8; 32] = [0; 32];
+
+/// The domain separators of the leaf and the internal node hashes.
+const LEAF_DOMAIN: u8 = 0;
+const INTERNAL_DOMAIN: u8 = 1;
+
+/// Returns the path of a key of a table in the state tree, which is the hash of the hash of the
+/// table name followed by the serialized key. The path does not depend on the order in which the
+/// tables were added, so adding a table does not move the entries of the others.
+pub fn state_key(table: &str, key: &[u8]) -> [u8; 32] {
+    let mut hasher = fleek_blake3::Hasher::new();
+    hasher.update(fleek_blake3::hash(table.as_bytes()).as_bytes());
+    hasher.update(key);
+    *hasher.finalize().as_bytes()
+}
+
+/// The name of the table holding the nodes of the state tree.
+pub(crate) const STATE_TREE_TABLE: &str = "#state_tree";
+
+/// A compact sparse Merkle tree over the entries of the tables. Every entry is a leaf at the
+/// position of its [`state_key`], and a subtree holding a single leaf is replaced by the leaf,
+/// so the shape of the tree only depends on the entries it holds.
+///
+/// The nodes are stored in a table of the storage backend under their position in the tree and
+/// are only read when they are visited. The changed nodes are kept in memory until they are
+/// written into a batch using [`StateTree::write`].
+pub(crate) struct StateTree<'a, B: StorageBackend> {
+    storage: &'a B,
+    tid: TableId,
+    /// The nodes changed since the tree was opened, [`None`] marks a removed node.
+    changes: FxHashMap<Position, Option<Node>>,
+}
+
+/// The position of a node in the tree, which is the depth of the node followed by the first
+/// `depth` bits of the paths under it.
+type Position = [u8; 34];
+
+#[derive(Clone, Copy)]
+enum Node {
+    Leaf { path: [u8; 32], value: [u8; 32] },
+    Internal { left: [u8; 32], right: [u8; 32] },
+}
+
+/// The proof of the value of a key (or the absence of it) in the state tree with the given root.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateProof {
+    /// The root of the tree the proof was created from.
+    pub root: [u8; 32],
+    /// The hashes of the siblings on the path to the key, from the root down.
+    pub siblings: Vec<[u8; 32]>,
+    /// The path and the value hash of the leaf at the end of the path, if there is one. For a
+    /// missing key this can be the leaf of another key that shares the path.
+    pub leaf: Option<([u8; 32], [u8; 32])>,
+}
+
+impl<'a, B: StorageBackend> StateTree<'a, B> {
+    /// Opens the tree whose nodes are stored in the given table.
+    pub fn new(storage: &'a B, tid: TableId) -> Self {
+        Self {
+            storage,
+            tid,
+            changes: FxHashMap::default(),
+        }
+    }
+
+    /// Returns the root hash of the tree.
+    pub fn root(&self) -> [u8; 32] {
+        self.get(&position(0, &[0; 32]))
+            .map_or(EMPTY_HASH, |node| node.hash())
+    }
+
+    /// Sets the value of the leaf at the given path.
+    pub fn insert(&mut self, path: [u8; 32], value: &[u8]) {
+        let value = *fleek_blake3::hash(value).as_bytes();
+        self.insert_at(0, path, value);
+    }
+
+    /// Removes the leaf at the given path.
+    pub fn remove(&mut self, path: [u8; 32]) {
+        self.remove_at(0, path);
+    }
+
+    /// Returns the proof for the leaf at the given path.
+    pub fn prove(&self, path: [u8; 32]) -> StateProof {
+        let mut siblings = Vec::new();
+        let leaf = loop {
+            match self.get(&position(siblings.len(), &path)) {
+                None => break None,
+                Some(Node::Leaf { path, value }) => break Some((path, value)),
+                Some(Node::Internal { left, right }) => {
+                    if bit(&path, siblings.len()) {
+                        siblings.push(left);
+                    } else {
+                        siblings.push(right);
+                    }
+                },
+            }
+        };
+
+        StateProof {
+            root: self.root(),
+            siblings,
+            leaf,
+        }
+    }
+
+    /// Writes the changed nodes into the batch of the table holding the nodes.
+    pub fn write(self, batch: &mut BatchHashMap) {
+        for (position, node) in self.changes {
+            let operation = match node {
+                Some(node) => Operation::Insert(node.encode().into()),
+                None => Operation::Remove,
+            };
+            batch.insert(position.into(), operation);
+        }
+    }
+
+    fn get(&self, position: &Position) -> Option<Node> {
+        match self.changes.get(position) {
+            Some(node) => *node,
+            None => self
+                .storage
+                .get(self.tid, position)
+                .map(|bytes| Node::decode(&bytes)),
+        }
+    }
+
+    fn set(&mut self, position: Position, node: Option<Node>) {
+        self.changes.insert(position, node);
+    }
+
+    /// Inserts the leaf into the subtree at the given depth and returns the new hash of the
+    /// subtree.
+    fn insert_at(&mut self, depth: usize, path: [u8; 32], value: [u8; 32]) -> [u8; 32] {
+        let at = position(depth, &path);
+        match self.get(&at) {
+            Some(Node::Internal { left, right }) => {
+                let child = self.insert_at(depth + 1, path, value);
+                let node = if bit(&path, depth) {
+                    Node::Internal { left, right: child }
+                } else {
+                    Node::Internal { left: child, right }
+                };
+                self.set(at, Some(node));
+                node.hash()
+            },
+            Some(
+                leaf @ Node::Leaf {
+                    path: leaf_path, ..
+                },
+            ) if leaf_path != path => {
+                // Push the existing leaf one level down and insert the new one next to it, this
+                // repeats until the paths diverge.
+                self.set(position(depth + 1, &leaf_path), Some(leaf));
+                let node = if bit(&leaf_path, depth) {
+                    Node::Internal {
+                        left: EMPTY_HASH,
+                        right: leaf.hash(),
+                    }
+                } else {
+                    Node::Internal {
+                        left: leaf.hash(),
+                        right: EMPTY_HASH,
+                    }
+                };
+                self.set(at, Some(node));
+                self.insert_at(depth, path, value)
+            },
+            _ => {
+                let leaf = Node::Leaf { path, value };
+                self.set(at, Some(leaf));
+                leaf.hash()
+            },
+        }
+    }
+
+    /// Removes the leaf from the subtree at the given depth and returns the new root node of the
+    /// subtree.
+    fn remove_at(&mut self, depth: usize, path: [u8; 32]) -> Option<Node> {
+        let at = position(depth, &path);
+        match self.get(&at) {
+            Some(Node::Leaf {
+                path: leaf_path, ..
+            }) if leaf_path == path => {
+                self.set(at, None);
+                None
+            },
+            Some(node @ Node::Internal { left, right }) => {
+                let child = self.remove_at(depth + 1, path);
+                let child_hash = child.map_or(EMPTY_HASH, |child| child.hash());
+                let (old_hash, sibling) = if bit(&path, depth) {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+                if child_hash == old_hash {
+                    return Some(node);
+                }
+
+                // A subtree that is left with a single leaf is replaced by the leaf.
+                let leaf_at = match child {
+                    None if sibling == EMPTY_HASH => {
+                        self.set(at, None);
+                        return None;
+                    },
+                    None => {
+                        let mut sibling_path = path;
+                        sibling_path[depth / 8] ^= 1 << (7 - depth % 8);
+                        Some(position(depth + 1, &sibling_path))
+                    },
+                    Some(Node::Leaf { .. }) if sibling == EMPTY_HASH => {
+                        Some(position(depth + 1, &path))
+                    },
+                    _ => None,
+                };
+                if let Some(leaf_at) = leaf_at {
+                    if let Some(leaf @ Node::Leaf { .. }) = self.get(&leaf_at) {
+                        self.set(leaf_at, None);
+                        self.set(at, Some(leaf));
+                        return Some(leaf);
+                    }
+                }
+
+                let node = if bit(&path, depth) {
+                    Node::Internal {
+                        left,
+                        right: child_hash,
+                    }
+                } else {
+                    Node::Internal {
+                        left: child_hash,
+                        right,
+                    }
+                };
+                self.set(at, Some(node));
+                Some(node)
+            },
+            node => node,
+        }
+    }
+}
+
+impl Node {
+    fn encode(&self) -> [u8; 65] {
+        let (domain, a, b) = match self {
+            Node::Leaf { path, value } => (LEAF_DOMAIN, path, value),
+            Node::Internal { left, right } => (INTERNAL_DOMAIN, left, right),
+        };
+        let mut bytes = [0; 65];
+        bytes[0] = domain;
+        bytes[1..33].copy_from_slice(a);
+        bytes[33..].copy_from_slice(b);
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        let a = bytes[1..33].try_into().unwrap();
+        let b = bytes[33..65].try_into().unwrap();
+        match bytes[0] {
+            LEAF_DOMAIN => Node::Leaf { path: a, value: b },
+            _ => Node::Internal { left: a, right: b },
+        }
+    }
+
+    /// Returns the hash of the node, which is the hash of its encoding.
+    fn hash(&self) -> [u8; 32] {
+        *fleek_blake3::hash(&self.encode()).as_bytes()
+    }
+}
+
+impl StateProof {
+    /// Returns `true` if the proof shows that the key of the table has the given serialized
+    /// value in the state with the given root, or that the key is missing if the value is
+    /// [`None`]. The key is serialized with [`crate::SerdeBackend::serialize_key`].
+    pub fn verify(&self, root: &[u8; 32], table: &str, key: &[u8], value: Option<&[u8]>) -> bool {
+        if self.root != *root || self.siblings.len() > 256 {
+            return false;
+        }
+
+        let path = state_key(table, key);
+        let mut hash = match (value, self.leaf) {
+            (Some(value), Some((leaf_path, leaf_value))) => {
+                if leaf_path != path || leaf_value != *fleek_blake3::hash(value).as_bytes() {
+                    return false;
+                }
+                Node::Leaf {
+                    path: leaf_path,
+                    value: leaf_value,
+                }
+                .hash()
+            },
+            (Some(_), None) => return false,
+            (None, None) => EMPTY_HASH,
+            // The key is missing if the path ends at the leaf of another key.
+            (None, Some((leaf_path, leaf_value))) => {
+                if leaf_path == path
+                    || (0..self.siblings.len())
+                        .any(|depth| bit(&leaf_path, depth) != bit(&path, depth))
+                {
+                    return false;
+                }
+                Node::Leaf {
+                    path: leaf_path,
+                    value: leaf_value,
+                }
+                .hash()
+            },
+        };
+
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            let node = if bit(&path, depth) {
+                Node::Internal {
+                    left: *sibling,
+                    right: hash,
+                }
+            } else {
+                Node::Internal {
+                    left: hash,
+                    right: *sibling,
+                }
+            };
+            hash = node.hash();
+        }
+
+        hash == *root
+    }
+}
+
+/// Returns the position of the node at the given depth on the path.
+fn position(depth: usize, path: &[u8; 32]) -> Position {
+    let mut position = [0; 34];
+    position[..2].copy_from_slice(&(depth as u16).to_be_bytes());
+    position[2..2 + depth / 8].copy_from_slice(&path[..depth / 8]);
+    let bits = depth % 8;
+    if bits > 0 {
+        position[2 + depth / 8] = path[depth / 8] & !(0xff >> bits);
+    }
+    position
+}
+
+/// Returns the bit of the path at the given depth, `true` means the right child.
+#[inline(always)]
+fn bit(path: &[u8; 32], depth: usize) -> bool {
+    (path[depth / 8] >> (7 - depth % 8)) & 1 == 1
+}
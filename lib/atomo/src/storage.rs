@@ -1,9 +1,12 @@
 use std::fmt::Debug;
-
-use dashmap::DashMap;
+use std::ops::Bound;
+use std::sync::{RwLock, RwLockReadGuard};
 
 use crate::batch::{BoxedVec, Operation, VerticalBatch};
 
+/// An iterator over raw key-value pairs of a table in the ascending order of the keys.
+pub type StorageIterator<'a> = Box<dyn Iterator<Item = (BoxedVec, BoxedVec)> + 'a>;
+
 pub trait StorageBackendConstructor {
     /// The storage API.
     type Storage: StorageBackend;
@@ -28,6 +31,11 @@ pub trait StorageBackend {
     /// Return all of the keys from a table.
     fn keys(&self, tid: u8) -> Vec<BoxedVec>;
 
+    /// Return an iterator over the key-value pairs of a table whose keys are within the given
+    /// bounds, ordered by the keys. The iterator must not observe the batches committed after
+    /// it was created.
+    fn range<'a>(&'a self, tid: u8, from: Bound<&[u8]>, to: Bound<&[u8]>) -> StorageIterator<'a>;
+
     /// Get the value associated with the given key from the provided table.
     fn get(&self, tid: u8, key: &[u8]) -> Option<Vec<u8>>;
 
@@ -35,8 +43,10 @@ pub trait StorageBackend {
     fn contains(&self, tid: u8, key: &[u8]) -> bool;
 }
 
+type InMemoryTable = im::OrdMap<BoxedVec, BoxedVec>;
+
 #[derive(Default)]
-pub struct InMemoryStorage(Vec<DashMap<BoxedVec, BoxedVec, fxhash::FxBuildHasher>>);
+pub struct InMemoryStorage(Vec<RwLock<InMemoryTable>>);
 
 // For the in memory database the constructor can be as same as the actual object.
 impl StorageBackendConstructor for InMemoryStorage {
@@ -45,7 +55,7 @@ impl StorageBackendConstructor for InMemoryStorage {
 
     #[inline]
     fn open_table(&mut self, _name: String) {
-        self.0.push(DashMap::default())
+        self.0.push(RwLock::default())
     }
 
     fn build(self) -> Result<Self::Storage, Self::Error> {
@@ -57,6 +67,7 @@ impl StorageBackend for InMemoryStorage {
     #[inline]
     fn commit(&self, batch: VerticalBatch) {
         for (table, batch) in self.0.iter().zip(batch.into_raw().into_iter()) {
+            let mut table = table.write().expect("Could not acquire the lock");
             for (key, operation) in batch.into_iter() {
                 match operation {
                     Operation::Remove => {
@@ -72,20 +83,76 @@ fn commit(&self, batch: VerticalBatch) {
 
     #[inline]
     fn keys(&self, tid: u8) -> Vec<BoxedVec> {
-        let mut collection = Vec::new();
-        for item in self.0[tid as usize].iter() {
-            collection.push(item.key().clone());
-        }
-        collection
+        self.table(tid).keys().cloned().collect()
+    }
+
+    #[inline]
+    fn range<'a>(&'a self, tid: u8, from: Bound<&[u8]>, to: Bound<&[u8]>) -> StorageIterator<'a> {
+        // Cloning the table is cheap and gives the iterator a view that is not affected by
+        // later commits.
+        Box::new(InMemoryRange {
+            table: self.table(tid).clone(),
+            from: to_owned_bound(from),
+            to: to_owned_bound(to),
+        })
     }
 
     #[inline]
     fn get(&self, tid: u8, key: &[u8]) -> Option<Vec<u8>> {
-        self.0[tid as usize].get(key).map(|v| v.to_vec())
+        self.table(tid).get(key).map(|v| v.to_vec())
     }
 
     #[inline]
     fn contains(&self, tid: u8, key: &[u8]) -> bool {
-        self.0[tid as usize].contains_key(key)
+        self.table(tid).contains_key(key)
+    }
+}
+
+impl InMemoryStorage {
+    #[inline]
+    fn table(&self, tid: u8) -> RwLockReadGuard<'_, InMemoryTable> {
+        self.0[tid as usize]
+            .read()
+            .expect("Could not acquire the lock")
+    }
+}
+
+/// A range over a version of an in memory table, every step looks up the first key after the
+/// previously returned one.
+struct InMemoryRange {
+    table: InMemoryTable,
+    from: Bound<BoxedVec>,
+    to: Bound<BoxedVec>,
+}
+
+impl Iterator for InMemoryRange {
+    type Item = (BoxedVec, BoxedVec);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self
+            .table
+            .range::<_, [u8]>((as_slice_bound(&self.from), as_slice_bound(&self.to)))
+            .next()
+            .map(|(key, value)| (key.clone(), value.clone()))?;
+        self.from = Bound::Excluded(key.clone());
+        Some((key, value))
+    }
+}
+
+/// Converts a bound on a borrowed key to a bound on an owned key.
+fn to_owned_bound(bound: Bound<&[u8]>) -> Bound<BoxedVec> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.into()),
+        Bound::Excluded(key) => Bound::Excluded(key.into()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Converts a bound on an owned key to a bound on a borrowed key.
+pub(crate) fn as_slice_bound(bound: &Bound<BoxedVec>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key),
+        Bound::Excluded(key) => Bound::Excluded(key),
+        Bound::Unbounded => Bound::Unbounded,
     }
 }
@@ -1,28 +1,52 @@
 use std::any::{Any, TypeId};
 use std::borrow::Borrow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 use std::sync::Arc;
 
 use fxhash::FxHashSet;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::batch::{BatchReference, Operation, VerticalBatch};
+use crate::batch::{BatchHashMap, BatchReference, BoxedVec, Operation, VerticalBatch};
 use crate::db::TableId;
 use crate::inner::AtomoInner;
 use crate::keys::VerticalKeys;
 use crate::serder::SerdeBackend;
 use crate::snapshot::Snapshot;
-use crate::{KeyIterator, StorageBackend};
+use crate::storage::as_slice_bound;
+use crate::{KeyIterator, RangeIterator, StorageBackend};
 
 pub struct TableMeta {
-    pub _name: String,
+    pub name: String,
     pub k_id: TypeId,
     pub v_id: TypeId,
+    /// The secondary indexes of the table.
+    pub indexes: Vec<IndexMeta>,
+    /// Whether the entries of the table are part of the state tree, if it is enabled.
+    pub in_state_tree: bool,
 }
 
+/// A secondary index of a table. The entries of an index are kept in a table of their own, keyed
+/// by the index key of a value followed by the key of the value, see [`index_entry`].
+pub struct IndexMeta {
+    pub name: String,
+    /// The table holding the entries of the index.
+    pub tid: TableId,
+    pub i_id: TypeId,
+    pub extractor: IndexExtractor,
+}
+
+/// Returns the serialized index key of a serialized value.
+pub type IndexExtractor = Box<dyn Fn(&[u8]) -> Vec<u8> + Send + Sync>;
+
+/// The key and value type of the internal tables, such as the tables holding the entries of an
+/// index, since the type is private these tables can not be resolved outside of this crate.
+struct InternalEntry;
+
 /// A resolved table reference can be used to cache the lookup of a table by its string name
 /// and the type validations and can be used to speed up the [`TableSelector::get_table`] function.
 ///
@@ -62,8 +86,21 @@ pub struct TableSelector<B: StorageBackend, S: SerdeBackend> {
     batch: VerticalBatch,
     /// The new version of the keys.
     keys: RefCell<VerticalKeys>,
+    /// The active savepoints from the oldest to the newest, with the length of the undo log and
+    /// the version of the keys when they were created.
+    savepoints: RefCell<Vec<(Savepoint, usize, VerticalKeys)>>,
+    /// The id of the next savepoint.
+    next_savepoint: Cell<usize>,
+    /// The writes made since the oldest active savepoint, as the table, the key and the change
+    /// the batch had for the key before the write.
+    undo_log: RefCell<Vec<(TableId, BoxedVec, Option<Operation>)>>,
 }
 
+/// A mark of the state of a [`TableSelector`] in the middle of a run, the writes made after it can
+/// be undone using [`TableSelector::rollback_to`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Savepoint(usize);
+
 /// A reference to a table inside an execution context (i.e [`TableSelector`]). A table reference
 /// can be used as a reference to a table to operate on it.
 pub struct TableRef<
@@ -81,11 +118,52 @@ pub struct TableRef<
 
 impl TableMeta {
     #[inline(always)]
-    pub fn new<K: Any, V: Any>(_name: String) -> Self {
+    pub fn new<K: Any, V: Any>(name: String) -> Self {
         let k_id = TypeId::of::<K>();
         let v_id = TypeId::of::<V>();
-        Self { _name, k_id, v_id }
+        Self {
+            name,
+            k_id,
+            v_id,
+            indexes: Vec::new(),
+            in_state_tree: true,
+        }
+    }
+
+    /// Create the meta for an internal table, such as the table holding the entries of an index
+    /// or the nodes of the state tree. The entries of internal tables are not part of the state
+    /// tree.
+    #[inline(always)]
+    pub fn internal(name: String) -> Self {
+        Self {
+            in_state_tree: false,
+            ..Self::new::<InternalEntry, InternalEntry>(name)
+        }
+    }
+}
+
+/// Returns the key of an index entry for the given serialized index key and table key. The index
+/// key is prefixed with its length so the entries of one index key are never mixed with another.
+pub(crate) fn index_entry(index_key: &[u8], key: &[u8]) -> BoxedVec {
+    let mut entry = Vec::with_capacity(4 + index_key.len() + key.len());
+    entry.extend_from_slice(&(index_key.len() as u32).to_be_bytes());
+    entry.extend_from_slice(index_key);
+    entry.extend_from_slice(key);
+    entry.into_boxed_slice()
+}
+
+/// Returns the bound after all of the keys starting with the given prefix, that is the prefix cut
+/// after its last byte below `u8::MAX` with that byte incremented. Without such a byte it is
+/// unbounded.
+fn prefix_end(prefix: &[u8]) -> Bound<BoxedVec> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return Bound::Excluded(end.into_boxed_slice());
+        }
     }
+    Bound::Unbounded
 }
 
 // When a table ref is dropped make it available to be claimed again.
@@ -103,9 +181,18 @@ impl<B: StorageBackend, S: SerdeBackend> TableSelector<B, S> {
     /// Create a new table selector for the head of an Atomo instance.
     #[inline]
     pub fn new(atomo: Arc<AtomoInner<B, S>>) -> Self {
+        let snapshot = atomo.snapshot_list.current();
+        Self::with_snapshot(atomo, snapshot)
+    }
+
+    /// Create a new table selector that views the data as of the given snapshot.
+    #[inline]
+    pub(crate) fn with_snapshot(
+        atomo: Arc<AtomoInner<B, S>>,
+        snapshot: Snapshot<VerticalBatch, VerticalKeys>,
+    ) -> Self {
         let num_tables = atomo.tables.len();
         let batch = VerticalBatch::new(num_tables);
-        let snapshot = atomo.snapshot_list.current();
         let keys = snapshot.get_metadata().clone();
 
         Self {
@@ -114,6 +201,9 @@ pub fn new(atomo: Arc<AtomoInner<B, S>>) -> Self {
             selected: RefCell::new(FxHashSet::default()),
             batch,
             keys: RefCell::new(keys),
+            savepoints: RefCell::new(Vec::new()),
+            next_savepoint: Cell::new(0),
+            undo_log: RefCell::new(Vec::new()),
         }
     }
 
@@ -134,6 +224,77 @@ pub fn get_table<K, V>(&self, name: impl AsRef<str>) -> TableRef<K, V, B, S>
     {
         self.atomo.resolve::<K, V>(name).get(self)
     }
+
+    /// Creates a savepoint at the current state of the tables. Savepoints can be nested, and
+    /// the ones that are still active when the run ends keep their writes.
+    pub fn savepoint(&self) -> Savepoint {
+        let savepoint = Savepoint(self.next_savepoint.get());
+        self.next_savepoint.set(savepoint.0 + 1);
+        self.savepoints.borrow_mut().push((
+            savepoint,
+            self.undo_log.borrow().len(),
+            self.keys.borrow().clone(),
+        ));
+        savepoint
+    }
+
+    /// Undoes all of the writes made since the savepoint was created. The savepoint stays active
+    /// while the ones created after it are dropped.
+    ///
+    /// # Panics
+    ///
+    /// If the savepoint is not active.
+    pub fn rollback_to(&self, savepoint: Savepoint) {
+        let mut savepoints = self.savepoints.borrow_mut();
+        let position = find_savepoint(&savepoints, savepoint);
+        savepoints.truncate(position + 1);
+        let (_, undo_len, keys) = &savepoints[position];
+
+        for (tid, key, previous) in self.undo_log.borrow_mut().drain(*undo_len..).rev() {
+            // Safety: A table reference only accesses its slot of the batch for the duration of
+            // one of its own calls, and the selector can not be shared with another thread, so
+            // no other reference to the slot is used while we restore it.
+            let mut batch = unsafe { self.batch.claim(tid as usize) };
+            match previous {
+                Some(operation) => batch.insert(key, operation),
+                None => batch.remove(&key),
+            };
+        }
+        *self.keys.borrow_mut() = keys.clone();
+    }
+
+    /// Keeps the writes made since the savepoint was created and drops the savepoint together
+    /// with the ones created after it.
+    ///
+    /// # Panics
+    ///
+    /// If the savepoint is not active.
+    pub fn release(&self, savepoint: Savepoint) {
+        let mut savepoints = self.savepoints.borrow_mut();
+        let position = find_savepoint(&savepoints, savepoint);
+        savepoints.truncate(position);
+        if savepoints.is_empty() {
+            self.undo_log.borrow_mut().clear();
+        }
+    }
+
+    /// Records a write to the batch so it can be undone, if there is an active savepoint.
+    #[inline]
+    fn write(&self, batch: &mut BatchHashMap, tid: TableId, key: BoxedVec, operation: Operation) {
+        if self.savepoints.borrow().is_empty() {
+            batch.insert(key, operation);
+        } else {
+            let previous = batch.insert(key.clone(), operation);
+            self.undo_log.borrow_mut().push((tid, key, previous));
+        }
+    }
+}
+
+fn find_savepoint(savepoints: &[(Savepoint, usize, VerticalKeys)], savepoint: Savepoint) -> usize {
+    savepoints
+        .iter()
+        .position(|(active, _, _)| *active == savepoint)
+        .expect("Savepoint is not active.")
 }
 
 impl<K, V> ResolvedTableReference<K, V> {
@@ -145,6 +306,12 @@ pub(crate) fn new(atomo_id: usize, index: TableId) -> Self {
         }
     }
 
+    /// Returns the id of the table.
+    #[inline(always)]
+    pub(crate) fn tid(&self) -> TableId {
+        self.index
+    }
+
     /// Returns the table reference for this table.
     ///
     /// # Panics
@@ -194,52 +361,164 @@ impl<'selector, K, V, B: StorageBackend, S: SerdeBackend> TableRef<'selector, K,
 {
     /// Insert a new `key` and `value` pair into the table.
     pub fn insert(&mut self, key: impl Borrow<K>, value: impl Borrow<V>) {
-        let k = S::serialize(key.borrow()).into_boxed_slice();
+        let k = S::serialize_key(key.borrow()).into_boxed_slice();
         let v = S::serialize(value.borrow()).into_boxed_slice();
+        self.update_indexes(&k, Some(&v));
         self.selector
             .keys
             .borrow_mut()
             .update(self.tid, |collection| {
                 collection.insert(k.clone());
             });
-        self.batch.as_mut().insert(k, Operation::Insert(v));
+        self.selector
+            .write(self.batch.as_mut(), self.tid, k, Operation::Insert(v));
     }
 
     /// Remove the given key from the table.
     pub fn remove(&mut self, key: impl Borrow<K>) {
-        let k = S::serialize(key.borrow()).into_boxed_slice();
+        let k = S::serialize_key(key.borrow()).into_boxed_slice();
+        self.update_indexes(&k, None);
         self.selector
             .keys
             .borrow_mut()
             .update(self.tid, |collection| {
                 collection.remove(&k);
             });
-        self.batch.as_mut().insert(k, Operation::Remove);
+        self.selector
+            .write(self.batch.as_mut(), self.tid, k, Operation::Remove);
     }
 
     /// Returns the value associated with the provided key. If the key doesn't exits in the table
     /// [`None`] is returned.
     pub fn get(&self, key: impl Borrow<K>) -> Option<V> {
-        let k = S::serialize(key.borrow()).into_boxed_slice();
-        // We get the underlying value before checking snapshots to fix a race condition where a
-        // value is updated after checking the snapshot and before we grab the data
-        // todo: optimize this
-        let tmp = self.selector.atomo.get::<V>(self.tid, &k);
+        let k = S::serialize_key(key.borrow()).into_boxed_slice();
         match self.batch.get(&k) {
             Some(Operation::Insert(value)) => return Some(S::deserialize(value)),
             Some(Operation::Remove) => return None,
             _ => {},
         }
 
+        self.get_committed_raw(&k)
+            .map(|value| S::deserialize(&value))
+    }
+
+    /// Returns the value associated with the provided key ignoring the changes made in the
+    /// current run. That is the value as of the version of the data this run started from.
+    pub fn get_committed(&self, key: impl Borrow<K>) -> Option<V> {
+        let k = S::serialize_key(key.borrow()).into_boxed_slice();
+        self.get_committed_raw(&k)
+            .map(|value| S::deserialize(&value))
+    }
+
+    /// Returns the changes made to the table in the current run as pairs of a key and its new
+    /// value, where [`None`] means the key was removed. Keys that were written with the value
+    /// they already had are not included.
+    pub fn changes(&self) -> Vec<(K, Option<V>)> {
+        self.batch
+            .iter()
+            .filter_map(|(k, operation)| {
+                let old_value = self.get_committed_raw(k);
+                match (operation, old_value) {
+                    (Operation::Remove, None) => None,
+                    (Operation::Insert(value), Some(old_value)) if value[..] == old_value[..] => {
+                        None
+                    },
+                    (Operation::Remove, Some(_)) => Some((S::deserialize_key(k), None)),
+                    (Operation::Insert(value), _) => {
+                        Some((S::deserialize_key(k), Some(S::deserialize(value))))
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the keys of the entries whose value has the given key in the index with the
+    /// provided name, including the changes made in the current run. The keys are ordered by
+    /// their serialized form.
+    ///
+    /// # Panics
+    ///
+    /// If the table does not have such an index or the index key type is not `I`. See the
+    /// documentation for [`crate::AtomoBuilder::with_index`] for more information.
+    pub fn get_by_index<I: Serialize + Any>(&self, index: &str, key: impl Borrow<I>) -> Vec<K> {
+        let meta = self.selector.atomo.tables[self.tid as usize]
+            .indexes
+            .iter()
+            .find(|meta| meta.name == index)
+            .unwrap_or_else(|| panic!("Index {index} not found."));
+
+        assert_eq!(
+            meta.i_id,
+            TypeId::of::<I>(),
+            "Could not resolve index '{index}' with key type '{}'.",
+            std::any::type_name::<I>()
+        );
+
+        let from = index_entry(&S::serialize_key(key.borrow()), &[]);
+        let to = prefix_end(&from);
+        // Safety: The index tables can not be resolved outside of this crate so the slot is never
+        // claimed by a table reference, and it is only read for the duration of this call.
+        let batch = unsafe { self.selector.batch.claim(meta.tid as usize) };
+        let mut entries: RangeIterator<(), (), S> =
+            self.raw_range(meta.tid, &batch, Bound::Included(from.clone()), to);
+        std::iter::from_fn(|| entries.next_raw())
+            .map(|(entry, _)| S::deserialize_key(&entry[from.len()..]))
+            .collect()
+    }
+
+    /// Keeps the entries of the indexes of the table consistent with writing the given value to
+    /// the key, where [`None`] means the key is removed. Must be called before the write.
+    fn update_indexes(&self, k: &[u8], value: Option<&[u8]>) {
+        let indexes = &self.selector.atomo.tables[self.tid as usize].indexes;
+        if indexes.is_empty() {
+            return;
+        }
+
+        let old_value = match self.batch.get(k) {
+            Some(Operation::Insert(value)) => Some(value.to_vec()),
+            Some(Operation::Remove) => None,
+            None => self.get_committed_raw(k),
+        };
+
+        for index in indexes {
+            let old_entry = old_value
+                .as_ref()
+                .map(|value| index_entry(&(index.extractor)(value), k));
+            let new_entry = value.map(|value| index_entry(&(index.extractor)(value), k));
+            if old_entry == new_entry {
+                continue;
+            }
+
+            // Safety: The index tables can not be resolved outside of this crate so the slot is
+            // never claimed by a table reference, and it is only used for the duration of this
+            // call.
+            let mut batch = unsafe { self.selector.batch.claim(index.tid as usize) };
+            if let Some(entry) = old_entry {
+                self.selector
+                    .write(batch.as_mut(), index.tid, entry, Operation::Remove);
+            }
+            if let Some(entry) = new_entry {
+                self.selector.write(
+                    batch.as_mut(),
+                    index.tid,
+                    entry,
+                    Operation::Insert(BoxedVec::default()),
+                );
+            }
+        }
+    }
+
+    fn get_committed_raw(&self, k: &[u8]) -> Option<Vec<u8>> {
+        // We get the underlying value before checking snapshots to fix a race condition where a
+        // value is updated after checking the snapshot and before we grab the data
+        // todo: optimize this
+        let tmp = self.selector.atomo.get_raw(self.tid, k);
+
         let index = self.tid as usize;
-        if let Some(operation) = self
-            .selector
-            .snapshot
-            .find(|batch| batch.get(index).get(&k))
-        {
+        if let Some(operation) = self.selector.snapshot.find(|batch| batch.get(index).get(k)) {
             return match operation {
                 Operation::Remove => None,
-                Operation::Insert(value) => Some(S::deserialize(value)),
+                Operation::Insert(value) => Some(value.to_vec()),
             };
         }
 
@@ -248,7 +527,7 @@ pub fn get(&self, key: impl Borrow<K>) -> Option<V> {
 
     /// Returns `true` if the key exists in the table.
     pub fn contains_key(&self, key: impl Borrow<K>) -> bool {
-        let k = S::serialize(key.borrow()).into_boxed_slice();
+        let k = S::serialize_key(key.borrow()).into_boxed_slice();
 
         {
             let keys_ref = self.selector.keys.borrow();
@@ -296,4 +575,77 @@ pub fn keys(&self) -> KeyIterator<K> {
 
         KeyIterator::new(keys)
     }
+
+    /// Returns an iterator over the key-value pairs whose keys are within the given range,
+    /// including the changes made in the current run.
+    ///
+    /// The entries are ordered by their serialized keys and the range is applied to the
+    /// serialized bounds, see [`SerdeBackend::serialize_key`] for the keys whose order this
+    /// preserves. The iterator reflects the table as of when it was created.
+    pub fn range(&self, range: impl RangeBounds<K>) -> RangeIterator<'selector, K, V, S> {
+        let serialize = |bound: Bound<&K>| match bound {
+            Bound::Included(key) => Bound::Included(S::serialize_key(key).into_boxed_slice()),
+            Bound::Excluded(key) => Bound::Excluded(S::serialize_key(key).into_boxed_slice()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        self.raw_range(
+            self.tid,
+            &self.batch,
+            serialize(range.start_bound()),
+            serialize(range.end_bound()),
+        )
+    }
+
+    /// Returns an iterator over the key-value pairs whose serialized keys start with the
+    /// serialized `prefix`, including the changes made in the current run. For tuple keys this
+    /// can be used to iterate all of the entries that share their leading elements.
+    ///
+    /// The iterator reflects the table as of when it was created.
+    pub fn prefix<P: Serialize>(&self, prefix: &P) -> RangeIterator<'selector, K, V, S> {
+        let from = S::serialize_key(prefix).into_boxed_slice();
+        let to = prefix_end(&from);
+        self.raw_range(self.tid, &self.batch, Bound::Included(from), to)
+    }
+
+    fn raw_range<T, U>(
+        &self,
+        tid: TableId,
+        batch: &BatchHashMap,
+        from: Bound<BoxedVec>,
+        to: Bound<BoxedVec>,
+    ) -> RangeIterator<'selector, T, U, S> {
+        let bounds = (as_slice_bound(&from), as_slice_bound(&to));
+
+        // The persisted data is read first, every commit it can observe has already pushed its
+        // inverse to the snapshot list so the changes below always correct it to our version.
+        let persisted = self
+            .selector
+            .atomo
+            .persistence
+            .range(tid, bounds.0, bounds.1);
+
+        // For every key the oldest inverse after our snapshot holds the value as of our version,
+        // and the changes of the current run take precedence over all of them.
+        let index = tid as usize;
+        let changes = RefCell::new(BTreeMap::new());
+        self.selector.snapshot.find(|batch| {
+            let mut changes = changes.borrow_mut();
+            for (key, operation) in batch.get(index).iter() {
+                if bounds.contains(&key[..]) {
+                    changes
+                        .entry(key.clone())
+                        .or_insert_with(|| operation.value());
+                }
+            }
+            None::<()>
+        });
+        let mut changes = changes.into_inner();
+        for (key, operation) in batch.iter() {
+            if bounds.contains(&key[..]) {
+                changes.insert(key.clone(), operation.value());
+            }
+        }
+
+        RangeIterator::new(persisted, changes.into_iter())
+    }
 }
@@ -67,7 +67,7 @@ pub struct OnEventResponseArgs {
         /// The public key of the user that we want their balance.
         pk: ClientPublicKey,
         =>
-        /// The balance of the user.
+        /// The stables held in escrow for the user, in the smallest unit (6 decimals).
         balance: u128,
     },
     /// Fetch a content via a blake3 digest.